@@ -0,0 +1,5 @@
+pub mod problems;
+pub mod puzzles;
+pub mod review;
+pub mod test_suite;
+pub mod training;
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use anyhow::Error;
+
+use crate::formats::fen::Position;
+use crate::formats::san;
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::board::square::Square;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::Piece;
+use crate::utils;
+
+/// What a problem asks for, in a number of moves of the side to move.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stipulation {
+    /// The side to move mates against any defence, e.g. "#2".
+    Mate(u8),
+    /// Both sides cooperate so that the side to move is mated, e.g. "h#2".
+    Helpmate(u8),
+    /// The side to move forces the opponent to mate it, e.g. "s#2".
+    Selfmate(u8),
+}
+
+impl Stipulation {
+    pub fn parse(text: &str) -> Result<Stipulation, Error> {
+        let invalid = || Error::msg(format!("Invalid stipulation {}, e.g. #2, h#3 or s#2", text));
+        let text = text.trim().to_lowercase();
+        let (kind, moves) = text.split_once('#').ok_or_else(invalid)?;
+        let moves: u8 = moves.parse().map_err(|_| invalid())?;
+        if moves == 0 {
+            return Err(invalid());
+        }
+        match kind {
+            "" => Ok(Stipulation::Mate(moves)),
+            "h" => Ok(Stipulation::Helpmate(moves)),
+            "s" => Ok(Stipulation::Selfmate(moves)),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn get_moves(&self) -> u8 {
+        match self {
+            Stipulation::Mate(moves)
+            | Stipulation::Helpmate(moves)
+            | Stipulation::Selfmate(moves) => *moves,
+        }
+    }
+}
+
+impl Display for Stipulation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stipulation::Mate(moves) => write!(f, "#{}", moves),
+            Stipulation::Helpmate(moves) => write!(f, "h#{}", moves),
+            Stipulation::Selfmate(moves) => write!(f, "s#{}", moves),
+        }
+    }
+}
+
+/// A move of a solution, with the moves answering it: all the defences after a move of the
+/// solving side, and every move still fulfilling the stipulation after a defence.
+#[derive(Debug, Clone)]
+pub struct Variation {
+    play: HalfMove,
+    san: String,
+    replies: Vec<Variation>,
+}
+
+impl Variation {
+    pub fn get_move(&self) -> HalfMove {
+        self.play
+    }
+
+    pub fn get_san(&self) -> &str {
+        &self.san
+    }
+
+    pub fn get_replies(&self) -> &Vec<Variation> {
+        &self.replies
+    }
+
+    /// Lines of text, one per branch, with the move numbers of the position.
+    fn format(&self, turn: usize, key: bool) -> Vec<String> {
+        let colour = self.play.get_piece().get_colour();
+        let number = match colour {
+            Colour::White => format!("{}.", turn),
+            Colour::Black => format!("{}...", turn),
+        };
+        let text = format!("{} {}{}", number, self.san, if key { "!" } else { "" });
+        let next_turn = match colour {
+            Colour::White => turn,
+            Colour::Black => turn + 1,
+        };
+
+        match &self.replies[..] {
+            [] => vec![text],
+            // A single answer goes on the same line, without its repeated move number
+            [reply] => {
+                let mut lines = reply.format(next_turn, false);
+                let first = match colour {
+                    Colour::White => lines[0].replacen(&format!("{}... ", turn), "", 1),
+                    Colour::Black => lines[0].clone(),
+                };
+                lines[0] = format!("{} {}", text, first);
+                for line in lines.iter_mut().skip(1) {
+                    *line = format!("{}{}", " ".repeat(text.len() + 1), line);
+                }
+                lines
+            }
+            replies => {
+                let mut lines = vec![text];
+                for reply in replies {
+                    lines.extend(
+                        reply
+                            .format(next_turn, false)
+                            .into_iter()
+                            .map(|line| format!("  {}", line)),
+                    );
+                }
+                lines
+            }
+        }
+    }
+}
+
+/// Everything the solver proved about a problem.
+#[derive(Debug, Clone)]
+pub struct Solution {
+    stipulation: Stipulation,
+    keys: Vec<Variation>,
+    shortest: Option<u8>,
+    nodes: u64,
+}
+
+impl Solution {
+    pub fn get_stipulation(&self) -> Stipulation {
+        self.stipulation
+    }
+
+    /// First moves fulfilling the stipulation, with the whole solution tree after each.
+    pub fn get_keys(&self) -> &Vec<Variation> {
+        &self.keys
+    }
+
+    /// Fewest moves in which the stipulation is fulfilled, `None` without solution.
+    pub fn get_shortest(&self) -> Option<u8> {
+        self.shortest
+    }
+
+    /// Positions visited, with the solution tree.
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Solutions of the problem: the keys of direct mates and selfmates, the lines of helpmates,
+    /// where the moves of both sides are part of the solution.
+    pub fn count_solutions(&self) -> usize {
+        fn count_lines(variation: &Variation) -> usize {
+            match variation.replies.is_empty() {
+                true => 1,
+                false => variation.replies.iter().map(count_lines).sum(),
+            }
+        }
+        match self.stipulation {
+            Stipulation::Helpmate(_) => self.keys.iter().map(count_lines).sum(),
+            _ => self.keys.len(),
+        }
+    }
+
+    /// Whether the problem is sound: a single solution, not shorter than stated.
+    pub fn is_sound(&self) -> bool {
+        self.count_solutions() == 1 && self.shortest == Some(self.stipulation.get_moves())
+    }
+
+    /// The solution tree, one line per variation, starting at the given move number.
+    pub fn format(&self, turn: u16) -> String {
+        let key = !matches!(self.stipulation, Stipulation::Helpmate(_));
+        let mut text = String::new();
+        for variation in &self.keys {
+            for line in variation.format(turn as usize, key) {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Solve the problem exhaustively, the side to move of the position moving first. Positions
+/// where the solution could need an under-promotion or an en-passant capture are refused, as
+/// the engine doesn't generate them.
+pub fn solve(position: &Position, stipulation: Stipulation) -> Result<Solution, Error> {
+    let board = position.get_board();
+    let colour = position.get_colour();
+    if position.get_en_passant().is_some() {
+        return Err(Error::msg(
+            "Unsupported problem: en-passant captures aren't generated",
+        ));
+    }
+    for side in [Colour::White, Colour::Black] {
+        if let Some(square) = get_promotable_pawn(board, side, stipulation.get_moves()) {
+            return Err(Error::msg(format!(
+                "Unsupported problem: the pawn on {} may promote, under-promotions aren't generated",
+                square
+            )));
+        }
+        if let Some(square) = get_en_passant_victim(board, side) {
+            return Err(Error::msg(format!(
+                "Unsupported problem: the pawn on {} may be taken en-passant, which isn't generated",
+                square
+            )));
+        }
+    }
+
+    let mut solver = Solver {
+        stipulation,
+        cache: HashMap::new(),
+        nodes: 0,
+    };
+    let moves = stipulation.get_moves();
+    let shortest = (1..=moves).find(|n| solver.is_fulfilled(board, colour, *n));
+    let keys = match shortest {
+        Some(_) => solver.get_variations(board, colour, moves),
+        None => vec![],
+    };
+    Ok(Solution {
+        stipulation,
+        keys,
+        shortest,
+        nodes: solver.nodes,
+    })
+}
+
+/// A pawn of the side close enough to the last rank to promote within the moves, counting the
+/// two squares of its first move as one.
+fn get_promotable_pawn(board: &Board, colour: Colour, moves: u8) -> Option<Square> {
+    board
+        .get_pieces(colour)
+        .iter()
+        .filter(|(_, piece)| piece.get_symbol() == 'P')
+        .find(|(square, piece)| {
+            let distance = match colour {
+                Colour::White => 7 - square.get_rank(),
+                Colour::Black => square.get_rank(),
+            };
+            let steps = match piece.is_first_move() && distance > 1 {
+                true => distance - 1,
+                false => distance,
+            };
+            steps <= moves
+        })
+        .map(|(square, _)| *square)
+}
+
+/// A pawn of the side on its starting rank, which an opponent pawn on a neighbouring file could
+/// take en-passant once it moved two squares.
+fn get_en_passant_victim(board: &Board, colour: Colour) -> Option<Square> {
+    let (start, capture) = match colour {
+        Colour::White => (1, 3),
+        Colour::Black => (6, 4),
+    };
+    let opponents = board.get_pieces(colour.get_opposite());
+    board
+        .get_pieces(colour)
+        .iter()
+        .filter(|(square, piece)| piece.get_symbol() == 'P' && square.get_rank() == start)
+        .map(|(square, _)| *square)
+        .find(|square| {
+            opponents.iter().any(|(other, piece)| {
+                piece.get_symbol() == 'P'
+                    && other.get_rank() == capture
+                    && other.get_file().abs_diff(square.get_file()) == 1
+            })
+        })
+}
+
+/// Proof search over all the moves of both sides, remembering the positions already solved.
+struct Solver {
+    stipulation: Stipulation,
+    cache: HashMap<(u64, u8), bool>,
+    nodes: u64,
+}
+
+impl Solver {
+    /// Whether the side to move fulfils the stipulation in at most n moves.
+    fn is_fulfilled(&mut self, board: &Board, colour: Colour, n: u8) -> bool {
+        let key = (utils::hash_position(board, colour), n);
+        if let Some(fulfilled) = self.cache.get(&key) {
+            return *fulfilled;
+        }
+        let fulfilled = self
+            .get_candidates(board, colour, n)
+            .iter()
+            .any(|play| self.get_answers(board, play, n).is_some());
+        self.cache.insert(key, fulfilled);
+        fulfilled
+    }
+
+    /// Moves of the side to move worth trying with n moves left, checks first.
+    fn get_candidates(&mut self, board: &Board, colour: Colour, n: u8) -> Vec<HalfMove> {
+        let mut moves = Game::select_legal_moves(board, colour);
+        self.nodes += moves.len() as u64;
+        // The last move of a direct mate must give check
+        if n == 1 && matches!(self.stipulation, Stipulation::Mate(_)) {
+            moves.retain(|m| m.is_check());
+        }
+        moves.sort_by_key(|m| (!m.is_check(), !m.is_capture()));
+        moves
+    }
+
+    /// Answers of the opponent after the move that keep the stipulation fulfilled: all its
+    /// replies when it defends, those leading to the mate when it helps. `None` if the move
+    /// fails, no answers if it ends the problem.
+    fn get_answers(&mut self, board: &Board, play: &HalfMove, n: u8) -> Option<Vec<HalfMove>> {
+        let colour = play.get_piece().get_colour();
+        let opponent = colour.get_opposite();
+        let mut next = board.duplicate();
+        next.execute_move(play);
+        let replies = Game::select_legal_moves(&next, opponent);
+        self.nodes += replies.len() as u64;
+
+        match self.stipulation {
+            Stipulation::Mate(_) => {
+                if replies.is_empty() {
+                    return next.is_under_check(opponent).then(Vec::new);
+                }
+                if n == 1 {
+                    return None;
+                }
+                let mut answers = vec![];
+                for reply in replies {
+                    let after = Solver::play(&next, &reply);
+                    if !self.is_fulfilled(&after, colour, n - 1) {
+                        return None;
+                    }
+                    answers.push(reply);
+                }
+                Some(answers)
+            }
+            Stipulation::Selfmate(_) => {
+                if replies.is_empty() {
+                    return None;
+                }
+                let mut answers = vec![];
+                for reply in replies {
+                    let after = Solver::play(&next, &reply);
+                    let mated = after.is_under_check(colour)
+                        && Game::select_legal_moves(&after, colour).is_empty();
+                    if !mated && (n == 1 || !self.is_fulfilled(&after, colour, n - 1)) {
+                        return None;
+                    }
+                    answers.push(reply);
+                }
+                Some(answers)
+            }
+            Stipulation::Helpmate(_) => {
+                // The opponent of the side to move delivers the mate
+                let mut answers = vec![];
+                for reply in replies {
+                    if n == 1 && !reply.is_check() {
+                        continue;
+                    }
+                    let after = Solver::play(&next, &reply);
+                    let mated = after.is_under_check(colour)
+                        && Game::select_legal_moves(&after, colour).is_empty();
+                    if mated || (n > 1 && self.is_fulfilled(&after, colour, n - 1)) {
+                        answers.push(reply);
+                    }
+                }
+                (!answers.is_empty()).then_some(answers)
+            }
+        }
+    }
+
+    /// The solution tree of the side to move, with n moves left.
+    fn get_variations(&mut self, board: &Board, colour: Colour, n: u8) -> Vec<Variation> {
+        let mut variations = vec![];
+        for play in self.get_candidates(board, colour, n) {
+            let Some(answers) = self.get_answers(board, &play, n) else {
+                continue;
+            };
+            let next = Solver::play(board, &play);
+            let mut replies = vec![];
+            for reply in &answers {
+                let after = Solver::play(&next, reply);
+                // Defences answered by a mate end the line, as do the mates given in selfmates
+                let finished = match self.stipulation {
+                    Stipulation::Mate(_) => false,
+                    _ => Game::select_legal_moves(&after, colour).is_empty(),
+                };
+                replies.push(Variation {
+                    play: *reply,
+                    san: san::to_san(&next, reply),
+                    replies: match finished || n == 1 {
+                        true => vec![],
+                        false => self.get_variations(&after, colour, n - 1),
+                    },
+                });
+            }
+            variations.push(Variation {
+                play,
+                san: san::to_san(board, &play),
+                replies,
+            });
+        }
+        variations
+    }
+
+    fn play(board: &Board, play: &HalfMove) -> Board {
+        let mut next = board.duplicate();
+        next.execute_move(play);
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fen;
+
+    fn solve_fen(text: &str, stipulation: &str) -> Result<Solution, Error> {
+        solve(
+            &fen::parse_fen(text).unwrap(),
+            Stipulation::parse(stipulation).unwrap(),
+        )
+    }
+
+    fn get_keys(solution: &Solution) -> Vec<&str> {
+        solution.get_keys().iter().map(|k| k.get_san()).collect()
+    }
+
+    #[test]
+    fn direct_mate() {
+        let solution = solve_fen("7k/8/5K2/8/8/8/8/6R1 w - - 0 1", "#2").unwrap();
+        assert!(solution.is_sound());
+        assert_eq!(get_keys(&solution), vec!["Kf7"]);
+        assert_eq!(solution.format(1), "1. Kf7! Kh7 2. Rh1#\n");
+    }
+
+    #[test]
+    fn helpmate() {
+        let solution = solve_fen("8/6k1/2B5/5K2/8/8/7R/8 b - - 0 1", "h#2").unwrap();
+        assert!(solution.is_sound());
+        assert_eq!(solution.format(1), "1... Kg8 2. Kf6 Kf8 3. Rh8#\n");
+    }
+
+    #[test]
+    fn selfmate() {
+        let solution = solve_fen("5r2/3Q4/8/8/8/5R2/1k4PP/7K w - - 0 1", "s#2").unwrap();
+        assert!(solution.is_sound());
+        assert_eq!(get_keys(&solution), vec!["Qd2+"]);
+        let defences: Vec<&str> = solution.get_keys()[0]
+            .get_replies()
+            .iter()
+            .map(|r| r.get_san())
+            .collect();
+        assert_eq!(defences, vec!["Ka1", "Kb1"]);
+    }
+
+    #[test]
+    fn short_solution_is_a_cook() {
+        let solution = solve_fen("k7/2K5/8/8/8/8/8/1R6 w - - 0 1", "#2").unwrap();
+        assert_eq!(solution.get_shortest(), Some(1));
+        assert!(!solution.is_sound());
+    }
+
+    #[test]
+    fn unsupported_pawns_are_refused() {
+        // A pawn promoting in time, one that may be taken en-passant, and a FEN en-passant square
+        assert!(solve_fen("7k/2P5/5K2/8/8/8/8/6R1 w - - 0 1", "#2").is_err());
+        assert!(solve_fen("7k/8/5K2/8/4p3/8/3P4/6R1 w - - 0 1", "#2").is_err());
+        assert!(solve_fen("7k/8/5K2/4pP2/8/8/8/6R1 w - e6 0 1", "#2").is_err());
+        // Pawns too far to promote are fine
+        assert!(solve_fen("7k/8/5K2/8/8/P7/8/6R1 w - - 0 1", "#2").is_ok());
+    }
+}
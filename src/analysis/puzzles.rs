@@ -0,0 +1,522 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crate::analysis::review;
+use crate::formats::{san, uci};
+use crate::game::game::Game;
+use crate::game::play::{HalfMove, Line};
+use crate::game::search::{self, SearchOptions};
+use crate::game::tree::GameTree;
+use crate::model::board::board::Board;
+use crate::model::board::square::Square;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::{Piece, PieceType};
+use crate::utils;
+use crate::variant::variant::Variant;
+
+/// Material, in pawns, that only the move of a puzzle wins, when it doesn't mate.
+const MIN_MATERIAL_GAIN: i32 = 3;
+
+/// Plies searched to check that material is won against any defence: the move, the reply and the
+/// capture, then only captures until none is left.
+const MATERIAL_PLIES: u8 = 3;
+
+/// Material lost with the king, beyond any other.
+const MATE_MATERIAL: i32 = 100;
+
+/// Plies of the solution of puzzles not ending in mate: the winning move, the best defence and
+/// the follow-up.
+const SOLUTION_PLIES: usize = 3;
+
+/// First line of puzzle files.
+pub const HEADER: &str = "id,fen,moves,san,themes";
+
+/// A position with a single winning move, and the line it starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    id: String,
+    fen: String,
+    moves: Vec<String>,
+    san: Vec<String>,
+    themes: Vec<String>,
+}
+
+impl Puzzle {
+    pub fn new(
+        id: String,
+        fen: String,
+        moves: Vec<String>,
+        san: Vec<String>,
+        themes: Vec<String>,
+    ) -> Puzzle {
+        Puzzle {
+            id,
+            fen,
+            moves,
+            san,
+            themes,
+        }
+    }
+
+    /// Puzzle from a line of a puzzle file: "id,fen,moves,san,themes", the moves and themes
+    /// separated by spaces.
+    pub fn parse(line: &str) -> Result<Puzzle, Error> {
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        let [id, fen, moves, san, themes] = fields[..] else {
+            return Err(Error::msg(format!("Invalid puzzle: {}", line)));
+        };
+        let words = |text: &str| text.split_whitespace().map(String::from).collect();
+        Ok(Puzzle::new(
+            id.to_string(),
+            fen.to_string(),
+            words(moves),
+            words(san),
+            words(themes),
+        ))
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_fen(&self) -> &str {
+        &self.fen
+    }
+
+    /// Moves of the solution in UCI notation, starting with the move to find.
+    pub fn get_moves(&self) -> &Vec<String> {
+        &self.moves
+    }
+
+    /// Moves of the solution in SAN.
+    pub fn get_san(&self) -> &Vec<String> {
+        &self.san
+    }
+
+    pub fn get_themes(&self) -> &Vec<String> {
+        &self.themes
+    }
+
+    /// The position of the puzzle.
+    pub fn start_game(&self) -> Result<Game, Error> {
+        Game::from_fen(&self.fen)
+    }
+}
+
+impl Display for Puzzle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{}",
+            self.id,
+            self.fen,
+            self.moves.join(" "),
+            self.san.join(" "),
+            self.themes.join(" ")
+        )
+    }
+}
+
+/// Read the puzzles of a file, skipping its header and empty lines.
+pub fn read_puzzles(path: &Path) -> Result<Vec<Puzzle>, Error> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Error::msg(format!("Cannot read {}: {}", path.display(), e)))?;
+    text.lines()
+        .filter(|line| !line.trim().is_empty() && line.trim() != HEADER)
+        .map(Puzzle::parse)
+        .collect()
+}
+
+/// Puzzles file with its header, one puzzle per line.
+pub fn write_puzzles(puzzles: &[Puzzle]) -> String {
+    let mut text = format!("{}\n", HEADER);
+    for puzzle in puzzles {
+        text.push_str(&format!("{}\n", puzzle));
+    }
+    text
+}
+
+/// Search every position of the main line of the game for the two best moves, and keep those
+/// where only the best one forces mate or wins decisively. Puzzles are named after the game and
+/// the move, e.g. "3-12b" for black's 12th move of game 3. Only games of standard chess are
+/// supported, as puzzles are replayed with its rules.
+pub fn find_puzzles(
+    tree: &GameTree,
+    options: &SearchOptions,
+    depth: u8,
+    game_id: &str,
+) -> Result<Vec<Puzzle>, Error> {
+    if !tree.get_variant().is_standard() {
+        return Err(Error::msg(format!(
+            "Puzzles can't be found in {} games",
+            tree.get_variant()
+        )));
+    }
+    let mut options = *options;
+    options.set_multi_pv(2);
+
+    let mut game = Game::from_fen(tree.get_start_fen())?;
+    let mut puzzles = vec![];
+    let main_line = tree.get_main_line();
+    let mut last: Option<(usize, Vec<HalfMove>)> = None;
+    for (ply, play) in main_line.iter().enumerate() {
+        let colour = game.get_side_to_move();
+        let moves = Game::select_legal_moves(game.get_board(), colour);
+
+        // The rest of a combination already found and played is not another puzzle
+        let is_continuation = last.as_ref().is_some_and(|(start, solution)| {
+            ply == start + 2
+                && solution.len() > 2
+                && (0..2).all(|i| review::is_same_move(&main_line[start + i], &solution[i]))
+        });
+        if moves.len() > 1 && !is_continuation {
+            let lines = search::search(&game, &options, colour, moves, depth);
+            if is_puzzle(game.get_board(), colour, &lines) {
+                let line = &lines[0];
+                let id = format!(
+                    "{}-{}{}",
+                    game_id,
+                    game.get_move_number(),
+                    match colour {
+                        Colour::White => 'w',
+                        Colour::Black => 'b',
+                    }
+                );
+                let puzzle = create_puzzle(id, &game, line.get_moves(), line.get_evaluation());
+                last = Some((ply, line.get_moves().clone()));
+                puzzles.push(puzzle);
+            }
+        }
+
+        let played = Game::select_legal_moves(game.get_board(), colour)
+            .into_iter()
+            .find(|m| review::is_same_move(m, play))
+            .ok_or(Error::msg(format!("Illegal move at ply {}", ply + 1)))?;
+        game.make_move(played)?;
+    }
+    Ok(puzzles)
+}
+
+fn create_puzzle(id: String, game: &Game, line: &[HalfMove], evaluation: f32) -> Puzzle {
+    // Mates are solved to the end, other wins once the material is won
+    let mut line = match utils::is_mate(evaluation) {
+        true => line.to_vec(),
+        false => {
+            let last_capture = (0..line.len().min(SOLUTION_PLIES))
+                .step_by(2)
+                .rfind(|index| line[*index].is_capture() || line[*index].is_promotion())
+                .unwrap_or(0);
+            line[..=last_capture].to_vec()
+        }
+    };
+    if line.len() % 2 == 0 {
+        line.pop();
+    }
+
+    let mut board = game.get_board().duplicate();
+    let mut san = vec![];
+    for play in &line {
+        san.push(san::to_san(&board, play));
+        board.execute_move(play);
+    }
+
+    // Tactics are only worth naming when they don't mate at once
+    let mut themes = vec![];
+    let mate_distance = utils::get_mate_distance(evaluation);
+    if utils::is_mate(evaluation) {
+        themes.push(format!("mate-in-{}", mate_distance));
+    }
+    let mut board = game.get_board().duplicate();
+    board.execute_move(&line[0]);
+    let is_mate_in_one = utils::is_mate(evaluation) && mate_distance == 1;
+    if !is_mate_in_one && is_fork(&board, &line[0]) {
+        themes.push(String::from("fork"));
+    }
+    if !is_mate_in_one && is_pin(&board, &line[0]) {
+        themes.push(String::from("pin"));
+    }
+    if !utils::is_mate(evaluation) && line.len() == 1 && line[0].is_capture() {
+        themes.push(String::from("hanging-piece"));
+    }
+    if line.iter().step_by(2).any(|m| m.is_promotion()) {
+        themes.push(String::from("promotion"));
+    }
+
+    Puzzle::new(
+        id,
+        game.get_fen(),
+        line.iter().map(uci::to_uci).collect(),
+        san,
+        themes,
+    )
+}
+
+/// Whether only the best of the two best lines forces mate or wins decisively.
+fn is_puzzle(board: &Board, colour: Colour, lines: &[Line]) -> bool {
+    match lines {
+        // No other move may mate, win by the tablebase or win material
+        [best, second, ..] if is_winning_mate(best.get_evaluation()) => {
+            let evaluation = second.get_evaluation();
+            !(utils::is_decisive(evaluation) && evaluation > 0.0)
+                && second
+                    .get_first_move()
+                    .is_none_or(|play| get_forced_gain(board, colour, &play) < MIN_MATERIAL_GAIN)
+        }
+        // The line found by the engine is only a hint, the gain must be forced
+        [best, _, ..] => {
+            let gain = get_material_after(board, best.get_moves(), colour)
+                - get_material_after(board, &[], colour);
+            gain >= MIN_MATERIAL_GAIN
+                && best
+                    .get_first_move()
+                    .is_some_and(|play| is_only_material_win(board, colour, &play))
+        }
+        _ => false,
+    }
+}
+
+fn is_winning_mate(evaluation: f32) -> bool {
+    utils::is_mate(evaluation) && evaluation > 0.0
+}
+
+/// Material of the player minus the material of the opponent, in pawns, once the line played.
+fn get_material_after(board: &Board, line: &[HalfMove], colour: Colour) -> i32 {
+    let mut board = board.duplicate();
+    for play in line {
+        board.execute_move(play);
+    }
+    let material = |colour: Colour| -> i32 {
+        board
+            .get_pieces(colour)
+            .values()
+            .filter(|piece| !matches!(piece.get_type(), PieceType::King(_)))
+            .map(|piece| piece.get_value() as i32)
+            .sum()
+    };
+    material(colour) - material(colour.get_opposite())
+}
+
+/// Whether the move wins material against any defence, and no other move does.
+fn is_only_material_win(board: &Board, colour: Colour, play: &HalfMove) -> bool {
+    let mut gain = i32::MIN;
+    let mut other_gain = i32::MIN;
+    for m in Game::select_legal_moves(board, colour) {
+        let value = get_forced_gain(board, colour, &m);
+        match review::is_same_move(&m, play) {
+            true => gain = value,
+            false => other_gain = other_gain.max(value),
+        }
+    }
+    gain >= MIN_MATERIAL_GAIN && other_gain < MIN_MATERIAL_GAIN
+}
+
+/// Material the move wins against any defence, in pawns.
+fn get_forced_gain(board: &Board, colour: Colour, play: &HalfMove) -> i32 {
+    let mut after = board.duplicate();
+    after.execute_move(play);
+    search_material(&after, colour, colour.get_opposite(), MATERIAL_PLIES - 1)
+        - get_material_after(board, &[], colour)
+}
+
+/// Material of the player once both sides have played the given number of plies, each side
+/// choosing the moves best for its own material, and the captures left played out.
+fn search_material(board: &Board, colour: Colour, side_to_move: Colour, plies: u8) -> i32 {
+    if plies == 0 {
+        return resolve_captures(board, colour, side_to_move);
+    }
+    let moves = Game::select_legal_moves(board, side_to_move);
+    if moves.is_empty() {
+        let material = get_material_after(board, &[], colour);
+        return match (board.is_under_check(side_to_move), side_to_move == colour) {
+            (true, true) => material - MATE_MATERIAL,
+            (true, false) => material + MATE_MATERIAL,
+            (false, _) => material,
+        };
+    }
+
+    let values = moves.iter().map(|m| {
+        let mut after = board.duplicate();
+        after.execute_move(m);
+        search_material(&after, colour, side_to_move.get_opposite(), plies - 1)
+    });
+    match side_to_move == colour {
+        true => values.max().unwrap_or(0),
+        false => values.min().unwrap_or(0),
+    }
+}
+
+/// Material of the player once the side to move has captured all it can without losing
+/// material, and the other side has recaptured, each side free to stop capturing instead.
+fn resolve_captures(board: &Board, colour: Colour, side_to_move: Colour) -> i32 {
+    let material = get_material_after(board, &[], colour);
+    let captures: Vec<HalfMove> = board
+        .get_pieces(side_to_move)
+        .iter()
+        .flat_map(|(square, piece)| piece.available_moves(board, square))
+        .filter(|m| m.is_capture() && board.see(m) >= 0)
+        .collect();
+    let values = board
+        .get_variant()
+        .select_moves(board, side_to_move, captures)
+        .into_iter()
+        .map(|m| {
+            let mut after = board.duplicate();
+            after.execute_move(&m);
+            resolve_captures(&after, colour, side_to_move.get_opposite())
+        });
+    match side_to_move == colour {
+        true => values.fold(material, i32::max),
+        false => values.fold(material, i32::min),
+    }
+}
+
+/// Whether the piece just moved attacks at least two enemy pieces worth more than a pawn, the
+/// king included.
+fn is_fork(board: &Board, play: &HalfMove) -> bool {
+    let colour = play.get_piece().get_colour();
+    let Some(piece) = board.get_piece(play.get_to(), Some(colour)) else {
+        return false;
+    };
+    if matches!(piece.get_type(), PieceType::King(_)) {
+        return false;
+    }
+    let targets = piece
+        .available_moves(board, &play.get_to())
+        .iter()
+        .filter(|m| m.is_capture())
+        .filter_map(|m| board.get_piece(m.get_to(), Some(colour.get_opposite())))
+        .filter(|target| target.get_value() > 1)
+        .count();
+    targets >= 2
+}
+
+/// Whether the piece just moved pins an enemy piece to its king.
+fn is_pin(board: &Board, play: &HalfMove) -> bool {
+    let colour = play.get_piece().get_colour();
+    let directions: &[(i8, i8)] = match play.get_piece() {
+        PieceType::Bishop(_) => &[(1, 1), (1, -1), (-1, 1), (-1, -1)],
+        PieceType::Rook(_) => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+        PieceType::Queen(_) => &[
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+        ],
+        _ => return false,
+    };
+
+    directions.iter().any(|(rank_step, file_step)| {
+        let pieces = get_pieces_on_ray(board, play.get_to(), *rank_step, *file_step);
+        match &pieces[..] {
+            [(first, pinned), (second, king), ..] => {
+                *first == colour.get_opposite()
+                    && *second == colour.get_opposite()
+                    && !matches!(pinned.get_type(), PieceType::King(_))
+                    && matches!(king.get_type(), PieceType::King(_))
+            }
+            _ => false,
+        }
+    })
+}
+
+/// The first two pieces met from the square in a direction, with their colours.
+fn get_pieces_on_ray(
+    board: &Board,
+    from: Square,
+    rank_step: i8,
+    file_step: i8,
+) -> Vec<(Colour, Box<dyn Piece>)> {
+    let mut pieces = vec![];
+    let (mut rank, mut file) = (from.get_rank() as i8, from.get_file() as i8);
+    while pieces.len() < 2 {
+        rank += rank_step;
+        file += file_step;
+        let Some(square) = board.get_square(rank, file) else {
+            break;
+        };
+        if let Some(piece) = board.get_piece(*square, None) {
+            pieces.push((piece.get_colour(), piece));
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::puzzles::{find_puzzles, is_only_material_win, is_puzzle};
+    use crate::formats::{fen, uci};
+    use crate::game::play::Line;
+    use crate::game::search::SearchOptions;
+    use crate::game::tree::GameTree;
+    use crate::model::board::board::Board;
+    use crate::model::piece::color::Colour;
+    use crate::utils;
+    use crate::variant::variant::{Variant, VariantType};
+
+    fn parse(fen: &str) -> (Board, Colour) {
+        let position = fen::parse_fen(fen).unwrap();
+        let colour = position.get_colour();
+        (position.into_board(), colour)
+    }
+
+    fn line(board: &Board, colour: Colour, play: &str, evaluation: f32) -> Line {
+        Line::new(
+            vec![uci::parse_uci(board, colour, play).unwrap()],
+            evaluation,
+        )
+    }
+
+    #[test]
+    fn fork_winning_a_rook() {
+        let (board, colour) = parse("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1");
+        let play = uci::parse_uci(&board, colour, "b5c7").unwrap();
+        assert!(is_only_material_win(&board, colour, &play));
+    }
+
+    #[test]
+    fn fork_on_a_defended_rook() {
+        // Nc7+ and Nxa8, but Bxa8 wins the knight back
+        let (board, colour) = parse("r3k3/1b6/8/1N6/8/8/8/4K3 w - - 0 1");
+        let play = uci::parse_uci(&board, colour, "b5c7").unwrap();
+        assert!(!is_only_material_win(&board, colour, &play));
+    }
+
+    #[test]
+    fn only_move_to_mate() {
+        let (board, colour) = parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let best = line(&board, colour, "a1a8", utils::WIN - 1.0);
+        let second = line(&board, colour, "g1f1", 3.0);
+        assert!(is_puzzle(&board, colour, &[best, second]));
+    }
+
+    #[test]
+    fn mate_with_another_winning_move() {
+        // Ra8# or Bxh5, winning the queen
+        let (board, colour) = parse("6k1/5ppp/8/7q/8/8/4B3/R5K1 w - - 0 1");
+        let best = line(&board, colour, "a1a8", utils::WIN - 1.0);
+        let second = line(&board, colour, "e2h5", 4.0);
+        assert!(!is_puzzle(&board, colour, &[best, second]));
+    }
+
+    #[test]
+    fn mate_with_another_decisive_move() {
+        let (board, colour) = parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let best = line(&board, colour, "a1a8", utils::WIN - 1.0);
+        let second = line(&board, colour, "g1f1", utils::TABLEBASE_WIN - 10.0);
+        assert!(!is_puzzle(&board, colour, &[best, second]));
+    }
+
+    #[test]
+    fn variant_games_are_refused() {
+        let variant = VariantType::parse("horde").unwrap();
+        let tree = GameTree::from_variant_fen(variant.get_start_fen(), variant).unwrap();
+        assert!(find_puzzles(&tree, &SearchOptions::new(), 1, "1").is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::Error;
+
+use crate::formats::san;
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::game::search::{self, SearchOptions};
+use crate::game::tree::GameTree;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::Piece;
+use crate::utils;
+
+/// Evaluations beyond this many centipawns, mates included, count as this many: once the game is
+/// won, a slower win is not a mistake.
+const MAX_CENTIPAWNS: f32 = 1000.0;
+
+/// Drops of the winning chances, in percents, from which a move is an inaccuracy, a mistake or
+/// a blunder.
+const INACCURACY: f32 = 10.0;
+const MISTAKE: f32 = 20.0;
+const BLUNDER: f32 = 30.0;
+
+/// How good a move is, compared to the best one found by the engine.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Classification {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Classification {
+    pub const ALL: [Classification; 5] = [
+        Classification::Best,
+        Classification::Good,
+        Classification::Inaccuracy,
+        Classification::Mistake,
+        Classification::Blunder,
+    ];
+
+    /// Numeric annotation glyph of the PGN standard: ?!, ? or ??.
+    pub fn get_nag(&self) -> Option<u8> {
+        match self {
+            Classification::Inaccuracy => Some(6),
+            Classification::Mistake => Some(2),
+            Classification::Blunder => Some(4),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Classification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Classification::Best => "best",
+            Classification::Good => "good",
+            Classification::Inaccuracy => "inaccuracy",
+            Classification::Mistake => "mistake",
+            Classification::Blunder => "blunder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A played move compared to the best one, both evaluated from the point of view of the mover.
+#[derive(Debug, Clone)]
+pub struct MoveReview {
+    turn: u16,
+    play: HalfMove,
+    san: String,
+    evaluation: f32,
+    best: HalfMove,
+    best_san: String,
+    best_evaluation: f32,
+}
+
+impl MoveReview {
+    pub fn get_turn(&self) -> u16 {
+        self.turn
+    }
+
+    pub fn get_colour(&self) -> Colour {
+        self.play.get_piece().get_colour()
+    }
+
+    pub fn get_move(&self) -> HalfMove {
+        self.play
+    }
+
+    pub fn get_san(&self) -> &str {
+        &self.san
+    }
+
+    pub fn get_evaluation(&self) -> f32 {
+        self.evaluation
+    }
+
+    pub fn get_best_move(&self) -> HalfMove {
+        self.best
+    }
+
+    pub fn get_best_san(&self) -> &str {
+        &self.best_san
+    }
+
+    pub fn get_best_evaluation(&self) -> f32 {
+        self.best_evaluation
+    }
+
+    /// Centipawns lost by playing this move instead of the best one.
+    pub fn get_loss(&self) -> f32 {
+        (to_centipawns(self.best_evaluation) - to_centipawns(self.evaluation)).max(0.0)
+    }
+
+    /// Percents of winning chances lost by playing this move instead of the best one.
+    pub fn get_win_loss(&self) -> f32 {
+        get_win_loss(self.best_evaluation, self.evaluation)
+    }
+
+    /// Accuracy of the move, from 100 for the best move down to 0, as defined by Lichess.
+    pub fn get_accuracy(&self) -> f32 {
+        (103.1668 * (-0.04354 * self.get_win_loss()).exp() - 3.1669).clamp(0.0, 100.0)
+    }
+
+    pub fn get_classification(&self) -> Classification {
+        let win_loss = self.get_win_loss();
+        if is_same_move(&self.play, &self.best) || self.get_loss() < 1.0 {
+            Classification::Best
+        } else if win_loss < INACCURACY {
+            Classification::Good
+        } else if win_loss < MISTAKE {
+            Classification::Inaccuracy
+        } else if win_loss < BLUNDER {
+            Classification::Mistake
+        } else {
+            Classification::Blunder
+        }
+    }
+}
+
+/// Moves reviewed for one player, averaged.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    moves: usize,
+    total_loss: f32,
+    total_accuracy: f32,
+    counts: [usize; 5],
+}
+
+impl Summary {
+    pub fn new(reviews: &[MoveReview], colour: Colour) -> Summary {
+        let mut summary = Summary::default();
+        for review in reviews.iter().filter(|r| r.get_colour() == colour) {
+            summary.moves += 1;
+            summary.total_loss += review.get_loss();
+            summary.total_accuracy += review.get_accuracy();
+            let index = Classification::ALL
+                .iter()
+                .position(|c| *c == review.get_classification())
+                .unwrap_or(0);
+            summary.counts[index] += 1;
+        }
+        summary
+    }
+
+    pub fn get_moves(&self) -> usize {
+        self.moves
+    }
+
+    pub fn get_average_loss(&self) -> f32 {
+        self.total_loss / self.moves.max(1) as f32
+    }
+
+    /// Average accuracy of the moves, in percents.
+    pub fn get_accuracy(&self) -> f32 {
+        match self.moves {
+            0 => 100.0,
+            moves => self.total_accuracy / moves as f32,
+        }
+    }
+
+    pub fn get_count(&self, classification: Classification) -> usize {
+        let index = Classification::ALL
+            .iter()
+            .position(|c| *c == classification);
+        index.map_or(0, |i| self.counts[i])
+    }
+}
+
+/// Search every position of the main line of the game, before and after the move played, at the
+/// given depth (or for the move time of the options). Each move is passed to `on_move` once
+/// reviewed.
+pub fn review_game(
+    tree: &GameTree,
+    options: &SearchOptions,
+    depth: u8,
+    on_move: &mut dyn FnMut(&MoveReview),
+) -> Result<Vec<MoveReview>, Error> {
+    let mut game = Game::from_variant_fen(tree.get_start_fen(), tree.get_variant())?;
+    let mut reviews = vec![];
+    for play in tree.get_main_line() {
+        let colour = game.get_side_to_move();
+        let board = game.get_board();
+        let moves = Game::select_legal_moves(board, colour);
+        let play = moves
+            .iter()
+            .find(|m| is_same_move(m, &play))
+            .copied()
+            .ok_or(Error::msg(format!(
+                "Illegal move {}",
+                san::to_san(board, &play)
+            )))?;
+
+        let lines = search::search(&game, options, colour, moves, depth);
+        let (best, best_evaluation) = match lines.first().and_then(|l| l.get_first_move()) {
+            Some(best) => (best, lines[0].get_evaluation()),
+            None => (play, play.get_evaluation()),
+        };
+        let evaluation = match is_same_move(&play, &best) {
+            true => best_evaluation,
+            false => search::search(&game, options, colour, vec![play], depth)
+                .first()
+                .map_or(best_evaluation, |line| line.get_evaluation()),
+        };
+
+        let review = MoveReview {
+            turn: game.get_move_number(),
+            play,
+            san: san::to_san(board, &play),
+            evaluation,
+            best,
+            best_san: san::to_san(board, &best),
+            best_evaluation,
+        };
+        on_move(&review);
+        reviews.push(review);
+        game.make_move(play)?;
+    }
+    Ok(reviews)
+}
+
+/// Copy of the game with glyphs on the inaccuracies, mistakes and blunders, and the better
+/// move in a comment.
+pub fn annotate(tree: &GameTree, reviews: &[MoveReview]) -> GameTree {
+    let mut tree = tree.clone();
+    tree.go_to_start();
+    for review in reviews {
+        if tree.forward(0).is_none() {
+            break;
+        }
+        let classification = review.get_classification();
+        if let Some(nag) = classification.get_nag() {
+            tree.add_nag(nag);
+            tree.set_comment(Some(format!(
+                "{} ({:.0} cp lost), {} was best",
+                capitalise(&classification.to_string()),
+                review.get_loss(),
+                review.get_best_san()
+            )));
+        }
+    }
+    tree.go_to_start();
+    tree
+}
+
+/// Percents of winning chances lost by getting the second evaluation instead of the first one,
+/// both from the point of view of the same player.
+pub(crate) fn get_win_loss(better: f32, worse: f32) -> f32 {
+    (get_win_percent(to_centipawns(better)) - get_win_percent(to_centipawns(worse))).max(0.0)
+}
+
+/// Evaluation in the centipawns the engine reports to GUIs, capped.
+fn to_centipawns(evaluation: f32) -> f32 {
+    match utils::is_decisive(evaluation) {
+        true => MAX_CENTIPAWNS * evaluation.signum(),
+        false => (100.0 * evaluation).clamp(-MAX_CENTIPAWNS, MAX_CENTIPAWNS),
+    }
+}
+
+/// Winning chances in percents for an advantage in centipawns, as fitted by Lichess on its games.
+fn get_win_percent(centipawns: f32) -> f32 {
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * centipawns).exp()) - 1.0)
+}
+
+pub(crate) fn is_same_move(first: &HalfMove, second: &HalfMove) -> bool {
+    first.get_from() == second.get_from()
+        && first.get_to() == second.get_to()
+        && first.is_promotion() == second.is_promotion()
+}
+
+fn capitalise(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::review::get_win_loss;
+    use crate::utils;
+
+    #[test]
+    fn no_loss_for_equal_evaluations() {
+        assert_eq!(get_win_loss(0.0, 0.0), 0.0);
+        assert_eq!(get_win_loss(3.0, 3.0), 0.0);
+        assert_eq!(get_win_loss(-2.0, -2.0), 0.0);
+        assert_eq!(get_win_loss(0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn loss_in_equal_position() {
+        // One pawn given away from equality costs about 9% of winning chances
+        let loss = get_win_loss(0.0, -1.0);
+        assert!((loss - 9.1).abs() < 0.1, "{}", loss);
+        assert!((get_win_loss(1.0, 0.0) - loss).abs() < 0.01);
+    }
+
+    #[test]
+    fn loss_in_winning_position() {
+        // The same pawn matters much less once the game is won
+        assert!(get_win_loss(8.0, 7.0) < get_win_loss(0.0, -1.0) / 4.0);
+
+        // Missing a mate for an equal position throws away half of the chances
+        let loss = get_win_loss(utils::WIN - 3.0, 0.0);
+        assert!(loss > 45.0 && loss <= 50.0, "{}", loss);
+    }
+
+    #[test]
+    fn loss_in_losing_position() {
+        assert!(get_win_loss(-7.0, -8.0) < get_win_loss(0.0, -1.0) / 4.0);
+        assert!(get_win_loss(-7.0, utils::LOSS + 4.0) < 5.0);
+    }
+}
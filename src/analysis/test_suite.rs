@@ -0,0 +1,270 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+
+use crate::analysis::review;
+use crate::formats::epd::{self, EpdRecord};
+use crate::formats::san;
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::game::search::SearchOptions;
+
+/// A position of a test suite, with the moves expected from the engine.
+#[derive(Debug, Clone)]
+pub struct TestPosition {
+    id: String,
+    fen: String,
+    best_moves: Vec<HalfMove>,
+    avoid_moves: Vec<HalfMove>,
+    points: Vec<(HalfMove, u32)>,
+}
+
+impl TestPosition {
+    /// Position from the "bm" (best moves), "am" (moves to avoid) and, as in the Strategic Test
+    /// Suite, "c0" (points of moves, e.g. "Nf3=10, Nc3=5") operations of the record. Returns
+    /// `None` when there is nothing to check.
+    pub fn from_epd(record: &EpdRecord, number: usize) -> Result<Option<TestPosition>, Error> {
+        let fen = record.get_fen();
+        let game = Game::from_fen(&fen)?;
+        let id = record
+            .get_operand("id")
+            .map_or(number.to_string(), String::from);
+        let parse = |text: &str| {
+            san::parse_san(game.get_board(), game.get_side_to_move(), text)
+                .ok_or(Error::msg(format!("Illegal move {} in {}", text, id)))
+        };
+        let parse_all = |opcode: &str| -> Result<Vec<HalfMove>, Error> {
+            record
+                .get_operation(opcode)
+                .map_or(Ok(vec![]), |moves| moves.iter().map(|m| parse(m)).collect())
+        };
+        let best_moves = parse_all("bm")?;
+        let avoid_moves = parse_all("am")?;
+
+        // Only the comments listing moves with points are scores
+        let mut points = vec![];
+        let comment = record.get_operand("c0").unwrap_or_default();
+        for item in comment.split(',') {
+            let Some((text, value)) = item.trim().split_once('=') else {
+                continue;
+            };
+            if let (Ok(play), Ok(value)) = (parse(text), value.trim().parse()) {
+                points.push((play, value));
+            }
+        }
+
+        if best_moves.is_empty() && avoid_moves.is_empty() && points.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(TestPosition {
+            id,
+            fen,
+            best_moves,
+            avoid_moves,
+            points,
+        }))
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_fen(&self) -> &str {
+        &self.fen
+    }
+
+    pub fn get_best_moves(&self) -> &Vec<HalfMove> {
+        &self.best_moves
+    }
+
+    pub fn get_avoid_moves(&self) -> &Vec<HalfMove> {
+        &self.avoid_moves
+    }
+
+    /// Whether the move is one of the best moves, if any are given, and none of the moves to
+    /// avoid. Without them, the move must be the one worth the most points.
+    pub fn is_solution(&self, play: &HalfMove) -> bool {
+        let contains = |moves: &[HalfMove]| moves.iter().any(|m| review::is_same_move(m, play));
+        if self.best_moves.is_empty() && self.avoid_moves.is_empty() {
+            return self.get_points(play) == self.get_max_points();
+        }
+        (self.best_moves.is_empty() || contains(&self.best_moves)) && !contains(&self.avoid_moves)
+    }
+
+    pub fn get_points(&self, play: &HalfMove) -> u32 {
+        self.points
+            .iter()
+            .find(|(m, _)| review::is_same_move(m, play))
+            .map_or(0, |(_, points)| *points)
+    }
+
+    /// Points of the best move, 0 if the position isn't scored.
+    pub fn get_max_points(&self) -> u32 {
+        self.points
+            .iter()
+            .map(|(_, points)| *points)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Expected moves in SAN, e.g. "bm Nf3 Nc3" or "am Qxb7".
+    pub fn format_expected(&self) -> String {
+        let board = Game::from_fen(&self.fen).map(|game| game.get_board().duplicate());
+        let Ok(board) = board else {
+            return String::new();
+        };
+        let format = |opcode: &str, moves: &[HalfMove]| {
+            let moves: Vec<String> = moves.iter().map(|m| san::to_san(&board, m)).collect();
+            format!("{} {}", opcode, moves.join(" "))
+        };
+        let mut parts = vec![];
+        if !self.best_moves.is_empty() {
+            parts.push(format("bm", &self.best_moves));
+        }
+        if !self.avoid_moves.is_empty() {
+            parts.push(format("am", &self.avoid_moves));
+        }
+        if parts.is_empty() {
+            let best: Vec<HalfMove> = self
+                .points
+                .iter()
+                .filter(|(_, points)| *points == self.get_max_points())
+                .map(|(play, _)| *play)
+                .collect();
+            parts.push(format("bm", &best));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Positions of an EPD file that have moves to check, and the reasons why the others were
+/// skipped (e.g. a position or a move the engine doesn't support).
+pub fn read_suite(path: &Path) -> Result<(Vec<TestPosition>, Vec<Error>), Error> {
+    let mut positions = vec![];
+    let mut skipped = vec![];
+    for (index, record) in epd::read_file(path)?.iter().enumerate() {
+        match TestPosition::from_epd(record, index + 1) {
+            Ok(position) => positions.extend(position),
+            Err(error) => skipped.push(error),
+        }
+    }
+    Ok((positions, skipped))
+}
+
+/// What the engine found in a position of a test suite.
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    found: Option<HalfMove>,
+    san: String,
+    solved: bool,
+    points: u32,
+    solved_after: Option<Duration>,
+    depth: u8,
+    nodes: u64,
+    elapsed: Duration,
+}
+
+impl TestResult {
+    pub fn get_move(&self) -> Option<HalfMove> {
+        self.found
+    }
+
+    /// The move found in SAN, or "none" without legal moves.
+    pub fn get_san(&self) -> &str {
+        &self.san
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.solved
+    }
+
+    pub fn get_points(&self) -> u32 {
+        self.points
+    }
+
+    /// Time after which the engine found the solution and didn't change its mind.
+    pub fn get_solved_after(&self) -> Option<Duration> {
+        self.solved_after
+    }
+
+    /// Deepest iteration completed.
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Search the position until the maximum depth or the move time of the options, following the
+/// best move after each iteration to know when the solution was found.
+pub fn run_position(
+    position: &TestPosition,
+    options: &SearchOptions,
+    max_depth: u8,
+) -> Result<TestResult, Error> {
+    let game = Game::from_fen(&position.fen)?;
+    let start = Instant::now();
+    let mut solved_after = None;
+    let mut depth = 0;
+    let mut nodes = 0;
+    let lines = game.analyse(options, max_depth, None, &mut |report| {
+        let best = report.get_lines().first().and_then(|l| l.get_first_move());
+        match best.is_some_and(|play| position.is_solution(&play)) {
+            true => solved_after = solved_after.or(Some(report.get_elapsed())),
+            false => solved_after = None,
+        }
+        depth = report.get_depth();
+        nodes = report.get_nodes();
+    });
+
+    let found = lines.first().and_then(|line| line.get_first_move());
+    let solved = found.is_some_and(|play| position.is_solution(&play));
+    let elapsed = start.elapsed();
+    Ok(TestResult {
+        found,
+        san: found.map_or(String::from("none"), |play| {
+            san::to_san(game.get_board(), &play)
+        }),
+        solved,
+        points: found.map_or(0, |play| position.get_points(&play)),
+        // A search stopped before completing any iteration only has its final answer
+        solved_after: solved.then(|| solved_after.unwrap_or(elapsed)),
+        depth,
+        nodes,
+        elapsed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn unparsable_positions_are_skipped() {
+        let path = env::temp_dir().join(format!("crust-suite-{}.epd", std::process::id()));
+        let suite = "\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4; id \"good\";
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e5; id \"illegal\";
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - am Nf6; id \"avoid\";
+";
+        fs::write(&path, suite).unwrap();
+        let result = read_suite(&path);
+        fs::remove_file(&path).unwrap();
+
+        let (positions, skipped) = result.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].id, "good");
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].to_string().contains("illegal"));
+    }
+}
@@ -0,0 +1,470 @@
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Error;
+
+use crate::analysis::puzzles::{self, Puzzle};
+use crate::formats::epd;
+use crate::formats::{san, uci};
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::model::piece::piece::PieceType;
+
+/// First line of progress files.
+const HEADER: &str = "id,level,due,attempts,solved,themes";
+
+/// A position to solve: a puzzle, and the other first moves accepted, as EPD records may give
+/// several best moves.
+#[derive(Debug, Clone)]
+pub struct Exercise {
+    puzzle: Puzzle,
+    alternatives: Vec<String>,
+}
+
+impl Exercise {
+    pub fn new(puzzle: Puzzle) -> Exercise {
+        Exercise {
+            puzzle,
+            alternatives: vec![],
+        }
+    }
+
+    pub fn get_puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    /// Whether the move, in UCI notation, is the expected one at this ply of the solution.
+    fn is_expected(&self, ply: usize, text: &str) -> bool {
+        self.puzzle.get_moves().get(ply).is_some_and(|m| m == text)
+            || (ply == 0 && self.alternatives.iter().any(|m| m == text))
+    }
+}
+
+/// Read exercises from an EPD file with "bm" operations, or from a puzzle file.
+pub fn read_exercises(path: &Path) -> Result<Vec<Exercise>, Error> {
+    if path.extension().is_none_or(|e| e != "epd") {
+        return Ok(puzzles::read_puzzles(path)?
+            .into_iter()
+            .map(Exercise::new)
+            .collect());
+    }
+
+    let mut exercises = vec![];
+    for (index, record) in epd::read_file(path)?.iter().enumerate() {
+        let Some(best_moves) = record.get_operation("bm") else {
+            continue;
+        };
+        let game = Game::from_fen(&record.get_fen())?;
+        let colour = game.get_side_to_move();
+        let moves: Vec<HalfMove> = best_moves
+            .iter()
+            .map(|text| {
+                san::parse_san(game.get_board(), colour, text).ok_or(Error::msg(format!(
+                    "Illegal best move {} at line {}",
+                    text,
+                    index + 1
+                )))
+            })
+            .collect::<Result<_, _>>()?;
+        let Some(first) = moves.first() else {
+            continue;
+        };
+
+        let id = record
+            .get_operand("id")
+            .map_or(format!("{}", index + 1), |id| id.replace(',', " "));
+        let puzzle = Puzzle::new(
+            id,
+            game.get_fen(),
+            vec![uci::to_uci(first)],
+            vec![san::to_san(game.get_board(), first)],
+            vec![],
+        );
+        exercises.push(Exercise {
+            puzzle,
+            alternatives: moves[1..].iter().map(uci::to_uci).collect(),
+        });
+    }
+    Ok(exercises)
+}
+
+/// How an exercise ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Solved,
+    SolvedWithHints,
+    Failed,
+    Quit,
+}
+
+/// Let the user solve the exercise: show the board, read the moves, play the replies of the
+/// solution and give hints on demand.
+pub fn solve(
+    exercise: &Exercise,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<Outcome, Error> {
+    let puzzle = exercise.get_puzzle();
+    let mut game = puzzle.start_game()?;
+    let colour = game.get_side_to_move();
+    writeln!(output, "\nPuzzle {}, {} to play", puzzle.get_id(), colour)?;
+
+    let mut hints = 0;
+    let mut ply = 0;
+    while ply < puzzle.get_moves().len() {
+        write!(output, "\n{}", game.get_board())?;
+        write!(output, "Your move (or hint, solution, quit): ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(Outcome::Quit);
+        }
+
+        let board = game.get_board();
+        let text = line.trim();
+        let play = match text {
+            "quit" | "q" => return Ok(Outcome::Quit),
+            "hint" | "h" => {
+                hints += 1;
+                writeln!(output, "{}", get_hint(exercise, &game, ply, hints)?)?;
+                continue;
+            }
+            "solution" | "s" => {
+                writeln!(output, "Solution: {}", puzzle.get_san()[ply..].join(" "))?;
+                return Ok(Outcome::Failed);
+            }
+            text => san::parse_san(board, colour, text).or(uci::parse_uci(board, colour, text)),
+        };
+        let Some(play) = play else {
+            writeln!(output, "Illegal move: {}", text)?;
+            continue;
+        };
+
+        game.make_move(play)?;
+        let opponent = colour.get_opposite();
+        let is_mate = Game::select_legal_moves(game.get_board(), opponent).is_empty()
+            && game.get_board().is_under_check(opponent);
+        if !exercise.is_expected(ply, &uci::to_uci(&play)) && !is_mate {
+            writeln!(
+                output,
+                "Wrong, the solution was {}",
+                puzzle.get_san()[ply..].join(" ")
+            )?;
+            return Ok(Outcome::Failed);
+        }
+        ply += 1;
+        if is_mate {
+            break;
+        }
+
+        // The reply of the opponent, if the solution goes on
+        if let Some(text) = puzzle.get_moves().get(ply) {
+            let reply = uci::parse_uci(game.get_board(), opponent, text)
+                .ok_or(Error::msg(format!("Illegal move {} in the solution", text)))?;
+            writeln!(output, "The opponent plays {}", puzzle.get_san()[ply])?;
+            game.make_move(reply)?;
+            ply += 1;
+        }
+    }
+
+    writeln!(output, "Solved!")?;
+    Ok(match hints {
+        0 => Outcome::Solved,
+        _ => Outcome::SolvedWithHints,
+    })
+}
+
+/// The piece to move first, then the move itself.
+fn get_hint(exercise: &Exercise, game: &Game, ply: usize, hints: usize) -> Result<String, Error> {
+    let puzzle = exercise.get_puzzle();
+    let text = &puzzle.get_moves()[ply];
+    let play = uci::parse_uci(game.get_board(), game.get_side_to_move(), text)
+        .ok_or(Error::msg(format!("Illegal move {} in the solution", text)))?;
+    Ok(match hints {
+        1 => format!(
+            "Move the {} on {}",
+            get_piece_name(play.get_piece()),
+            &text[..2]
+        ),
+        _ => format!("Play {}", puzzle.get_san()[ply]),
+    })
+}
+
+fn get_piece_name(piece: PieceType) -> &'static str {
+    match piece {
+        PieceType::Pawn(_) => "pawn",
+        PieceType::Knight(_) => "knight",
+        PieceType::Bishop(_) => "bishop",
+        PieceType::Rook(_) => "rook",
+        PieceType::Queen(_) => "queen",
+        PieceType::King(_) => "king",
+    }
+}
+
+/// The training on one puzzle, scheduled with Leitner boxes: each puzzle solved moves up a level
+/// and comes back twice as late, each puzzle failed goes back to level 0 and comes back at the
+/// next session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    id: String,
+    level: u32,
+    due: u64,
+    attempts: u32,
+    solved: u32,
+    themes: Vec<String>,
+}
+
+impl Record {
+    fn parse(line: &str) -> Result<Record, Error> {
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        let invalid = || Error::msg(format!("Invalid progress record: {}", line));
+        let [id, level, due, attempts, solved, themes] = fields[..] else {
+            return Err(invalid());
+        };
+        Ok(Record {
+            id: id.to_string(),
+            level: level.parse().map_err(|_| invalid())?,
+            due: due.parse().map_err(|_| invalid())?,
+            attempts: attempts.parse().map_err(|_| invalid())?,
+            solved: solved.parse().map_err(|_| invalid())?,
+            themes: themes.split_whitespace().map(String::from).collect(),
+        })
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_level(&self) -> u32 {
+        self.level
+    }
+
+    /// Day from which the puzzle should be solved again, counted from the Unix epoch.
+    pub fn get_due(&self) -> u64 {
+        self.due
+    }
+
+    pub fn get_attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn get_solved(&self) -> u32 {
+        self.solved
+    }
+}
+
+/// Progress of the user on all the puzzles tried, kept in a file between sessions.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    records: Vec<Record>,
+}
+
+impl Progress {
+    /// Progress saved in the file, or no progress if it doesn't exist yet.
+    pub fn open(path: &Path) -> Result<Progress, Error> {
+        if !path.exists() {
+            return Ok(Progress::default());
+        }
+        let text = fs::read_to_string(path)
+            .map_err(|e| Error::msg(format!("Cannot read {}: {}", path.display(), e)))?;
+        let records = text
+            .lines()
+            .filter(|line| !line.trim().is_empty() && line.trim() != HEADER)
+            .map(Record::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Progress { records })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut text = format!("{}\n", HEADER);
+        for r in &self.records {
+            text.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                r.id,
+                r.level,
+                r.due,
+                r.attempts,
+                r.solved,
+                r.themes.join(" ")
+            ));
+        }
+        fs::write(path, text)
+            .map_err(|e| Error::msg(format!("Cannot write {}: {}", path.display(), e)))
+    }
+
+    pub fn get_record(&self, id: &str) -> Option<&Record> {
+        self.records.iter().find(|r| r.id == id)
+    }
+
+    /// Schedule the puzzle again, later if it was solved.
+    pub fn add_attempt(&mut self, puzzle: &Puzzle, solved: bool, today: u64) {
+        let index = match self.records.iter().position(|r| r.id == puzzle.get_id()) {
+            Some(index) => index,
+            None => {
+                self.records.push(Record {
+                    id: puzzle.get_id().to_string(),
+                    level: 0,
+                    due: today,
+                    attempts: 0,
+                    solved: 0,
+                    themes: vec![],
+                });
+                self.records.len() - 1
+            }
+        };
+        let record = &mut self.records[index];
+        record.themes = puzzle.get_themes().clone();
+        record.attempts += 1;
+        match solved {
+            true => {
+                record.solved += 1;
+                record.level += 1;
+                record.due = today + (1 << (record.level - 1).min(10));
+            }
+            false => {
+                record.level = 0;
+                record.due = today;
+            }
+        }
+    }
+
+    /// Exercises for a session: the ones due, those failed the longest ago first, then new ones.
+    pub fn select<'a>(
+        &self,
+        exercises: &'a [Exercise],
+        today: u64,
+        count: usize,
+    ) -> Vec<&'a Exercise> {
+        let mut due: Vec<(&Record, &Exercise)> = exercises
+            .iter()
+            .filter_map(|e| self.get_record(e.get_puzzle().get_id()).map(|r| (r, e)))
+            .filter(|(record, _)| record.due <= today)
+            .collect();
+        due.sort_by_key(|(record, _)| (record.due, record.level));
+        let new = exercises
+            .iter()
+            .filter(|e| self.get_record(e.get_puzzle().get_id()).is_none());
+        due.into_iter()
+            .map(|(_, exercise)| exercise)
+            .chain(new)
+            .take(count)
+            .collect()
+    }
+
+    /// Puzzles solved and tried for each theme, sorted by theme, puzzles without themes first.
+    pub fn get_theme_rates(&self) -> Vec<(String, u32, u32)> {
+        let mut rates: Vec<(String, u32, u32)> = vec![];
+        for record in &self.records {
+            let untagged = [String::from("untagged")];
+            let themes = match record.themes.is_empty() {
+                true => &untagged[..],
+                false => &record.themes[..],
+            };
+            for theme in themes {
+                match rates.iter_mut().find(|(name, _, _)| name == theme) {
+                    Some(rate) => {
+                        rate.1 += record.solved;
+                        rate.2 += record.attempts;
+                    }
+                    None => rates.push((theme.clone(), record.solved, record.attempts)),
+                }
+            }
+        }
+        rates.sort_by(|a, b| (a.0 != "untagged", &a.0).cmp(&(b.0 != "untagged", &b.0)));
+        rates
+    }
+}
+
+/// Days since the Unix epoch.
+pub fn get_today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86_400)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_puzzle(id: &str) -> Puzzle {
+        Puzzle::new(
+            id.to_string(),
+            String::from("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"),
+            vec![String::from("a1a8")],
+            vec![String::from("Ra8#")],
+            vec![String::from("mate")],
+        )
+    }
+
+    fn play(input: &str) -> (Outcome, String) {
+        let exercise = Exercise::new(create_puzzle("1"));
+        let mut output = vec![];
+        let outcome = solve(&exercise, &mut input.as_bytes(), &mut output).unwrap();
+        (outcome, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn leitner_scheduling() {
+        let puzzle = create_puzzle("1");
+        let mut progress = Progress::default();
+        let today = 100;
+        for (level, due) in [(1, 101), (2, 102), (3, 104)] {
+            progress.add_attempt(&puzzle, true, today);
+            let record = progress.get_record("1").unwrap();
+            assert_eq!((record.get_level(), record.get_due()), (level, due));
+        }
+        progress.add_attempt(&puzzle, false, today);
+        let record = progress.get_record("1").unwrap();
+        assert_eq!((record.get_level(), record.get_due()), (0, today));
+        assert_eq!((record.get_attempts(), record.get_solved()), (4, 3));
+    }
+
+    #[test]
+    fn select_due_then_new() {
+        let exercises: Vec<Exercise> = ["solved", "failed", "new"]
+            .map(|id| Exercise::new(create_puzzle(id)))
+            .to_vec();
+        let mut progress = Progress::default();
+        progress.add_attempt(exercises[0].get_puzzle(), true, 100);
+        progress.add_attempt(exercises[1].get_puzzle(), false, 100);
+
+        let ids = |today: u64| -> Vec<&str> {
+            progress
+                .select(&exercises, today, 10)
+                .iter()
+                .map(|e| e.get_puzzle().get_id())
+                .collect()
+        };
+        assert_eq!(ids(100), vec!["failed", "new"]);
+        assert_eq!(ids(101), vec!["failed", "solved", "new"]);
+        assert_eq!(progress.select(&exercises, 101, 1).len(), 1);
+    }
+
+    #[test]
+    fn solve_with_the_right_move() {
+        let (outcome, output) = play("Ra8\n");
+        assert_eq!(outcome, Outcome::Solved);
+        assert!(output.contains("Solved!"));
+    }
+
+    #[test]
+    fn solve_with_a_wrong_move() {
+        let (outcome, output) = play("xyz\nRb1\n");
+        assert_eq!(outcome, Outcome::Failed);
+        assert!(output.contains("Illegal move: xyz"));
+        assert!(output.contains("Wrong, the solution was Ra8#"));
+    }
+
+    #[test]
+    fn solve_with_hints() {
+        let (outcome, output) = play("hint\nhint\na1a8\n");
+        assert_eq!(outcome, Outcome::SolvedWithHints);
+        assert!(output.contains("Move the rook on a1"));
+        assert!(output.contains("Play Ra8#"));
+
+        assert_eq!(play("quit\n").0, Outcome::Quit);
+        assert_eq!(play("").0, Outcome::Quit);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod polyglot;
+mod random;
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crate::book::random::RANDOM_64;
+use crate::formats::pgn::PgnGame;
+use crate::formats::san;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::board::square::Square;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::{Piece, PieceType};
+use crate::utils;
+
+const ENTRY_SIZE: usize = 16;
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BookMode {
+    /// Always play the move with the highest weight
+    Best,
+    /// Pick a move at random, proportionally to its weight
+    Weighted,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BookEntry {
+    key: u64,
+    play: u16,
+    weight: u16,
+    learn: u32,
+}
+
+impl BookEntry {
+    pub fn new(key: u64, play: u16, weight: u16, learn: u32) -> BookEntry {
+        BookEntry {
+            key,
+            play,
+            weight,
+            learn,
+        }
+    }
+
+    pub fn get_weight(&self) -> u16 {
+        self.weight
+    }
+
+    /// Origin and target squares of the move (castling is encoded as king takes rook).
+    pub fn get_squares(&self) -> (Square, Square) {
+        let to = Square::new(((self.play >> 3) & 7) as u8, (self.play & 7) as u8);
+        let from = Square::new(((self.play >> 9) & 7) as u8, ((self.play >> 6) & 7) as u8);
+        (from, to)
+    }
+
+    /// Promotion piece: 0 none, 1 knight, 2 bishop, 3 rook, 4 queen.
+    pub fn get_promotion(&self) -> u16 {
+        (self.play >> 12) & 7
+    }
+
+    fn read(bytes: &[u8]) -> BookEntry {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            play: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.key.to_be_bytes());
+        bytes.extend_from_slice(&self.play.to_be_bytes());
+        bytes.extend_from_slice(&self.weight.to_be_bytes());
+        bytes.extend_from_slice(&self.learn.to_be_bytes());
+    }
+}
+
+/// Opening book in the Polyglot `.bin` format: entries sorted by position key, each one holding
+/// a move and its weight.
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+    mode: BookMode,
+}
+
+impl PolyglotBook {
+    pub fn new(mut entries: Vec<BookEntry>, mode: BookMode) -> PolyglotBook {
+        entries.sort_by_key(|e| (e.key, u16::MAX - e.weight));
+        PolyglotBook { entries, mode }
+    }
+
+    pub fn open(path: &Path, mode: BookMode) -> Result<PolyglotBook, Error> {
+        let bytes = fs::read(path)
+            .map_err(|e| Error::msg(format!("Cannot read {}: {}", path.display(), e)))?;
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(Error::msg(format!(
+                "{} is not a Polyglot book",
+                path.display()
+            )));
+        }
+        let entries = bytes.chunks(ENTRY_SIZE).map(BookEntry::read).collect();
+
+        Ok(PolyglotBook::new(entries, mode))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            entry.write(&mut bytes);
+        }
+
+        fs::write(path, bytes)
+            .map_err(|e| Error::msg(format!("Cannot write {}: {}", path.display(), e)))
+    }
+
+    /// Build a book from the first `max_plies` plies of each game. As Polyglot does, a move
+    /// weighs 2 for each game won by the player who played it and 1 for each draw.
+    pub fn build(games: &[PgnGame], max_plies: usize, mode: BookMode) -> PolyglotBook {
+        let mut weights: HashMap<(u64, u16), u32> = HashMap::new();
+
+        // Games starting from a custom position are not supported
+        for game in games.iter().filter(|g| g.get_tag("FEN").is_none()) {
+            let mut board = Board::new(
+                utils::init_squares(),
+                utils::init_white_pieces(),
+                utils::init_black_pieces(),
+            );
+            let mut colour = Colour::White;
+            let mut last_move = None;
+
+            for san in game.get_moves().iter().take(max_plies) {
+                // Stop at the first move the engine cannot play
+                let play = match san::parse_san(&board, colour, san) {
+                    Some(play) => play,
+                    None => break,
+                };
+                let weight = match (game.get_result(), colour) {
+                    ("1-0", Colour::White) | ("0-1", Colour::Black) => 2,
+                    ("1/2-1/2", _) | ("*", _) => 1,
+                    _ => 0,
+                };
+                let key = hash_position(&board, colour, last_move.as_ref());
+                *weights.entry((key, encode_move(&play))).or_insert(0) += weight;
+
+                board.execute_move(&play);
+                colour = colour.get_opposite();
+                last_move = Some(play);
+            }
+        }
+
+        // Weights must fit in 16 bits
+        let max_weight = weights.values().copied().max().unwrap_or(0);
+        let scale = (max_weight as f64 / u16::MAX as f64).max(1.0);
+        let entries = weights
+            .into_iter()
+            .filter(|(_, w)| *w > 0)
+            .map(|((key, play), w)| {
+                let weight = ((w as f64 / scale) as u16).max(1);
+                BookEntry::new(key, play, weight, 0)
+            })
+            .collect();
+
+        PolyglotBook::new(entries, mode)
+    }
+
+    pub fn get_entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = self.entries.partition_point(|e| e.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Book move for the position among the given legal moves, if any.
+    pub fn choose_move(
+        &self,
+        board: &Board,
+        colour: Colour,
+        last_move: Option<&HalfMove>,
+        moves: &[HalfMove],
+    ) -> Option<HalfMove> {
+        let key = hash_position(board, colour, last_move);
+
+        // Keep only entries the engine can actually play
+        let candidates: Vec<(BookEntry, HalfMove)> = self
+            .get_entries(key)
+            .iter()
+            .filter_map(|e| {
+                let (from, to) = e.get_squares();
+                moves
+                    .iter()
+                    .find(|m| {
+                        m.get_from() == from
+                            && m.get_to() == to
+                            && m.is_promotion() == (e.get_promotion() != 0)
+                    })
+                    .map(|m| (*e, *m))
+            })
+            .filter(|(e, _)| e.get_promotion() == 0 || e.get_promotion() == 4)
+            .collect();
+
+        match self.mode {
+            BookMode::Best => candidates
+                .iter()
+                .max_by_key(|(e, _)| e.get_weight())
+                .map(|(_, m)| *m),
+            BookMode::Weighted => {
+                let total: u64 = candidates.iter().map(|(e, _)| e.get_weight() as u64).sum();
+                if total == 0 {
+                    return candidates.first().map(|(_, m)| *m);
+                }
+                let mut choice = utils::random_u64() % total;
+                for (e, m) in &candidates {
+                    if choice < e.get_weight() as u64 {
+                        return Some(*m);
+                    }
+                    choice -= e.get_weight() as u64;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Polyglot key of a position. It's independent of the engine own hash, so that books built by
+/// other tools can be used. The last move is needed to know whether en-passant is possible.
+pub fn hash_position(board: &Board, colour: Colour, last_move: Option<&HalfMove>) -> u64 {
+    let mut key = 0;
+
+    // Pieces
+    for c in [Colour::White, Colour::Black] {
+        for (square, piece) in board.get_pieces(c) {
+            let kind = match piece.get_type() {
+                PieceType::Pawn(_) => 0,
+                PieceType::Knight(_) => 1,
+                PieceType::Bishop(_) => 2,
+                PieceType::Rook(_) => 3,
+                PieceType::Queen(_) => 4,
+                PieceType::King(_) => 5,
+            };
+            let kind = 2 * kind
+                + match c {
+                    Colour::White => 1,
+                    Colour::Black => 0,
+                };
+            let index = 64 * kind + 8 * square.get_rank() as usize + square.get_file() as usize;
+            key ^= RANDOM_64[index];
+        }
+    }
+
+    // Castling rights: king and rook have never moved
+    let rights = [
+        (Colour::White, 0, 7),
+        (Colour::White, 0, 0),
+        (Colour::Black, 7, 7),
+        (Colour::Black, 7, 0),
+    ];
+    for (i, (c, rank, rook_file)) in rights.into_iter().enumerate() {
+        let king = board.get_piece(Square::new(rank, 4), Some(c));
+        let rook = board.get_piece(Square::new(rank, rook_file), Some(c));
+        let is_unmoved = |p: &Option<Box<dyn Piece>>, symbol| {
+            p.as_ref()
+                .is_some_and(|p| p.get_symbol() == symbol && p.is_first_move())
+        };
+        if is_unmoved(&king, 'K') && is_unmoved(&rook, 'R') {
+            key ^= RANDOM_64[CASTLING_OFFSET + i];
+        }
+    }
+
+    // En-passant file, only if a pawn can actually capture
+    if let Some(m) = last_move {
+        let is_double_push = m.get_piece().get_symbol() == 'P'
+            && m.get_from().get_rank().abs_diff(m.get_to().get_rank()) == 2;
+        if is_double_push {
+            let to = m.get_to();
+            let can_capture = [-1, 1].into_iter().any(|step| {
+                board
+                    .get_square(to.get_rank() as i8, to.get_file() as i8 + step)
+                    .and_then(|s| board.get_piece(*s, Some(colour)))
+                    .is_some_and(|p| p.get_symbol() == 'P')
+            });
+            if can_capture {
+                key ^= RANDOM_64[EN_PASSANT_OFFSET + to.get_file() as usize];
+            }
+        }
+    }
+
+    // Side to move
+    if colour == Colour::White {
+        key ^= RANDOM_64[TURN_OFFSET];
+    }
+
+    key
+}
+
+fn encode_move(play: &HalfMove) -> u16 {
+    let from = play.get_from();
+    let to = play.get_to();
+    let promotion = match play.is_promotion() {
+        true => 4,
+        false => 0,
+    };
+
+    (to.get_file() as u16)
+        | (to.get_rank() as u16) << 3
+        | (from.get_file() as u16) << 6
+        | (from.get_rank() as u16) << 9
+        | promotion << 12
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{fen, uci};
+
+    /// Keys after each move from the starting position, as given by the Polyglot reference.
+    #[test]
+    fn reference_keys() {
+        let mut board = fen::parse_fen(fen::START_FEN).unwrap().into_board();
+        let mut colour = Colour::White;
+        assert_eq!(hash_position(&board, colour, None), 0x463b96181691fc9c);
+
+        let keys = [
+            ("e2e4", 0x823c9b50fd114196),
+            ("d7d5", 0x0756b94461c50fb0),
+            ("e4e5", 0x662fafb965db29d4),
+            ("f7f5", 0x22a48b5a8e47ff78),
+        ];
+        for (text, key) in keys {
+            let play = uci::parse_uci(&board, colour, text).unwrap();
+            board.execute_move(&play);
+            colour = colour.get_opposite();
+            assert_eq!(hash_position(&board, colour, Some(&play)), key, "{}", text);
+        }
+    }
+}
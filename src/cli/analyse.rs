@@ -0,0 +1,262 @@
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use anyhow::Error;
+
+use crust::formats::{self, pgn, san};
+use crust::game::perft;
+use crust::{format_evaluation, is_decisive, Colour, Game, Line, Report, VariantType};
+
+use crate::cli::{Arguments, Command, Flag, DEPTH, FEN, HELP, THREADS, VARIANT, VERBOSE};
+
+/// Depth searched when no limit is given, more than any analysis reaches in practice.
+const MAX_DEPTH: u8 = 64;
+
+const SECONDS: Flag =
+    Flag::new("--time", "<seconds>", "Stop searching after this time").with_short("-t");
+const LINES: Flag =
+    Flag::new("--lines", "<count>", "Number of best lines to show").with_short("-l");
+const FORMAT: Flag = Flag::new(
+    "--format",
+    "<text|uci>",
+    "Print lines in SAN, or as UCI info lines",
+)
+.with_short("-f");
+const GAMES: Flag = Flag::new(
+    "--pgn",
+    "<file>",
+    "Take the position from a game of this file",
+);
+const GAME: Flag = Flag::new(
+    "--game",
+    "<number>",
+    "Game of the PGN file, the first by default",
+);
+const MOVE: Flag = Flag::new(
+    "--move",
+    "<number>[w|b]",
+    "Position before this move of the game, the end by default",
+)
+.with_short("-m");
+const DIVIDE: Flag = Flag::new("--divide", "", "Print the count after each legal move");
+
+pub const ANALYSE: Command = Command {
+    name: "analyse",
+    arguments: "[fen]",
+    summary: "Search a position until Enter is pressed, printing each depth",
+    flags: &[
+        DEPTH, SECONDS, LINES, THREADS, FORMAT, GAMES, GAME, MOVE, VERBOSE, HELP,
+    ],
+    run: analyse,
+};
+
+pub const PERFT: Command = Command {
+    name: "perft",
+    arguments: "",
+    summary: "Count the positions reached by all the legal move sequences",
+    flags: &[FEN, VARIANT, DEPTH, DIVIDE, HELP],
+    run: count,
+};
+
+/// The position to analyse: the FEN record given as arguments, quoted or not, or a position of
+/// a PGN game.
+fn get_game(arguments: &Arguments) -> Result<Game, Error> {
+    let Some(path) = arguments.get(&GAMES) else {
+        return match arguments.get_positional().is_empty() {
+            true => Err(Error::msg("Missing position, e.g. crust analyse \"<fen>\"")),
+            false => Game::from_fen(&arguments.get_positional().join(" ")),
+        };
+    };
+
+    let games = pgn::read_file(Path::new(path))?;
+    let number = arguments.get_number::<usize>(&GAME)?.unwrap_or(1);
+    let record = games
+        .get(number.wrapping_sub(1))
+        .ok_or(Error::msg(format!("No game {} in {}", number, path)))?;
+    let tree = pgn::read_tree(record)?;
+    let moves = tree.get_main_line();
+    let plies = match arguments.get(&MOVE) {
+        Some(text) => get_plies(text, tree.get_first_turn(), tree.get_start_colour())
+            .filter(|plies| *plies <= moves.len())
+            .ok_or(Error::msg(format!("No move {} in game {}", text, number)))?,
+        None => moves.len(),
+    };
+
+    let mut game = Game::from_variant_fen(tree.get_start_fen(), tree.get_variant())?;
+    for play in &moves[..plies] {
+        game.make_move(*play)?;
+    }
+    Ok(game)
+}
+
+/// Half moves played before the given move, e.g. "12" or "12w" for white's 12th move and "12b"
+/// for black's, in a game starting at the given move number and colour.
+fn get_plies(text: &str, first_turn: u16, start_colour: Colour) -> Option<usize> {
+    let (number, colour) = match text.strip_suffix(['w', 'b']) {
+        Some(number) if text.ends_with('b') => (number, Colour::Black),
+        Some(number) => (number, Colour::White),
+        None => (text, Colour::White),
+    };
+    let index = |turn: usize, colour: Colour| 2 * turn + (colour == Colour::Black) as usize;
+    index(number.parse().ok()?, colour).checked_sub(index(first_turn as usize, start_colour))
+}
+
+fn analyse(arguments: &Arguments) -> Result<(), Error> {
+    let game = get_game(arguments)?;
+    let mut options = arguments.get_search_options()?;
+    options.set_multi_pv(arguments.get_number(&LINES)?.unwrap_or(1));
+    options.set_move_time(arguments.get_number(&SECONDS)?);
+    let depth: Option<u8> = arguments.get_number(&DEPTH)?;
+    let format = arguments.get(&FORMAT).unwrap_or("text");
+    if !["text", "uci"].contains(&format) {
+        return Err(Error::msg(format!("Unknown format {}", format)));
+    }
+
+    if arguments.has(&VERBOSE) {
+        println!("{}", game);
+    }
+    if game.get_end_reason().is_some() {
+        println!("{}", game.get_end_reason().unwrap_or_default());
+        return Ok(());
+    }
+
+    // Enter stops the search, and so does the end of the input when nothing else would
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let is_limited = depth.is_some() || options.get_move_time().is_some();
+    let flag = Arc::clone(&interrupt);
+    thread::spawn(move || {
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line).is_ok_and(|n| n > 0) || !is_limited {
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+    if format == "text" {
+        eprintln!("Press Enter to stop");
+        println!(
+            "{:>5} {:>10} {:>9} {:>8} {:>8}  line",
+            "depth", "nodes", "nps", "time", "score"
+        );
+    }
+
+    let mut reported = false;
+    let start = Instant::now();
+    let lines = game.analyse(
+        &options,
+        // The search counts its depth from 0
+        depth.map_or(MAX_DEPTH, |depth| depth.max(1) - 1),
+        Some(&interrupt),
+        &mut |report| {
+            reported = true;
+            // Nothing can be done about a closed output but stopping
+            if write_report(&mut io::stdout(), &game, report, format).is_err() {
+                interrupt.store(true, Ordering::Relaxed);
+            }
+        },
+    );
+
+    // The search may have been stopped before completing any depth
+    if !reported && format == "text" {
+        for line in &lines {
+            println!("{:>35}  {}", "", format_line(&game, line));
+        }
+    }
+    let best = lines.first().and_then(|line| line.get_first_move());
+    match (format, best) {
+        ("uci", Some(play)) => println!("bestmove {}", formats::uci::to_uci(&play)),
+        (_, Some(play)) => println!("Best move {}", san::to_san(game.get_board(), &play)),
+        (_, None) => {}
+    }
+    if arguments.has(&VERBOSE) {
+        println!("Searched for {:.2}s", start.elapsed().as_secs_f32());
+    }
+    Ok(())
+}
+
+/// Lines of a completed depth, with the nodes searched so far.
+fn write_report(
+    output: &mut impl Write,
+    game: &Game,
+    report: &Report,
+    format: &str,
+) -> Result<(), Error> {
+    let elapsed = report.get_elapsed();
+    for (index, line) in report.get_lines().iter().enumerate() {
+        match format {
+            "uci" => {
+                let moves: Vec<String> =
+                    line.get_moves().iter().map(formats::uci::to_uci).collect();
+                writeln!(
+                    output,
+                    "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+                    report.get_depth() + 1,
+                    index + 1,
+                    formats::uci::format_score(line.get_evaluation()),
+                    report.get_nodes(),
+                    report.get_nodes_per_second(),
+                    elapsed.as_millis(),
+                    moves.join(" ")
+                )?
+            }
+            _ => writeln!(
+                output,
+                "{:>5} {:>10} {:>9} {:>7.2}s {}",
+                report.get_depth() + 1,
+                report.get_nodes(),
+                report.get_nodes_per_second(),
+                elapsed.as_secs_f32(),
+                format_line(game, line)
+            )?,
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// Evaluation, with two decimals when it isn't decisive, and the moves of a line.
+pub fn format_line(game: &Game, line: &Line) -> String {
+    let evaluation = line.get_evaluation();
+    let score = match is_decisive(evaluation) {
+        true => format_evaluation(evaluation),
+        false => format!("{:+.2}", evaluation),
+    };
+    let moves = san::format_line(game.get_board(), line.get_moves(), game.get_move_number());
+    format!("{:>8}  {}", score, moves)
+}
+
+fn count(arguments: &Arguments) -> Result<(), Error> {
+    let variant = match arguments.get(&VARIANT) {
+        Some(name) => VariantType::parse(name)?,
+        None => VariantType::default(),
+    };
+    let game = match arguments.get(&FEN) {
+        Some(fen) => Game::from_variant_fen(fen, variant)?,
+        None => Game::from_variant(variant),
+    };
+    let depth = arguments.get_number(&DEPTH)?.unwrap_or(4);
+    let (board, colour) = (game.get_board(), game.get_side_to_move());
+
+    let start = Instant::now();
+    let nodes = match arguments.has(&DIVIDE) {
+        true => {
+            let counts = perft::divide(board, colour, depth);
+            for (play, nodes) in &counts {
+                println!("{}: {}", formats::uci::to_uci(play), nodes);
+            }
+            counts.iter().map(|(_, nodes)| nodes).sum()
+        }
+        false => perft::perft(board, colour, depth),
+    };
+    let seconds = start.elapsed().as_secs_f64();
+    println!(
+        "Depth {}: {} nodes in {:.2}s ({:.0} nodes/s)",
+        depth,
+        nodes,
+        seconds,
+        nodes as f64 / seconds.max(1e-9)
+    );
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use anyhow::Error;
+
+use crust::competition::engine_match::{self, MatchOptions};
+use crust::competition::stats::Sprt;
+use crust::competition::tournament::{Pairing, Tournament, TournamentOptions};
+use crust::TimeControl;
+
+use crate::cli::{Arguments, Command, Flag, HELP, PGN, TIME};
+
+const GAMES: Flag =
+    Flag::new("--games", "<count>", "Number of games, 10 by default").with_short("-n");
+const CONCURRENCY: Flag = Flag::new("--concurrency", "<count>", "Games played at the same time");
+const OPENINGS: Flag = Flag::new(
+    "--openings",
+    "<file.pgn|file.epd>",
+    "Starting positions, each one played with both colours",
+);
+const CHESS960: Flag = Flag::new(
+    "--chess960",
+    "",
+    "Start from Chess960 positions drawn at random, each one played with both colours",
+);
+const SPRT: Flag = Flag::new(
+    "--sprt",
+    "<elo0> <elo1> [alpha beta]",
+    "Stop once a sequential test decides between elo0 and elo1",
+)
+.with_values(2, 4);
+const CYCLES: Flag = Flag::new("--cycles", "<count>", "Round-robin cycles, 1 by default");
+const SWISS: Flag = Flag::new(
+    "--swiss",
+    "<rounds>",
+    "Swiss pairings instead of round-robin",
+);
+
+pub const MATCH: Command = Command {
+    name: "match",
+    arguments: "<player> <player>",
+    summary: "Play a match between two players, alternating colours",
+    flags: &[
+        GAMES,
+        CONCURRENCY,
+        TIME,
+        OPENINGS,
+        CHESS960,
+        SPRT,
+        PGN,
+        HELP,
+    ],
+    run: play_match,
+};
+
+pub const TOURNAMENT: Command = Command {
+    name: "tournament",
+    arguments: "<player> <player>...",
+    summary: "Play a round-robin or Swiss tournament and print its crosstable",
+    flags: &[
+        CYCLES,
+        SWISS,
+        CONCURRENCY,
+        TIME,
+        OPENINGS,
+        CHESS960,
+        PGN,
+        HELP,
+    ],
+    run: play_tournament,
+};
+
+fn play_match(arguments: &Arguments) -> Result<(), Error> {
+    let [first, second] = &arguments.get_positional()[..] else {
+        return Err(Error::msg(
+            "A match needs two players, see crust help match",
+        ));
+    };
+
+    let mut options = MatchOptions::new();
+    if let Some(games) = arguments.get_number(&GAMES)? {
+        options.set_games(games);
+    }
+    if let Some(concurrency) = arguments.get_number(&CONCURRENCY)? {
+        options.set_concurrency(concurrency);
+    }
+    if let Some(text) = arguments.get(&TIME) {
+        options.set_time_control(Some(TimeControl::parse(text)?));
+    }
+    if let Some(path) = arguments.get(&OPENINGS) {
+        options.set_openings(engine_match::read_openings(Path::new(path))?);
+    }
+    if arguments.has(&CHESS960) {
+        options.set_openings(engine_match::get_chess960_openings());
+    }
+    if let Some(values) = arguments.get_values(&SPRT) {
+        let numbers = values
+            .iter()
+            .map(|v| v.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|_| Error::msg(format!("Invalid SPRT values: {}", values.join(" "))))?;
+        // Error rates are optional, 5% by default
+        let (alpha, beta) = match numbers[2..] {
+            [alpha, beta] => (alpha, beta),
+            [] => (0.05, 0.05),
+            _ => return Err(Error::msg("SPRT needs both alpha and beta, or none")),
+        };
+        options.set_sprt(Some(Sprt::new(numbers[0], numbers[1], alpha, beta)?));
+    }
+    options.set_pgn(arguments.get(&PGN).map(String::from));
+
+    let score = engine_match::play_match(first, second, &options)?;
+    println!(
+        "{}",
+        engine_match::format_report(&score, options.get_sprt())
+    );
+    Ok(())
+}
+
+fn play_tournament(arguments: &Arguments) -> Result<(), Error> {
+    let mut options = TournamentOptions::new();
+    if let Some(rounds) = arguments.get_number(&SWISS)? {
+        options.set_pairing(Pairing::Swiss { rounds });
+    } else if let Some(cycles) = arguments.get_number(&CYCLES)? {
+        options.set_pairing(Pairing::RoundRobin { cycles });
+    }
+    if let Some(concurrency) = arguments.get_number(&CONCURRENCY)? {
+        options.set_concurrency(concurrency);
+    }
+    if let Some(text) = arguments.get(&TIME) {
+        options.set_time_control(Some(TimeControl::parse(text)?));
+    }
+    if let Some(path) = arguments.get(&OPENINGS) {
+        options.set_openings(engine_match::read_openings(Path::new(path))?);
+    }
+    if arguments.has(&CHESS960) {
+        options.set_openings(engine_match::get_chess960_openings());
+    }
+    options.set_pgn(arguments.get(&PGN).map(String::from));
+
+    let mut tournament = Tournament::new(arguments.get_positional().clone(), options)?;
+    tournament.play()?;
+    println!("{}", tournament.format_crosstable());
+    Ok(())
+}
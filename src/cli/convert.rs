@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crust::book::polyglot::{BookMode, PolyglotBook};
+use crust::formats::{pgn, san, uci};
+use crust::tablebase::retrograde::EndgameTables;
+use crust::Game;
+
+use crate::cli::{Arguments, Command, Flag, HELP, VERBOSE};
+
+const FORMAT: Flag = Flag::new(
+    "--format",
+    "<pgn|san|uci|fen>",
+    "Whole games, main lines in SAN or UCI notation, or final positions",
+)
+.with_short("-f");
+const OUTPUT: Flag = Flag::new(
+    "--output",
+    "<file>",
+    "Write to a file instead of the console",
+)
+.with_short("-o");
+const PLIES: Flag = Flag::new(
+    "--plies",
+    "<count>",
+    "Moves of each game to add, 20 by default",
+);
+
+pub const CONVERT: Command = Command {
+    name: "convert",
+    arguments: "<games.pgn>",
+    summary: "Convert the games of a PGN file to another notation",
+    flags: &[FORMAT, OUTPUT, HELP],
+    run: convert,
+};
+
+pub const BUILD_BOOK: Command = Command {
+    name: "build-book",
+    arguments: "<games.pgn> <book.bin>",
+    summary: "Build a Polyglot opening book from the games of a PGN file",
+    flags: &[PLIES, HELP],
+    run: build_book,
+};
+
+pub const GENERATE_TABLES: Command = Command {
+    name: "generate-tables",
+    arguments: "<directory> [endings, e.g. KQvK KBNvK]",
+    summary: "Generate endgame tables, KQvK, KRvK and KPvK by default",
+    flags: &[VERBOSE, HELP],
+    run: generate_tables,
+};
+
+fn convert(arguments: &Arguments) -> Result<(), Error> {
+    let [path] = &arguments.get_positional()[..] else {
+        return Err(Error::msg("Missing PGN file, see crust help convert"));
+    };
+    let format = arguments.get(&FORMAT).unwrap_or("pgn");
+    if !["pgn", "san", "uci", "fen"].contains(&format) {
+        return Err(Error::msg(format!("Unknown format {}", format)));
+    }
+
+    let mut text = String::new();
+    for (index, record) in pgn::read_file(Path::new(path))?.iter().enumerate() {
+        let tree =
+            pgn::read_tree(record).map_err(|e| Error::msg(format!("Game {}: {}", index + 1, e)))?;
+        if format == "pgn" {
+            text.push_str(&pgn::write_game(
+                record.get_tags(),
+                &tree,
+                record.get_result(),
+            ));
+            continue;
+        }
+
+        let mut game = Game::from_variant_fen(tree.get_start_fen(), tree.get_variant())?;
+        let moves = tree.get_main_line();
+        let line = match format {
+            "san" => san::format_line(game.get_board(), &moves, game.get_move_number()),
+            "uci" => moves.iter().map(uci::to_uci).collect::<Vec<_>>().join(" "),
+            _ => {
+                for play in moves {
+                    game.make_move(play)?;
+                }
+                game.get_fen()
+            }
+        };
+        text.push_str(&line);
+        text.push('\n');
+    }
+
+    match arguments.get(&OUTPUT) {
+        Some(path) => {
+            fs::write(path, text).map_err(|e| Error::msg(format!("Cannot write {}: {}", path, e)))
+        }
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn build_book(arguments: &Arguments) -> Result<(), Error> {
+    let [games, book] = &arguments.get_positional()[..] else {
+        return Err(Error::msg("Missing files, see crust help build-book"));
+    };
+    let max_plies = arguments.get_number(&PLIES)?.unwrap_or(20);
+    let games = pgn::read_file(Path::new(games))?;
+    PolyglotBook::build(&games, max_plies, BookMode::Best).save(Path::new(book))
+}
+
+fn generate_tables(arguments: &Arguments) -> Result<(), Error> {
+    let Some((directory, endings)) = arguments.get_positional().split_first() else {
+        return Err(Error::msg(
+            "Missing directory, see crust help generate-tables",
+        ));
+    };
+    let endings = match endings.is_empty() {
+        true => vec![
+            String::from("KQvK"),
+            String::from("KRvK"),
+            String::from("KPvK"),
+        ],
+        false => endings.to_vec(),
+    };
+
+    let mut tables = EndgameTables::new();
+    for ending in &endings {
+        tables.generate(ending)?;
+    }
+    tables.save(Path::new(directory))?;
+    for table in tables.get_tables() {
+        println!("{}", table.get_name());
+    }
+    Ok(())
+}
@@ -0,0 +1,275 @@
+use std::str::FromStr;
+use std::thread;
+
+use anyhow::Error;
+
+use crust::SearchOptions;
+
+pub(crate) mod analyse;
+pub(crate) mod competition;
+pub(crate) mod convert;
+pub(crate) mod play;
+pub(crate) mod problems;
+pub(crate) mod protocol;
+pub(crate) mod puzzles;
+pub(crate) mod review;
+pub(crate) mod suite;
+
+/// An option of a subcommand, with the number of values it takes.
+pub struct Flag {
+    name: &'static str,
+    short: Option<&'static str>,
+    values: &'static str,
+    min_values: usize,
+    max_values: usize,
+    help: &'static str,
+}
+
+impl Flag {
+    const fn new(name: &'static str, values: &'static str, help: &'static str) -> Flag {
+        let count = match values.is_empty() {
+            true => 0,
+            false => 1,
+        };
+        Flag {
+            name,
+            short: None,
+            values,
+            min_values: count,
+            max_values: count,
+            help,
+        }
+    }
+
+    const fn with_short(mut self, short: &'static str) -> Flag {
+        self.short = Some(short);
+        self
+    }
+
+    const fn with_values(mut self, min_values: usize, max_values: usize) -> Flag {
+        self.min_values = min_values;
+        self.max_values = max_values;
+        self
+    }
+}
+
+pub const HELP: Flag = Flag::new("--help", "", "Print this help").with_short("-h");
+pub const QUIET: Flag = Flag::new("--quiet", "", "Only print the results").with_short("-q");
+pub const VERBOSE: Flag =
+    Flag::new("--verbose", "", "Print more details while working").with_short("-v");
+pub const DEPTH: Flag = Flag::new("--depth", "<plies>", "Maximum search depth").with_short("-d");
+pub const TIME: Flag = Flag::new(
+    "--time",
+    "<control>",
+    "Time control, e.g. 300+2 or 40/5400+30:1800+30",
+)
+.with_short("-t");
+pub const FEN: Flag = Flag::new("--fen", "<fen>", "Starting position");
+pub const VARIANT: Flag = Flag::new(
+    "--variant",
+    "<name>",
+    "Rules of the game: kingofthehill, 3check, antichess, atomic or horde",
+);
+pub const THREADS: Flag = Flag::new(
+    "--threads",
+    "<count>",
+    "Search threads, all the cores by default",
+);
+pub const PGN: Flag = Flag::new("--pgn", "<file>", "Write the games to a PGN file");
+
+/// A subcommand, with what is needed to print its help.
+pub struct Command {
+    name: &'static str,
+    arguments: &'static str,
+    summary: &'static str,
+    flags: &'static [Flag],
+    run: fn(&Arguments) -> Result<(), Error>,
+}
+
+pub const COMMANDS: [Command; 15] = [
+    play::PLAY,
+    analyse::ANALYSE,
+    analyse::PERFT,
+    review::REVIEW,
+    puzzles::PUZZLES,
+    puzzles::TRAIN,
+    suite::EPDTEST,
+    problems::SOLVE,
+    protocol::UCI,
+    protocol::XBOARD,
+    competition::MATCH,
+    competition::TOURNAMENT,
+    convert::CONVERT,
+    convert::BUILD_BOOK,
+    convert::GENERATE_TABLES,
+];
+
+impl Command {
+    pub fn get_help(&self) -> String {
+        let usage = format!("crust {} {} [options]", self.name, self.arguments);
+        let mut help = format!(
+            "{}\n\nUsage: {}\n\nOptions:\n",
+            self.summary,
+            usage.replace("  ", " ")
+        );
+        let flags: Vec<String> = self
+            .flags
+            .iter()
+            .map(|flag| {
+                let short = flag.short.map_or(String::new(), |s| format!("{}, ", s));
+                format!("{}{} {}", short, flag.name, flag.values)
+            })
+            .collect();
+        let width = flags.iter().map(|f| f.len()).max().unwrap_or(0);
+        for (text, flag) in flags.iter().zip(self.flags) {
+            help.push_str(&format!("  {:<width$}  {}\n", text, flag.help));
+        }
+        help
+    }
+}
+
+/// Print the help of the whole program.
+pub fn get_help() -> String {
+    let mut help =
+        String::from("Crust, a chess engine\n\nUsage: crust <command> [options]\n\nCommands:\n");
+    for command in &COMMANDS {
+        help.push_str(&format!("  {:<16} {}\n", command.name, command.summary));
+    }
+    help.push_str("  help <command>   Print the help of a command\n");
+    help.push_str("\nPlayers are \"engine[:depth[:seconds per move]]\", \"human\", \"random\" ");
+    help.push_str("or \"uci:<engine command>\".\nWithout a command, crust plays a game.\n");
+    help
+}
+
+/// Run the command given on the command line, playing a game by default.
+pub fn run(args: &[String]) -> Result<(), Error> {
+    let (name, rest) = match args.first() {
+        Some(name) if !name.starts_with('-') => (name.as_str(), &args[1..]),
+        _ => ("play", args),
+    };
+    if name == "help" || args.first().is_some_and(|a| a == "--help" || a == "-h") {
+        match rest.first().filter(|_| name == "help") {
+            Some(name) => print!("{}", find_command(name)?.get_help()),
+            None => print!("{}", get_help()),
+        }
+        return Ok(());
+    }
+
+    let command = find_command(name)?;
+    let arguments = Arguments::parse(rest, command)?;
+    match arguments.has(&HELP) {
+        true => print!("{}", command.get_help()),
+        false => (command.run)(&arguments)?,
+    }
+    Ok(())
+}
+
+fn find_command(name: &str) -> Result<&'static Command, Error> {
+    COMMANDS
+        .iter()
+        .find(|c| c.name == name)
+        .ok_or(Error::msg(format!(
+            "Unknown command {}, see crust help",
+            name
+        )))
+}
+
+/// Whether the argument is the name of an option. Negative numbers and "-" are values.
+fn is_option(arg: &str) -> bool {
+    arg.starts_with('-') && arg.len() > 1 && arg.parse::<f32>().is_err()
+}
+
+/// Arguments given to a subcommand: the positional ones, then the options with their values.
+pub struct Arguments {
+    positional: Vec<String>,
+    options: Vec<(&'static str, Vec<String>)>,
+}
+
+impl Arguments {
+    pub fn parse(args: &[String], command: &Command) -> Result<Arguments, Error> {
+        let find = |arg: &str| {
+            command
+                .flags
+                .iter()
+                .find(|f| f.name == arg || f.short == Some(arg))
+        };
+        let mut positional = vec![];
+        let mut options = vec![];
+        let mut index = 0;
+        while index < args.len() {
+            let arg = &args[index];
+            index += 1;
+            if !is_option(arg) {
+                positional.push(arg.clone());
+                continue;
+            }
+
+            let flag = find(arg).ok_or(Error::msg(format!(
+                "Unknown option {}, see crust help {}",
+                arg, command.name
+            )))?;
+            let mut values = vec![];
+            while values.len() < flag.max_values
+                && args
+                    .get(index)
+                    .is_some_and(|a| values.len() < flag.min_values || !is_option(a))
+            {
+                values.push(args[index].clone());
+                index += 1;
+            }
+            if values.len() < flag.min_values {
+                return Err(Error::msg(format!(
+                    "Missing value for {}: {} {}",
+                    flag.name, flag.name, flag.values
+                )));
+            }
+            options.push((flag.name, values));
+        }
+
+        Ok(Arguments {
+            positional,
+            options,
+        })
+    }
+
+    pub fn get_positional(&self) -> &Vec<String> {
+        &self.positional
+    }
+
+    pub fn has(&self, flag: &Flag) -> bool {
+        self.options.iter().any(|(name, _)| *name == flag.name)
+    }
+
+    /// Values of the last occurrence of the option.
+    pub fn get_values(&self, flag: &Flag) -> Option<&Vec<String>> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == flag.name)
+            .map(|(_, values)| values)
+    }
+
+    pub fn get(&self, flag: &Flag) -> Option<&str> {
+        self.get_values(flag)
+            .and_then(|values| values.first())
+            .map(|value| value.as_str())
+    }
+
+    pub fn get_number<T: FromStr>(&self, flag: &Flag) -> Result<Option<T>, Error> {
+        self.get(flag)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| Error::msg(format!("Invalid value for {}: {}", flag.name, value)))
+            })
+            .transpose()
+    }
+
+    /// Search settings from the --threads option.
+    pub fn get_search_options(&self) -> Result<SearchOptions, Error> {
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+        let mut options = SearchOptions::new();
+        options.set_threads(self.get_number(&THREADS)?.unwrap_or(cores));
+        Ok(options)
+    }
+}
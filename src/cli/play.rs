@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crust::book::polyglot::{BookMode, PolyglotBook};
+use crust::formats::pgn;
+use crust::game::chess960;
+use crust::player::player::parse_player;
+use crust::tablebase::retrograde::EndgameTables;
+use crust::tablebase::syzygy::Tablebase;
+use crust::{Game, TimeControl, VariantType};
+
+use crate::cli::{
+    Arguments, Command, Flag, DEPTH, FEN, HELP, PGN, QUIET, THREADS, TIME, VARIANT, VERBOSE,
+};
+
+const WHITE: Flag = Flag::new("--white", "<player>", "White player, the engine by default");
+const BLACK: Flag = Flag::new("--black", "<player>", "Black player, the engine by default");
+const COLOUR: Flag = Flag::new(
+    "--colour",
+    "<white|black>",
+    "Play this colour against the engine",
+)
+.with_short("-c");
+const CHESS960: Flag = Flag::new(
+    "--chess960",
+    "[number]",
+    "Start from this Chess960 position, or one drawn at random",
+)
+.with_values(0, 1);
+const BOOK: Flag = Flag::new("--book", "<book.bin>", "Polyglot opening book");
+const SYZYGY: Flag = Flag::new("--syzygy", "<directory>", "Syzygy endgame tablebases");
+const TABLES: Flag = Flag::new(
+    "--tables",
+    "<directory>",
+    "Endgame tables made by generate-tables",
+);
+const FORMAT: Flag = Flag::new(
+    "--format",
+    "<text|pgn|fen>",
+    "How to print the game once over, as text by default",
+)
+.with_short("-f");
+
+pub const PLAY: Command = Command {
+    name: "play",
+    arguments: "",
+    summary: "Play a game, by default the engine against itself",
+    flags: &[
+        WHITE, BLACK, COLOUR, DEPTH, TIME, FEN, VARIANT, CHESS960, BOOK, SYZYGY, TABLES, THREADS,
+        PGN, FORMAT, QUIET, VERBOSE, HELP,
+    ],
+    run: play,
+};
+
+fn play(arguments: &Arguments) -> Result<(), Error> {
+    let options = arguments.get_search_options()?;
+    let depth: Option<u8> = arguments.get_number(&DEPTH)?;
+    let format = arguments.get(&FORMAT).unwrap_or("text");
+    if !["text", "pgn", "fen"].contains(&format) {
+        return Err(Error::msg(format!("Unknown format {}", format)));
+    }
+
+    // The engine plays at the given depth unless its description says otherwise
+    let mut descriptions = [
+        arguments.get(&WHITE).unwrap_or("engine"),
+        arguments.get(&BLACK).unwrap_or("engine"),
+    ];
+    match arguments.get(&COLOUR) {
+        Some("white") => descriptions = ["human", "engine"],
+        Some("black") => descriptions = ["engine", "human"],
+        Some(colour) => return Err(Error::msg(format!("Unknown colour {}", colour))),
+        None => {}
+    }
+    let engine = depth.map_or(String::from("engine"), |d| format!("engine:{}", d));
+    let [white, black] = descriptions.map(|description| match description {
+        "engine" => parse_player(&engine, &options),
+        description => parse_player(description, &options),
+    });
+    let (mut white, mut black) = (white?, black?);
+
+    let variant = match arguments.get(&VARIANT) {
+        Some(name) => VariantType::parse(name)?,
+        None => VariantType::default(),
+    };
+    let mut game = match (arguments.get(&FEN), arguments.has(&CHESS960)) {
+        (Some(_), true) => return Err(Error::msg("Give either a FEN or a Chess960 position")),
+        (Some(fen), false) => Game::from_variant_fen(fen, variant)?,
+        (None, true) if !variant.is_standard() => {
+            return Err(Error::msg(format!(
+                "Chess960 positions can't be played as {}",
+                variant
+            )))
+        }
+        (None, true) => {
+            let index = arguments.get_number(&CHESS960)?;
+            let index = index.unwrap_or_else(chess960::get_random_index);
+            if arguments.has(&VERBOSE) {
+                println!("Chess960 position {}", index);
+            }
+            Game::from_chess960(index)?
+        }
+        (None, false) => Game::from_variant(variant),
+    };
+    if let Some(path) = arguments.get(&BOOK) {
+        game.set_book(Some(PolyglotBook::open(
+            Path::new(path),
+            BookMode::Weighted,
+        )?));
+    }
+    if let Some(path) = arguments.get(&SYZYGY) {
+        let tablebase = Tablebase::open(Path::new(path))?;
+        if arguments.has(&VERBOSE) {
+            println!("Tablebases up to {} pieces", tablebase.get_max_pieces());
+        }
+        game.set_tablebase(Some(tablebase));
+    }
+    if let Some(path) = arguments.get(&TABLES) {
+        game.set_endgame_tables(Some(EndgameTables::open(Path::new(path))?));
+    }
+    if let Some(text) = arguments.get(&TIME) {
+        game.set_time_control(TimeControl::parse(text)?);
+    }
+
+    let quiet = arguments.has(&QUIET);
+    game.set_verbose(!quiet);
+    if arguments.has(&VERBOSE) {
+        println!("{} vs {}", white.get_name(), black.get_name());
+    }
+    if !quiet {
+        println!("{}", game);
+    }
+    game.play(white.as_mut(), black.as_mut());
+
+    let tags = vec![
+        (String::from("Event"), String::from("Casual game")),
+        (String::from("White"), white.get_name()),
+        (String::from("Black"), black.get_name()),
+        (String::from("Result"), game.get_result().to_string()),
+    ];
+    match format {
+        "pgn" => print!(
+            "{}",
+            pgn::write_game(&tags, game.get_tree(), game.get_result())
+        ),
+        "fen" => println!("{}", game.get_fen()),
+        _ if quiet => println!("{}", game.get_result()),
+        _ => println!("{}", game),
+    }
+    if let Some(reason) = game.get_end_reason().filter(|_| arguments.has(&VERBOSE)) {
+        println!("{}", reason);
+    }
+
+    if let Some(path) = arguments.get(&PGN) {
+        let text = pgn::write_game(&tags, game.get_tree(), game.get_result());
+        fs::write(path, text).map_err(|e| Error::msg(format!("Cannot write {}: {}", path, e)))?;
+    }
+    Ok(())
+}
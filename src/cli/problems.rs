@@ -0,0 +1,122 @@
+use std::time::Instant;
+
+use anyhow::Error;
+
+use crust::analysis::problems::{self, Stipulation};
+use crust::formats::{fen, san, uci};
+use crust::{format_evaluation, get_mate_distance, is_mate, Game};
+
+use crate::cli::{Arguments, Command, Flag, HELP, QUIET, THREADS};
+
+const MOVES: Flag = Flag::new("--moves", "<count>", "Moves of the stipulation").with_short("-n");
+const HELPMATE: Flag = Flag::new(
+    "--helpmate",
+    "",
+    "Both sides cooperate so that the side to move is mated",
+);
+const SELFMATE: Flag = Flag::new(
+    "--selfmate",
+    "",
+    "The side to move forces the opponent to mate it",
+);
+const SEARCH: Flag = Flag::new(
+    "--search",
+    "",
+    "Check that the engine search finds the mate of a direct problem",
+);
+
+pub const SOLVE: Command = Command {
+    name: "solve",
+    arguments: "<fen>",
+    summary: "Prove a mate problem, listing all its keys, cooks and variations",
+    flags: &[MOVES, HELPMATE, SELFMATE, SEARCH, THREADS, QUIET, HELP],
+    run: solve,
+};
+
+fn solve(arguments: &Arguments) -> Result<(), Error> {
+    if arguments.get_positional().is_empty() {
+        return Err(Error::msg("Usage: crust solve <fen> -n <moves>"));
+    }
+    let text = arguments.get_positional().join(" ");
+    let game = Game::from_fen(&text)?;
+    let moves = arguments
+        .get_number(&MOVES)?
+        .ok_or(Error::msg("Missing number of moves, e.g. -n 2"))?;
+    let stipulation = match (arguments.has(&HELPMATE), arguments.has(&SELFMATE)) {
+        (false, false) => Stipulation::Mate(moves),
+        (true, false) => Stipulation::Helpmate(moves),
+        (false, true) => Stipulation::Selfmate(moves),
+        (true, true) => return Err(Error::msg("A problem is a helpmate or a selfmate")),
+    };
+    let board = game.get_board();
+    let colour = game.get_side_to_move();
+    let quiet = arguments.has(&QUIET);
+
+    let start = Instant::now();
+    let solution = problems::solve(&fen::parse_fen(&text)?, stipulation)?;
+    let keys: Vec<&str> = solution.get_keys().iter().map(|k| k.get_san()).collect();
+    if !quiet {
+        println!("{}, {} to play\n", stipulation, colour);
+        let tree = solution.format(game.get_move_number());
+        if !tree.is_empty() {
+            println!("{}", tree);
+        }
+    }
+    match solution.get_shortest() {
+        None => println!("No solution"),
+        Some(_) if solution.is_sound() => println!("Sound, key {}", keys.join(" ")),
+        Some(shortest) => {
+            println!(
+                "Cooked, {} solutions, keys {}",
+                solution.count_solutions(),
+                keys.join(" ")
+            );
+            if shortest < stipulation.get_moves() {
+                println!(
+                    "Short solution in {} move{}",
+                    shortest,
+                    if shortest == 1 { "" } else { "s" }
+                );
+            }
+        }
+    }
+    if !quiet {
+        println!(
+            "{} nodes in {:.2}s",
+            solution.get_nodes(),
+            start.elapsed().as_secs_f32()
+        );
+    }
+
+    if arguments.has(&SEARCH) {
+        let Stipulation::Mate(moves) = stipulation else {
+            return Err(Error::msg("Only direct mates can be checked with a search"));
+        };
+        let options = arguments.get_search_options()?;
+        let lines = game.think(&options, 2 * moves - 1);
+        let Some(line) = lines.first() else {
+            println!("Search: no legal move");
+            return Ok(());
+        };
+        let Some(play) = line.get_first_move() else {
+            return Ok(());
+        };
+        let evaluation = line.get_evaluation();
+        let found = is_mate(evaluation) && get_mate_distance(evaluation) <= moves as i32;
+        let key = solution
+            .get_keys()
+            .iter()
+            .any(|k| uci::to_uci(&k.get_move()) == uci::to_uci(&play));
+        println!(
+            "Search: {} {}, {}",
+            san::to_san(board, &play),
+            format_evaluation(evaluation),
+            match (found, key) {
+                (true, true) => "mate found with a key",
+                (true, false) => "mate found without a key",
+                (false, _) => "mate not found",
+            }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::io;
+
+use anyhow::Error;
+
+use crust::protocol::uci::UciEngine;
+use crust::protocol::xboard::XboardEngine;
+
+use crate::cli::{Arguments, Command, DEPTH, HELP, THREADS};
+
+pub const UCI: Command = Command {
+    name: "uci",
+    arguments: "",
+    summary: "Talk to a GUI with the Universal Chess Interface",
+    flags: &[DEPTH, THREADS, HELP],
+    run: uci,
+};
+
+pub const XBOARD: Command = Command {
+    name: "xboard",
+    arguments: "",
+    summary: "Talk to a GUI with the XBoard/WinBoard protocol",
+    flags: &[DEPTH, THREADS, HELP],
+    run: xboard,
+};
+
+fn uci(arguments: &Arguments) -> Result<(), Error> {
+    let depth = arguments.get_number(&DEPTH)?.unwrap_or(4);
+    UciEngine::new(arguments.get_search_options()?, depth).run(io::stdin().lock(), io::stdout())
+}
+
+fn xboard(arguments: &Arguments) -> Result<(), Error> {
+    let depth = arguments.get_number(&DEPTH)?.unwrap_or(4);
+    XboardEngine::new(arguments.get_search_options()?, depth)
+        .run(io::stdin().lock(), &mut io::stdout())
+}
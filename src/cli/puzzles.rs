@@ -0,0 +1,133 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crust::analysis::puzzles::{self, Puzzle};
+use crust::analysis::training::{self, Outcome, Progress};
+use crust::formats::pgn;
+
+use crate::cli::{Arguments, Command, Flag, DEPTH, HELP, QUIET, THREADS};
+
+const SECONDS: Flag = Flag::new("--time", "<seconds>", "Search time per position").with_short("-t");
+const OUTPUT: Flag = Flag::new(
+    "--output",
+    "<file>",
+    "Write the puzzles to this file instead of printing them",
+)
+.with_short("-o");
+const PROGRESS: Flag = Flag::new(
+    "--progress",
+    "<file>",
+    "Where to keep the progress, crust-progress.csv by default",
+);
+const COUNT: Flag =
+    Flag::new("--count", "<puzzles>", "Puzzles per session, 10 by default").with_short("-n");
+const STATISTICS: Flag = Flag::new("--stats", "", "Only print the success rates per theme");
+
+pub const PUZZLES: Command = Command {
+    name: "puzzles",
+    arguments: "<games.pgn>",
+    summary: "Find the positions of games where a single move wins",
+    flags: &[DEPTH, SECONDS, THREADS, OUTPUT, QUIET, HELP],
+    run: extract,
+};
+
+pub const TRAIN: Command = Command {
+    name: "train",
+    arguments: "<puzzles.csv|positions.epd>",
+    summary: "Solve puzzles, failed ones coming back until they are solved",
+    flags: &[PROGRESS, COUNT, STATISTICS, HELP],
+    run: train,
+};
+
+fn extract(arguments: &Arguments) -> Result<(), Error> {
+    let [path] = &arguments.get_positional()[..] else {
+        return Err(Error::msg("Usage: crust puzzles <games.pgn>"));
+    };
+    let mut options = arguments.get_search_options()?;
+    options.set_move_time(arguments.get_number(&SECONDS)?);
+    let depth = arguments.get_number(&DEPTH)?.unwrap_or(3);
+    let output = arguments.get(&OUTPUT);
+    let quiet = arguments.has(&QUIET);
+
+    let games = pgn::read_file(Path::new(path))?;
+    let mut found: Vec<Puzzle> = vec![];
+    for (index, record) in games.iter().enumerate() {
+        let tree =
+            pgn::read_tree(record).map_err(|e| Error::msg(format!("Game {}: {}", index + 1, e)))?;
+        if !tree.get_variant().is_standard() {
+            eprintln!(
+                "Game {}: skipped, {} isn't supported",
+                index + 1,
+                tree.get_variant()
+            );
+            continue;
+        }
+        let puzzles = puzzles::find_puzzles(&tree, &options, depth, &(index + 1).to_string())?;
+        if output.is_some() && !quiet {
+            println!("Game {}: {} puzzles", index + 1, puzzles.len());
+        }
+        found.extend(puzzles);
+    }
+
+    match output {
+        Some(path) => fs::write(path, puzzles::write_puzzles(&found))
+            .map_err(|e| Error::msg(format!("Cannot write {}: {}", path, e)))?,
+        None => print!("{}", puzzles::write_puzzles(&found)),
+    }
+    if !quiet {
+        eprintln!("{} puzzles in {} games", found.len(), games.len());
+    }
+    Ok(())
+}
+
+fn train(arguments: &Arguments) -> Result<(), Error> {
+    let [path] = &arguments.get_positional()[..] else {
+        return Err(Error::msg("Usage: crust train <puzzles.csv|positions.epd>"));
+    };
+    let progress_path = Path::new(arguments.get(&PROGRESS).unwrap_or("crust-progress.csv"));
+    let mut progress = Progress::open(progress_path)?;
+
+    if !arguments.has(&STATISTICS) {
+        let exercises = training::read_exercises(Path::new(path))?;
+        let today = training::get_today();
+        let selected = progress.select(
+            &exercises,
+            today,
+            arguments.get_number(&COUNT)?.unwrap_or(10),
+        );
+        if selected.is_empty() {
+            println!("No puzzles to solve today");
+        }
+
+        let (mut input, mut output) = (io::stdin().lock(), io::stdout());
+        let mut solved = 0;
+        for (index, exercise) in selected.iter().enumerate() {
+            let outcome = training::solve(exercise, &mut input, &mut output)?;
+            if outcome == Outcome::Quit {
+                break;
+            }
+            // Puzzles solved with hints come back like failed ones
+            progress.add_attempt(exercise.get_puzzle(), outcome == Outcome::Solved, today);
+            progress.save(progress_path)?;
+            if outcome == Outcome::Solved {
+                solved += 1;
+            }
+            println!("{}/{} solved", solved, index + 1);
+        }
+    }
+
+    println!("\nSuccess rates:");
+    for (theme, solved, attempts) in progress.get_theme_rates() {
+        println!(
+            "  {:<16} {:>4}/{:<4} {:>3.0}%",
+            theme,
+            solved,
+            attempts,
+            100.0 * solved as f32 / attempts.max(1) as f32
+        );
+    }
+    Ok(())
+}
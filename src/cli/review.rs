@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crust::analysis::review::{self, Classification, MoveReview, Summary};
+use crust::formats::pgn;
+use crust::Colour;
+
+use crate::cli::{Arguments, Command, Flag, DEPTH, HELP, QUIET, THREADS};
+
+const SECONDS: Flag = Flag::new("--time", "<seconds>", "Search time per position").with_short("-t");
+const GAME: Flag = Flag::new("--game", "<number>", "Only review this game of the file");
+const OUTPUT: Flag = Flag::new(
+    "--output",
+    "<file>",
+    "Write the games annotated with the mistakes",
+)
+.with_short("-o");
+
+pub const REVIEW: Command = Command {
+    name: "review",
+    arguments: "<games.pgn>",
+    summary: "Find the mistakes of each player and rate their accuracy",
+    flags: &[DEPTH, SECONDS, GAME, THREADS, OUTPUT, QUIET, HELP],
+    run: review,
+};
+
+fn review(arguments: &Arguments) -> Result<(), Error> {
+    let [path] = &arguments.get_positional()[..] else {
+        return Err(Error::msg("Usage: crust review <games.pgn>"));
+    };
+    let mut options = arguments.get_search_options()?;
+    options.set_move_time(arguments.get_number(&SECONDS)?);
+    let depth = arguments.get_number(&DEPTH)?.unwrap_or(2);
+    let quiet = arguments.has(&QUIET);
+
+    let games = pgn::read_file(Path::new(path))?;
+    let selected: Option<usize> = arguments.get_number(&GAME)?;
+    if selected.is_some_and(|number| number == 0 || number > games.len()) {
+        return Err(Error::msg(format!(
+            "No game {} in {}",
+            selected.unwrap_or(0),
+            path
+        )));
+    }
+
+    let mut annotated = String::new();
+    for (index, record) in games.iter().enumerate() {
+        if selected.is_some_and(|number| number != index + 1) {
+            continue;
+        }
+        let tree =
+            pgn::read_tree(record).map_err(|e| Error::msg(format!("Game {}: {}", index + 1, e)))?;
+        let names = [
+            record.get_tag("White").unwrap_or("White"),
+            record.get_tag("Black").unwrap_or("Black"),
+        ];
+        println!(
+            "Game {}: {} - {} {}",
+            index + 1,
+            names[0],
+            names[1],
+            record.get_result()
+        );
+
+        let reviews = review::review_game(&tree, &options, depth, &mut |review| {
+            if !quiet {
+                println!("{}", format_review(review));
+            }
+        })?;
+        for (colour, name) in [Colour::White, Colour::Black].iter().zip(names) {
+            println!("{}", format_summary(name, &Summary::new(&reviews, *colour)));
+        }
+        println!();
+
+        let mut tags = record.get_tags().clone();
+        tags.retain(|(name, _)| name != "Annotator");
+        tags.push((
+            String::from("Annotator"),
+            format!("Crust {}", env!("CARGO_PKG_VERSION")),
+        ));
+        let annotated_tree = review::annotate(&tree, &reviews);
+        annotated.push_str(&pgn::write_game(
+            &tags,
+            &annotated_tree,
+            record.get_result(),
+        ));
+    }
+
+    if let Some(path) = arguments.get(&OUTPUT) {
+        fs::write(path, annotated)
+            .map_err(|e| Error::msg(format!("Cannot write {}: {}", path, e)))?;
+    }
+    Ok(())
+}
+
+/// "12... Nf6          133 cp  inaccuracy, Nc6 was best"
+fn format_review(review: &MoveReview) -> String {
+    let number = match review.get_colour() {
+        Colour::White => format!("{}.", review.get_turn()),
+        Colour::Black => format!("{}...", review.get_turn()),
+    };
+    let mut text = format!(
+        "{:>6} {:<8} {:>6.0} cp  {}",
+        number,
+        review.get_san(),
+        review.get_loss(),
+        review.get_classification()
+    );
+    if !matches!(
+        review.get_classification(),
+        Classification::Best | Classification::Good
+    ) {
+        text.push_str(&format!(", {} was best", review.get_best_san()));
+    }
+    text
+}
+
+fn format_summary(name: &str, summary: &Summary) -> String {
+    format!(
+        "{}: {:.0} cp average loss, {:.1}% accuracy, inaccuracies/mistakes/blunders {}/{}/{}",
+        name,
+        summary.get_average_loss(),
+        summary.get_accuracy(),
+        summary.get_count(Classification::Inaccuracy),
+        summary.get_count(Classification::Mistake),
+        summary.get_count(Classification::Blunder)
+    )
+}
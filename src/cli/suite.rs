@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Error;
+
+use crust::analysis::test_suite::{self, TestPosition, TestResult};
+
+use crate::cli::{Arguments, Command, Flag, DEPTH, HELP, QUIET, THREADS};
+
+/// Depth searched when only a time is given, more than any search reaches in practice.
+const MAX_DEPTH: u8 = 64;
+
+const SECONDS: Flag = Flag::new(
+    "--time",
+    "<seconds>",
+    "Search time per position, 1 by default without a depth",
+)
+.with_short("-t");
+
+pub const EPDTEST: Command = Command {
+    name: "epdtest",
+    arguments: "<suite.epd>",
+    summary: "Run a test suite with bm, am or scored c0 moves and count the solved positions",
+    flags: &[DEPTH, SECONDS, THREADS, QUIET, HELP],
+    run: run_suite,
+};
+
+fn run_suite(arguments: &Arguments) -> Result<(), Error> {
+    let [path] = &arguments.get_positional()[..] else {
+        return Err(Error::msg("Usage: crust epdtest <suite.epd>"));
+    };
+    let (positions, skipped) = test_suite::read_suite(Path::new(path))?;
+    for error in &skipped {
+        eprintln!("Skipped: {}", error);
+    }
+    if positions.is_empty() {
+        return Err(Error::msg(format!("No positions to test in {}", path)));
+    }
+
+    let mut options = arguments.get_search_options()?;
+    let depth: Option<u8> = arguments.get_number(&DEPTH)?;
+    let seconds: Option<f32> = arguments.get_number(&SECONDS)?;
+    options.set_move_time(seconds.or(depth.map_or(Some(1.0), |_| None)));
+    let quiet = arguments.has(&QUIET);
+
+    if !quiet {
+        println!(
+            "{:<20} {:<24} {:<8} {:<6} {:>6} {:>5} {:>10} {:>8}",
+            "id", "expected", "found", "result", "points", "depth", "nodes", "solved"
+        );
+    }
+    let mut results = vec![];
+    for position in &positions {
+        let result = test_suite::run_position(position, &options, depth.unwrap_or(MAX_DEPTH))?;
+        if !quiet {
+            println!("{}", format_result(position, &result));
+        }
+        results.push(result);
+    }
+
+    let solved: Vec<&TestResult> = results.iter().filter(|r| r.is_solved()).collect();
+    println!(
+        "\nSolved {}/{} ({:.1}%)",
+        solved.len(),
+        results.len(),
+        100.0 * solved.len() as f32 / results.len() as f32
+    );
+    if !solved.is_empty() {
+        let total: Duration = solved.iter().filter_map(|r| r.get_solved_after()).sum();
+        println!(
+            "Average time to solution {:.2}s",
+            total.as_secs_f32() / solved.len() as f32
+        );
+    }
+    let max_points: u32 = positions.iter().map(|p| p.get_max_points()).sum();
+    if max_points > 0 {
+        let points: u32 = results.iter().map(|r| r.get_points()).sum();
+        println!(
+            "Score {}/{} ({:.1}%)",
+            points,
+            max_points,
+            100.0 * points as f32 / max_points as f32
+        );
+    }
+    Ok(())
+}
+
+fn format_result(position: &TestPosition, result: &TestResult) -> String {
+    let solved_after = result.get_solved_after().map_or(String::from("-"), |time| {
+        format!("{:.2}s", time.as_secs_f32())
+    });
+    let points = match position.get_max_points() {
+        0 => String::from("-"),
+        _ => result.get_points().to_string(),
+    };
+    format!(
+        "{:<20} {:<24} {:<8} {:<6} {:>6} {:>5} {:>10} {:>8}",
+        position.get_id(),
+        position.format_expected(),
+        result.get_san(),
+        if result.is_solved() { "ok" } else { "failed" },
+        points,
+        result.get_depth(),
+        result.get_nodes(),
+        solved_after
+    )
+}
@@ -0,0 +1,368 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::Error;
+
+use crate::competition::stats::{Score, Sprt, Verdict};
+use crate::formats::{fen, pgn, san};
+use crate::game::chess960;
+use crate::game::clock::TimeControl;
+use crate::game::game::Game;
+use crate::game::search::SearchOptions;
+use crate::model::piece::color::Colour;
+use crate::player::player::parse_player;
+
+/// Starting position of a game: a FEN record and moves in SAN played from it.
+#[derive(Debug, Clone)]
+pub struct Opening {
+    fen: String,
+    moves: Vec<String>,
+}
+
+impl Opening {
+    pub fn new(fen: String, moves: Vec<String>) -> Opening {
+        Opening { fen, moves }
+    }
+
+    /// Game after the opening moves, checking they are legal.
+    pub fn start_game(&self) -> Result<Game, Error> {
+        let mut game = Game::from_fen(&self.fen)?;
+        for text in &self.moves {
+            let play = san::parse_san(game.get_board(), game.get_side_to_move(), text)
+                .ok_or(Error::msg(format!("Illegal opening move {}", text)))?;
+            game.make_move(play)?;
+        }
+
+        Ok(game)
+    }
+}
+
+impl Default for Opening {
+    fn default() -> Opening {
+        Opening::new(String::from(fen::START_FEN), vec![])
+    }
+}
+
+/// Read openings from a PGN file (the main line of each game, from its FEN tag if any), or from a
+/// file with one FEN or EPD record per line.
+pub fn read_openings(path: &Path) -> Result<Vec<Opening>, Error> {
+    let openings: Vec<Opening> = match path.extension().is_some_and(|e| e == "pgn") {
+        true => pgn::read_file(path)?
+            .iter()
+            .map(|game| {
+                let fen = game.get_tag("FEN").unwrap_or(fen::START_FEN);
+                Opening::new(fen.to_string(), game.get_moves().clone())
+            })
+            .collect(),
+        false => fs::read_to_string(path)
+            .map_err(|e| Error::msg(format!("Cannot read {}: {}", path.display(), e)))?
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                // EPD operations follow the four first fields instead of the move counters
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let is_fen =
+                    fields.len() == 6 && fields[4..].iter().all(|f| f.parse::<u8>().is_ok());
+                let length = if is_fen { 6 } else { fields.len().min(4) };
+                Opening::new(fields[..length].join(" "), vec![])
+            })
+            .collect(),
+    };
+    if openings.is_empty() {
+        return Err(Error::msg(format!("No openings in {}", path.display())));
+    }
+    for opening in &openings {
+        opening.start_game()?;
+    }
+
+    Ok(openings)
+}
+
+/// All the Chess960 starting positions, in random order.
+pub fn get_chess960_openings() -> Vec<Opening> {
+    chess960::get_shuffled_indexes()
+        .into_iter()
+        .filter_map(|index| chess960::get_fen(index).ok())
+        .map(|fen| Opening::new(fen, vec![]))
+        .collect()
+}
+
+/// Settings of a match, shared by all its games.
+#[derive(Debug, Clone)]
+pub struct MatchOptions {
+    games: usize,
+    concurrency: usize,
+    time_control: Option<TimeControl>,
+    openings: Vec<Opening>,
+    sprt: Option<Sprt>,
+    pgn: Option<String>,
+}
+
+impl MatchOptions {
+    pub fn new() -> MatchOptions {
+        MatchOptions {
+            games: 10,
+            concurrency: 1,
+            time_control: None,
+            openings: vec![Opening::default()],
+            sprt: None,
+            pgn: None,
+        }
+    }
+
+    pub fn get_games(&self) -> usize {
+        self.games
+    }
+
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn get_time_control(&self) -> Option<&TimeControl> {
+        self.time_control.as_ref()
+    }
+
+    pub fn get_openings(&self) -> &Vec<Opening> {
+        &self.openings
+    }
+
+    pub fn get_sprt(&self) -> Option<&Sprt> {
+        self.sprt.as_ref()
+    }
+
+    pub fn get_pgn(&self) -> Option<&str> {
+        self.pgn.as_deref()
+    }
+
+    pub fn set_games(&mut self, games: usize) {
+        self.games = games;
+    }
+
+    /// Games played at the same time.
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+
+    pub fn set_time_control(&mut self, time_control: Option<TimeControl>) {
+        self.time_control = time_control;
+    }
+
+    /// Openings played in turn, each one twice with the colours reversed.
+    pub fn set_openings(&mut self, openings: Vec<Opening>) {
+        if !openings.is_empty() {
+            self.openings = openings;
+        }
+    }
+
+    /// Stop the match as soon as the test is conclusive.
+    pub fn set_sprt(&mut self, sprt: Option<Sprt>) {
+        self.sprt = sprt;
+    }
+
+    /// File to write all the games to.
+    pub fn set_pgn(&mut self, pgn: Option<String>) {
+        self.pgn = pgn;
+    }
+}
+
+impl Default for MatchOptions {
+    fn default() -> MatchOptions {
+        MatchOptions::new()
+    }
+}
+
+/// Search settings for the engines of a game, sharing the cores with the other games played at
+/// the same time.
+pub fn get_search_options(concurrency: usize) -> SearchOptions {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut options = SearchOptions::new();
+    options.set_threads((cores / concurrency.max(1)).max(1));
+    options
+}
+
+/// Play a game between two players from the given opening, returning it with the names of White
+/// and Black.
+pub fn play_game(
+    white: &str,
+    black: &str,
+    opening: &Opening,
+    time_control: Option<&TimeControl>,
+    options: &SearchOptions,
+) -> Result<(Game, String, String), Error> {
+    let mut white = parse_player(white, options)?;
+    let mut black = parse_player(black, options)?;
+    let mut game = opening.start_game()?;
+    if let Some(time_control) = time_control {
+        game.set_time_control(time_control.clone());
+    }
+    game.set_verbose(false);
+    game.play(white.as_mut(), black.as_mut());
+
+    Ok((game, white.get_name(), black.get_name()))
+}
+
+/// Points scored by the given side in a game with the given result.
+pub fn get_points(result: &str, colour: Colour) -> f32 {
+    let white = match result {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        _ => 0.5,
+    };
+    match colour {
+        Colour::White => white,
+        Colour::Black => 1.0 - white,
+    }
+}
+
+/// Tag pairs of a game played in a match or tournament.
+pub fn get_tags(
+    event: &str,
+    round: usize,
+    white: &str,
+    black: &str,
+    game: &Game,
+) -> Vec<(String, String)> {
+    let mut tags = vec![
+        (String::from("Event"), event.to_string()),
+        (String::from("Round"), round.to_string()),
+        (String::from("White"), white.to_string()),
+        (String::from("Black"), black.to_string()),
+        (String::from("Result"), game.get_result().to_string()),
+    ];
+    if let Some(time_control) = game.get_clock(Colour::White).get_time_control() {
+        tags.push((String::from("TimeControl"), time_control.to_string()));
+    }
+    tags
+}
+
+/// Play a match between two players, described as for `parse_player`, and report its score for
+/// the first one.
+pub fn play_match(first: &str, second: &str, options: &MatchOptions) -> Result<Score, Error> {
+    let search_options = get_search_options(options.concurrency);
+    let next_game = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let score = Mutex::new(Score::new());
+    let games = Mutex::new(vec![]);
+    let errors = Mutex::new(vec![]);
+
+    thread::scope(|scope| {
+        for _ in 0..options.concurrency.min(options.games) {
+            scope.spawn(|| loop {
+                let index = next_game.fetch_add(1, Ordering::SeqCst);
+                if index >= options.games || stop.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                // Each opening is played twice, the players swapping colours
+                let opening = &options.openings[(index / 2) % options.openings.len()];
+                let (white, black, colour) = match index % 2 {
+                    0 => (first, second, Colour::White),
+                    _ => (second, first, Colour::Black),
+                };
+                let result = play_game(
+                    white,
+                    black,
+                    opening,
+                    options.time_control.as_ref(),
+                    &search_options,
+                );
+                let (game, white, black) = match result {
+                    Ok(played) => played,
+                    Err(error) => {
+                        errors.lock().unwrap().push(error);
+                        stop.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                };
+
+                let mut score = score.lock().unwrap();
+                score.add_points(get_points(game.get_result(), colour));
+                let (names, ratio) = match colour {
+                    Colour::White => ((&white, &black), score.get_ratio()),
+                    Colour::Black => ((&black, &white), score.get_ratio()),
+                };
+                println!(
+                    "Game {} ({} vs {}): {} | Score of {} vs {}: {} - {} - {} [{:.3}] {}",
+                    index + 1,
+                    white,
+                    black,
+                    game.get_result(),
+                    names.0,
+                    names.1,
+                    score.get_wins(),
+                    score.get_losses(),
+                    score.get_draws(),
+                    ratio,
+                    score.get_games()
+                );
+                if let Some(sprt) = &options.sprt {
+                    if let Some(verdict) = sprt.get_verdict(&score) {
+                        println!("SPRT: {}", format_verdict(verdict));
+                        stop.store(true, Ordering::SeqCst);
+                    }
+                }
+                drop(score);
+
+                let tags = get_tags("Engine match", index + 1, &white, &black, &game);
+                let text = pgn::write_game(&tags, game.get_tree(), game.get_result());
+                games.lock().unwrap().push((index, text));
+            });
+        }
+    });
+
+    if let Some(error) = errors.into_inner().unwrap().pop() {
+        return Err(error);
+    }
+    if let Some(path) = &options.pgn {
+        let mut games = games.into_inner().unwrap();
+        games.sort_by_key(|(index, _)| *index);
+        let text: Vec<String> = games.into_iter().map(|(_, text)| text).collect();
+        fs::write(path, text.concat())
+            .map_err(|e| Error::msg(format!("Cannot write {}: {}", path, e)))?;
+    }
+
+    Ok(score.into_inner().unwrap())
+}
+
+/// Summary of a match score: wins, draws and losses, Elo difference and SPRT status.
+pub fn format_report(score: &Score, sprt: Option<&Sprt>) -> String {
+    let (elo, error) = score.get_elo();
+    let mut report = format!(
+        "Games: {}, wins: {}, draws: {}, losses: {}, points: {:.1} ({:.1}%)\nElo difference: {:.1} +/- {:.1}",
+        score.get_games(),
+        score.get_wins(),
+        score.get_draws(),
+        score.get_losses(),
+        score.get_points(),
+        100.0 * score.get_ratio(),
+        elo,
+        error
+    );
+    if let Some(sprt) = sprt {
+        let (lower, upper) = sprt.get_bounds();
+        report.push_str(&format!(
+            "\nSPRT ({}, {}): LLR {:.2} [{:.2}, {:.2}], {}",
+            sprt.get_elo0(),
+            sprt.get_elo1(),
+            sprt.get_llr(score),
+            lower,
+            upper,
+            match sprt.get_verdict(score) {
+                Some(verdict) => format_verdict(verdict),
+                None => String::from("inconclusive"),
+            }
+        ));
+    }
+    report
+}
+
+fn format_verdict(verdict: Verdict) -> String {
+    match verdict {
+        Verdict::Accepted => String::from("H1 accepted"),
+        Verdict::Rejected => String::from("H0 accepted"),
+    }
+}
@@ -0,0 +1,3 @@
+pub mod engine_match;
+pub mod stats;
+pub mod tournament;
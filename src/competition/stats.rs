@@ -0,0 +1,234 @@
+use anyhow::Error;
+
+/// Quantile of the normal distribution for a two-sided 95% confidence interval.
+const Z_95: f32 = 1.959964;
+
+/// Wins, draws and losses of a player.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Score {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Score {
+    pub fn new() -> Score {
+        Score::default()
+    }
+
+    /// Count a game from its points for the player: 1, 0.5 or 0.
+    pub fn add_points(&mut self, points: f32) {
+        match points {
+            p if p > 0.75 => self.wins += 1,
+            p if p > 0.25 => self.draws += 1,
+            _ => self.losses += 1,
+        }
+    }
+
+    pub fn get_wins(&self) -> u32 {
+        self.wins
+    }
+
+    pub fn get_draws(&self) -> u32 {
+        self.draws
+    }
+
+    pub fn get_losses(&self) -> u32 {
+        self.losses
+    }
+
+    pub fn get_games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn get_points(&self) -> f32 {
+        self.wins as f32 + self.draws as f32 / 2.0
+    }
+
+    /// Average points per game, between 0 and 1.
+    pub fn get_ratio(&self) -> f32 {
+        match self.get_games() {
+            0 => 0.5,
+            games => self.get_points() / games as f32,
+        }
+    }
+
+    /// Variance of the points of a single game.
+    fn get_variance(&self) -> f32 {
+        let games = self.get_games() as f32;
+        if games == 0.0 {
+            return 0.0;
+        }
+        let ratio = self.get_ratio();
+        (self.wins as f32 * (1.0 - ratio).powi(2)
+            + self.draws as f32 * (0.5 - ratio).powi(2)
+            + self.losses as f32 * ratio.powi(2))
+            / games
+    }
+
+    /// Elo difference matching the score, with the half-width of its 95% confidence interval.
+    /// Both are infinite when every game has been won, or lost.
+    pub fn get_elo(&self) -> (f32, f32) {
+        let ratio = self.get_ratio();
+        let deviation = (self.get_variance() / self.get_games().max(1) as f32).sqrt();
+        let low = elo_difference(ratio - Z_95 * deviation);
+        let high = elo_difference(ratio + Z_95 * deviation);
+
+        let error = match high - low {
+            width if width.is_nan() => f32::INFINITY,
+            width => width / 2.0,
+        };
+
+        (elo_difference(ratio), error)
+    }
+}
+
+/// Elo difference expected between two players when one scores the given ratio of points.
+pub fn elo_difference(ratio: f32) -> f32 {
+    match ratio {
+        r if r <= 0.0 => f32::NEG_INFINITY,
+        r if r >= 1.0 => f32::INFINITY,
+        r => 400.0 * (r / (1.0 - r)).log10(),
+    }
+}
+
+/// Expected ratio of points for a player with the given Elo advantage.
+pub fn expected_ratio(elo: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf(-elo / 400.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The player is at least elo1 stronger.
+    Accepted,
+    /// The player is at most elo0 stronger.
+    Rejected,
+}
+
+/// Sequential probability ratio test between the hypotheses that the player is elo0 (H0) or elo1
+/// (H1) stronger than its opponent, with false positive rate alpha and false negative rate beta.
+#[derive(Debug, Clone, Copy)]
+pub struct Sprt {
+    elo0: f32,
+    elo1: f32,
+    alpha: f32,
+    beta: f32,
+}
+
+impl Sprt {
+    pub fn new(elo0: f32, elo1: f32, alpha: f32, beta: f32) -> Result<Sprt, Error> {
+        if elo0 >= elo1 {
+            return Err(Error::msg("SPRT elo0 must be lower than elo1"));
+        }
+        if !(0.0..0.5).contains(&alpha) || !(0.0..0.5).contains(&beta) || alpha * beta == 0.0 {
+            return Err(Error::msg("SPRT alpha and beta must be between 0 and 0.5"));
+        }
+
+        Ok(Sprt {
+            elo0,
+            elo1,
+            alpha,
+            beta,
+        })
+    }
+
+    pub fn get_elo0(&self) -> f32 {
+        self.elo0
+    }
+
+    pub fn get_elo1(&self) -> f32 {
+        self.elo1
+    }
+
+    /// Log-likelihood ratio below which H0 is accepted, and above which H1 is.
+    pub fn get_bounds(&self) -> (f32, f32) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log-likelihood ratio of H1 against H0, with the normal approximation of the score.
+    pub fn get_llr(&self, score: &Score) -> f32 {
+        let variance = score.get_variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let s0 = expected_ratio(self.elo0);
+        let s1 = expected_ratio(self.elo1);
+
+        score.get_games() as f32 * (s1 - s0) * (2.0 * score.get_ratio() - s0 - s1)
+            / (2.0 * variance)
+    }
+
+    /// Outcome of the test, if the score is conclusive.
+    pub fn get_verdict(&self, score: &Score) -> Option<Verdict> {
+        let llr = self.get_llr(score);
+        let (lower, upper) = self.get_bounds();
+        if llr >= upper {
+            Some(Verdict::Accepted)
+        } else if llr <= lower {
+            Some(Verdict::Rejected)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(wins: u32, draws: u32, losses: u32) -> Score {
+        let mut score = Score::new();
+        for (count, points) in [(wins, 1.0), (draws, 0.5), (losses, 0.0)] {
+            for _ in 0..count {
+                score.add_points(points);
+            }
+        }
+        score
+    }
+
+    #[test]
+    fn elo_of_a_ratio() {
+        assert!((elo_difference(0.75) - 190.85).abs() < 0.01);
+        assert_eq!(elo_difference(0.5), 0.0);
+        assert_eq!(elo_difference(1.0), f32::INFINITY);
+        assert!((expected_ratio(190.85) - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn elo_of_a_score() {
+        let (elo, error) = score(50, 0, 50).get_elo();
+        assert_eq!(elo, 0.0);
+        // Elo of 0.5 + 1.96 × 0.05, the interval being symmetric
+        assert!((error - 69.0).abs() < 0.1, "{}", error);
+
+        let (elo, error) = score(35, 5, 10).get_elo();
+        assert!((elo - 190.85).abs() < 0.01);
+        assert!(error.is_finite() && error > 0.0);
+
+        assert_eq!(score(3, 0, 0).get_elo().1, f32::INFINITY);
+    }
+
+    #[test]
+    fn sprt_bounds() {
+        let (lower, upper) = Sprt::new(0.0, 5.0, 0.05, 0.05).unwrap().get_bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+        assert!(Sprt::new(5.0, 0.0, 0.05, 0.05).is_err());
+    }
+
+    #[test]
+    fn sprt_llr_sign() {
+        let sprt = Sprt::new(0.0, 10.0, 0.05, 0.05).unwrap();
+        // Even scores favour elo0, winning ones elo1
+        assert!(sprt.get_llr(&score(40, 20, 40)) < 0.0);
+        assert!(sprt.get_llr(&score(50, 20, 30)) > 0.0);
+        assert_eq!(
+            sprt.get_verdict(&score(500, 200, 300)),
+            Some(Verdict::Accepted)
+        );
+        assert_eq!(sprt.get_llr(&Score::new()), 0.0);
+    }
+}
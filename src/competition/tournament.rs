@@ -0,0 +1,631 @@
+use std::collections::HashSet;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::Error;
+
+use crate::competition::engine_match::{self, Opening};
+use crate::competition::stats;
+use crate::formats::pgn;
+use crate::game::clock::TimeControl;
+use crate::model::piece::color::Colour;
+
+/// Average of the performance ratings, which are only relative to each other.
+const AVERAGE_RATING: f32 = 1500.0;
+
+/// Largest rating difference granted for a perfect (or null) score, as in FIDE rules.
+const MAX_RATING_DIFFERENCE: f32 = 800.0;
+
+/// How the players are paired each round.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pairing {
+    /// Every player meets every other one once per cycle, with reversed colours in even cycles.
+    RoundRobin { cycles: usize },
+    /// Players with the same score meet, never twice if possible.
+    Swiss { rounds: usize },
+}
+
+/// Settings of a tournament, shared by all its games.
+#[derive(Debug, Clone)]
+pub struct TournamentOptions {
+    pairing: Pairing,
+    concurrency: usize,
+    time_control: Option<TimeControl>,
+    openings: Vec<Opening>,
+    pgn: Option<String>,
+}
+
+impl TournamentOptions {
+    pub fn new() -> TournamentOptions {
+        TournamentOptions {
+            pairing: Pairing::RoundRobin { cycles: 1 },
+            concurrency: 1,
+            time_control: None,
+            openings: vec![Opening::default()],
+            pgn: None,
+        }
+    }
+
+    pub fn get_pairing(&self) -> Pairing {
+        self.pairing
+    }
+
+    pub fn set_pairing(&mut self, pairing: Pairing) {
+        self.pairing = pairing;
+    }
+
+    /// Games played at the same time.
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+
+    pub fn set_time_control(&mut self, time_control: Option<TimeControl>) {
+        self.time_control = time_control;
+    }
+
+    /// Openings played in turn, the same one for all the games of a round.
+    pub fn set_openings(&mut self, openings: Vec<Opening>) {
+        if !openings.is_empty() {
+            self.openings = openings;
+        }
+    }
+
+    /// File to write all the games to.
+    pub fn set_pgn(&mut self, pgn: Option<String>) {
+        self.pgn = pgn;
+    }
+}
+
+impl Default for TournamentOptions {
+    fn default() -> TournamentOptions {
+        TournamentOptions::new()
+    }
+}
+
+/// A game of the tournament, between players given by their indexes. Without Black, White had a
+/// bye and gets a point.
+#[derive(Debug, Clone, Copy)]
+pub struct Encounter {
+    round: usize,
+    white: usize,
+    black: Option<usize>,
+    points: f32,
+}
+
+impl Encounter {
+    pub fn get_round(&self) -> usize {
+        self.round
+    }
+
+    pub fn get_white(&self) -> usize {
+        self.white
+    }
+
+    pub fn get_black(&self) -> Option<usize> {
+        self.black
+    }
+
+    /// Points scored by White.
+    pub fn get_points(&self) -> f32 {
+        self.points
+    }
+
+    /// Opponent of the player, and the points they scored, if they played this game.
+    fn get_result(&self, player: usize) -> Option<(usize, f32)> {
+        let black = self.black?;
+        if self.white == player {
+            Some((black, self.points))
+        } else if black == player {
+            Some((self.white, 1.0 - self.points))
+        } else {
+            None
+        }
+    }
+}
+
+/// Final rank of a player, with their tiebreaks.
+#[derive(Debug, Clone)]
+pub struct Standing {
+    player: usize,
+    points: f32,
+    games: usize,
+    sonneborn_berger: f32,
+    performance: f32,
+}
+
+impl Standing {
+    pub fn get_player(&self) -> usize {
+        self.player
+    }
+
+    pub fn get_points(&self) -> f32 {
+        self.points
+    }
+
+    pub fn get_games(&self) -> usize {
+        self.games
+    }
+
+    /// Sum of the scores of the opponents beaten, and of half the scores of those drawn.
+    pub fn get_sonneborn_berger(&self) -> f32 {
+        self.sonneborn_berger
+    }
+
+    /// Rating matching the results against the opponents, relative to an average of 1500.
+    pub fn get_performance(&self) -> f32 {
+        self.performance
+    }
+}
+
+/// A tournament between players described as for `parse_player`.
+pub struct Tournament {
+    players: Vec<String>,
+    names: Vec<String>,
+    options: TournamentOptions,
+    games: Vec<Encounter>,
+    records: Vec<String>,
+}
+
+impl Tournament {
+    pub fn new(players: Vec<String>, options: TournamentOptions) -> Result<Tournament, Error> {
+        if players.len() < 2 {
+            return Err(Error::msg("A tournament needs at least two players"));
+        }
+
+        Ok(Tournament {
+            names: players.clone(),
+            players,
+            options,
+            games: vec![],
+            records: vec![],
+        })
+    }
+
+    /// Names given by the players themselves, once they have played.
+    pub fn get_names(&self) -> &Vec<String> {
+        &self.names
+    }
+
+    pub fn get_games(&self) -> &Vec<Encounter> {
+        &self.games
+    }
+
+    /// Play all the rounds, then write the games to the PGN file if any.
+    pub fn play(&mut self) -> Result<(), Error> {
+        let rounds = match self.options.pairing {
+            Pairing::RoundRobin { cycles } => {
+                cycles * (self.players.len() + self.players.len() % 2 - 1)
+            }
+            Pairing::Swiss { rounds } => rounds,
+        };
+        for round in 0..rounds {
+            let pairs = match self.options.pairing {
+                Pairing::RoundRobin { .. } => self.pair_round_robin(round),
+                Pairing::Swiss { .. } => self.pair_swiss(),
+            };
+            self.play_round(round, pairs)?;
+        }
+
+        if let Some(path) = &self.options.pgn {
+            fs::write(path, self.records.concat())
+                .map_err(|e| Error::msg(format!("Cannot write {}: {}", path, e)))?;
+        }
+        Ok(())
+    }
+
+    /// Pairs of a round, with the circle method: the first player stays in place while the
+    /// others rotate around the table.
+    fn pair_round_robin(&self, round: usize) -> Vec<(usize, Option<usize>)> {
+        let mut table: Vec<Option<usize>> = (0..self.players.len()).map(Some).collect();
+        if table.len() % 2 == 1 {
+            table.push(None);
+        }
+        let size = table.len();
+        let (cycle, round) = (round / (size - 1), round % (size - 1));
+        table[1..].rotate_right(round);
+
+        (0..size / 2)
+            .filter_map(|board| {
+                let (mut first, mut second) = (table[board], table[size - 1 - board]);
+                if (round + board + cycle) % 2 == 1 {
+                    (first, second) = (second, first);
+                }
+                match (first, second) {
+                    (Some(white), black) => Some((white, black)),
+                    (None, Some(player)) => Some((player, None)),
+                    (None, None) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Pairs of the next Swiss round: the players are sorted by score, the lowest one without a
+    /// bye yet gets it if their number is odd, and the others meet the best ranked opponent they
+    /// haven't met yet.
+    fn pair_swiss(&self) -> Vec<(usize, Option<usize>)> {
+        let standings = self.get_standings();
+        let mut ranking: Vec<usize> = standings.iter().map(|s| s.player).collect();
+        let mut pairs = vec![];
+        if ranking.len() % 2 == 1 {
+            let had_bye = |player: usize| {
+                self.games
+                    .iter()
+                    .any(|g| g.white == player && g.black.is_none())
+            };
+            let index = ranking
+                .iter()
+                .rposition(|p| !had_bye(*p))
+                .unwrap_or(ranking.len() - 1);
+            pairs.push((ranking.remove(index), None));
+        }
+
+        let met: HashSet<(usize, usize)> = self
+            .games
+            .iter()
+            .filter_map(|g| {
+                g.black
+                    .map(|black| (g.white.min(black), g.white.max(black)))
+            })
+            .collect();
+        let opponents = Tournament::pair_players(&ranking, &met)
+            .unwrap_or_else(|| ranking.chunks(2).map(|p| (p[0], p[1])).collect());
+
+        // The player who had White less often gets it
+        let balance = |player: usize| -> i32 {
+            self.games
+                .iter()
+                .filter(|g| g.black.is_some())
+                .map(|g| match (g.white == player, g.black == Some(player)) {
+                    (true, _) => 1,
+                    (_, true) => -1,
+                    _ => 0,
+                })
+                .sum()
+        };
+        for (first, second) in opponents {
+            match balance(first) > balance(second) {
+                true => pairs.push((second, Some(first))),
+                false => pairs.push((first, Some(second))),
+            }
+        }
+        pairs
+    }
+
+    /// Pair the players in ranking order, none of them meeting again.
+    fn pair_players(
+        ranking: &[usize],
+        met: &HashSet<(usize, usize)>,
+    ) -> Option<Vec<(usize, usize)>> {
+        let Some((first, others)) = ranking.split_first() else {
+            return Some(vec![]);
+        };
+        for (index, second) in others.iter().enumerate() {
+            if met.contains(&(*first.min(second), *first.max(second))) {
+                continue;
+            }
+            let mut rest = others.to_vec();
+            rest.remove(index);
+            if let Some(mut pairs) = Tournament::pair_players(&rest, met) {
+                pairs.insert(0, (*first, *second));
+                return Some(pairs);
+            }
+        }
+        None
+    }
+
+    fn play_round(
+        &mut self,
+        round: usize,
+        pairs: Vec<(usize, Option<usize>)>,
+    ) -> Result<(), Error> {
+        let opening = &self.options.openings[round % self.options.openings.len()];
+        let search_options = engine_match::get_search_options(self.options.concurrency);
+        let boards: Vec<(usize, usize)> = pairs
+            .iter()
+            .filter_map(|(white, black)| black.map(|black| (*white, black)))
+            .collect();
+        let next_board = AtomicUsize::new(0);
+        let results = Mutex::new(vec![]);
+        let errors = Mutex::new(vec![]);
+
+        thread::scope(|scope| {
+            for _ in 0..self.options.concurrency.min(boards.len()) {
+                scope.spawn(|| loop {
+                    let board = next_board.fetch_add(1, Ordering::SeqCst);
+                    if board >= boards.len() || !errors.lock().unwrap().is_empty() {
+                        break;
+                    }
+                    let (white, black) = boards[board];
+                    match engine_match::play_game(
+                        &self.players[white],
+                        &self.players[black],
+                        opening,
+                        self.options.time_control.as_ref(),
+                        &search_options,
+                    ) {
+                        Ok(played) => results.lock().unwrap().push((board, played)),
+                        Err(error) => errors.lock().unwrap().push(error),
+                    }
+                });
+            }
+        });
+        if let Some(error) = errors.into_inner().unwrap().pop() {
+            return Err(error);
+        }
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(board, _)| *board);
+        for (board, (game, white_name, black_name)) in results {
+            let (white, black) = boards[board];
+            self.names[white] = white_name;
+            self.names[black] = black_name;
+            self.games.push(Encounter {
+                round,
+                white,
+                black: Some(black),
+                points: engine_match::get_points(game.get_result(), Colour::White),
+            });
+            println!(
+                "Round {}: {} - {} {}",
+                round + 1,
+                self.names[white],
+                self.names[black],
+                game.get_result()
+            );
+
+            let tags = engine_match::get_tags(
+                "Crust tournament",
+                round + 1,
+                &self.names[white],
+                &self.names[black],
+                &game,
+            );
+            self.records
+                .push(pgn::write_game(&tags, game.get_tree(), game.get_result()));
+        }
+        for (player, _) in pairs.iter().filter(|(_, black)| black.is_none()) {
+            println!("Round {}: {} has a bye", round + 1, self.names[*player]);
+            self.games.push(Encounter {
+                round,
+                white: *player,
+                black: None,
+                points: 1.0,
+            });
+        }
+        Ok(())
+    }
+
+    /// Points of each player, byes included.
+    fn get_points(&self) -> Vec<f32> {
+        let mut points = vec![0.0; self.players.len()];
+        for game in &self.games {
+            points[game.white] += game.points;
+            if let Some(black) = game.black {
+                points[black] += 1.0 - game.points;
+            }
+        }
+        points
+    }
+
+    /// Performance ratings, computed again from those of the opponents until they settle.
+    fn get_performances(&self) -> Vec<f32> {
+        let mut ratings = vec![AVERAGE_RATING; self.players.len()];
+        for _ in 0..50 {
+            let mut performances: Vec<f32> = (0..self.players.len())
+                .map(|player| {
+                    let results: Vec<(usize, f32)> = self
+                        .games
+                        .iter()
+                        .filter_map(|g| g.get_result(player))
+                        .collect();
+                    if results.is_empty() {
+                        return AVERAGE_RATING;
+                    }
+                    let games = results.len() as f32;
+                    let opponents: f32 = results.iter().map(|(o, _)| ratings[*o]).sum();
+                    let points: f32 = results.iter().map(|(_, p)| p).sum();
+                    let difference = stats::elo_difference(points / games)
+                        .clamp(-MAX_RATING_DIFFERENCE, MAX_RATING_DIFFERENCE);
+                    opponents / games + difference
+                })
+                .collect();
+            let shift = AVERAGE_RATING - performances.iter().sum::<f32>() / ratings.len() as f32;
+            performances.iter_mut().for_each(|p| *p += shift);
+            ratings = performances;
+        }
+        ratings
+    }
+
+    /// Players from the first to the last: by points, then Sonneborn-Berger score, then
+    /// performance.
+    pub fn get_standings(&self) -> Vec<Standing> {
+        let points = self.get_points();
+        let performances = self.get_performances();
+        let mut standings: Vec<Standing> = (0..self.players.len())
+            .map(|player| {
+                let results: Vec<(usize, f32)> = self
+                    .games
+                    .iter()
+                    .filter_map(|g| g.get_result(player))
+                    .collect();
+                Standing {
+                    player,
+                    points: points[player],
+                    games: results.len(),
+                    sonneborn_berger: results.iter().map(|(o, p)| p * points[*o]).sum(),
+                    performance: performances[player],
+                }
+            })
+            .collect();
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+                .then(b.performance.total_cmp(&a.performance))
+                .then(a.player.cmp(&b.player))
+        });
+        standings
+    }
+
+    /// Standings with the results of each game against the players of each rank: "1" for a win,
+    /// "=" for a draw, "0" for a loss.
+    pub fn format_crosstable(&self) -> String {
+        let standings = self.get_standings();
+        let width = self.names.iter().map(|n| n.len()).max().unwrap_or(0).max(4);
+        let mut ranks = vec![0; self.players.len()];
+        for (rank, standing) in standings.iter().enumerate() {
+            ranks[standing.player] = rank;
+        }
+        let cells: Vec<Vec<String>> = standings
+            .iter()
+            .map(|standing| {
+                let mut cells = vec![String::new(); self.players.len()];
+                cells[ranks[standing.player]] = String::from("*");
+                for game in &self.games {
+                    if let Some((opponent, points)) = game.get_result(standing.player) {
+                        cells[ranks[opponent]].push(match points {
+                            p if p > 0.75 => '1',
+                            p if p > 0.25 => '=',
+                            _ => '0',
+                        });
+                    }
+                }
+                cells
+            })
+            .collect();
+        let cell_width = cells.iter().flatten().map(|c| c.len()).max().unwrap_or(1);
+
+        let mut table = format!(
+            "{:>3}  {:<width$}  {:>6}  {:>5}  {:>6}  {:>5} ",
+            "#", "Name", "Points", "Games", "SB", "Perf"
+        );
+        for rank in 0..standings.len() {
+            table.push_str(&format!(" {:>cell_width$}", rank + 1));
+        }
+        for (rank, standing) in standings.iter().enumerate() {
+            table.push_str(&format!(
+                "\n{:>3}  {:<width$}  {:>6.1}  {:>5}  {:>6.2}  {:>5.0} ",
+                rank + 1,
+                self.names[standing.player],
+                standing.points,
+                standing.games,
+                standing.sonneborn_berger,
+                standing.performance
+            ));
+            for cell in &cells[rank] {
+                table.push_str(&format!(" {:>cell_width$}", cell));
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn create_tournament(players: usize) -> Tournament {
+        let names = (0..players).map(|p| format!("player{}", p)).collect();
+        Tournament::new(names, TournamentOptions::new()).unwrap()
+    }
+
+    fn add_game(
+        tournament: &mut Tournament,
+        round: usize,
+        white: usize,
+        black: usize,
+        points: f32,
+    ) {
+        tournament.games.push(Encounter {
+            round,
+            white,
+            black: Some(black),
+            points,
+        });
+    }
+
+    #[test]
+    fn round_robin_pairs_everyone_once_per_cycle() {
+        for players in [4, 5] {
+            let tournament = create_tournament(players);
+            let rounds = players + players % 2 - 1;
+            let mut colours = HashMap::new();
+            for cycle in 0..2 {
+                let mut met = HashSet::new();
+                for round in cycle * rounds..(cycle + 1) * rounds {
+                    for (white, black) in tournament.pair_round_robin(round) {
+                        let Some(black) = black else {
+                            continue;
+                        };
+                        let pair = (white.min(black), white.max(black));
+                        assert!(met.insert(pair), "{:?} met twice", pair);
+                        // Colours are reversed in the second cycle
+                        match colours.insert(pair, white) {
+                            Some(first) => assert_ne!(first, white, "{:?}", pair),
+                            None => assert_eq!(cycle, 0),
+                        }
+                    }
+                }
+                assert_eq!(met.len(), players * (players - 1) / 2);
+            }
+        }
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let mut tournament = create_tournament(6);
+        let mut met = HashSet::new();
+        for round in 0..3 {
+            for (white, black) in tournament.pair_swiss() {
+                let black = black.unwrap();
+                assert!(met.insert((white.min(black), white.max(black))));
+                // The player with the lowest index wins
+                let points = if white < black { 1.0 } else { 0.0 };
+                add_game(&mut tournament, round, white, black, points);
+            }
+        }
+    }
+
+    #[test]
+    fn swiss_rotates_the_bye() {
+        let mut tournament = create_tournament(5);
+        let mut byes = HashSet::new();
+        for round in 0..5 {
+            for (white, black) in tournament.pair_swiss() {
+                match black {
+                    Some(black) => add_game(&mut tournament, round, white, black, 0.5),
+                    None => {
+                        assert!(byes.insert(white), "second bye for {}", white);
+                        tournament.games.push(Encounter {
+                            round,
+                            white,
+                            black: None,
+                            points: 1.0,
+                        });
+                    }
+                }
+            }
+        }
+        assert_eq!(byes.len(), 5);
+    }
+
+    #[test]
+    fn sonneborn_berger() {
+        // 0 beats 1 and draws with 2, 1 beats 2: 1.5, 1 and 0.5 points
+        let mut tournament = create_tournament(3);
+        add_game(&mut tournament, 0, 0, 1, 1.0);
+        add_game(&mut tournament, 1, 2, 0, 0.5);
+        add_game(&mut tournament, 2, 1, 2, 1.0);
+        let standings = tournament.get_standings();
+        let scores: Vec<(usize, f32, f32)> = standings
+            .iter()
+            .map(|s| (s.get_player(), s.get_points(), s.get_sonneborn_berger()))
+            .collect();
+        assert_eq!(scores, vec![(0, 1.5, 1.25), (1, 1.0, 0.5), (2, 0.5, 0.75)]);
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+/// A record of the Extended Position Description: the four first fields of a FEN record, then
+/// operations such as `bm Nf3 Nc3; id "WAC.001";`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpdRecord {
+    position: String,
+    operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdRecord {
+    pub fn parse(line: &str) -> Result<EpdRecord, Error> {
+        let fields: Vec<&str> = line.trim().splitn(5, ' ').collect();
+        if fields.len() < 4 {
+            return Err(Error::msg(format!("Invalid EPD record: {}", line)));
+        }
+        let operations = fields
+            .get(4)
+            .map_or(vec![], |text| parse_operations(text));
+
+        Ok(EpdRecord {
+            position: fields[..4].join(" "),
+            operations,
+        })
+    }
+
+    /// The position as a FEN record, with the move counters of the "hmvc" and "fmvn" operations.
+    pub fn get_fen(&self) -> String {
+        let counter = |name: &str, default: &str| {
+            self.get_operand(name)
+                .filter(|value| value.parse::<u32>().is_ok())
+                .unwrap_or(default)
+                .to_string()
+        };
+        format!(
+            "{} {} {}",
+            self.position,
+            counter("hmvc", "0"),
+            counter("fmvn", "1")
+        )
+    }
+
+    pub fn get_operations(&self) -> &Vec<(String, Vec<String>)> {
+        &self.operations
+    }
+
+    /// Operands of the first operation with this opcode.
+    pub fn get_operation(&self, opcode: &str) -> Option<&Vec<String>> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands)
+    }
+
+    /// First operand of the operation, e.g. the name given by "id".
+    pub fn get_operand(&self, opcode: &str) -> Option<&str> {
+        self.get_operation(opcode)
+            .and_then(|operands| operands.first())
+            .map(|operand| operand.as_str())
+    }
+}
+
+/// Operations separated by semicolons, each an opcode followed by operands separated by spaces.
+/// Strings between double quotes are single operands, and may contain semicolons.
+fn parse_operations(text: &str) -> Vec<(String, Vec<String>)> {
+    let mut operations = vec![];
+    let mut words: Vec<String> = vec![];
+    let mut word = String::new();
+    let mut in_string = false;
+    for c in text.chars().chain(std::iter::once(';')) {
+        match c {
+            '"' => in_string = !in_string,
+            ';' | ' ' if !in_string => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                if c == ';' && !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    operations
+}
+
+/// Read the records of an EPD file, one per line, skipping empty lines and comments.
+pub fn read_file(path: &Path) -> Result<Vec<EpdRecord>, Error> {
+    fs::read_to_string(path)
+        .map_err(|e| Error::msg(format!("Cannot read {}: {}", path.display(), e)))?
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
+        .map(EpdRecord::parse)
+        .collect()
+}
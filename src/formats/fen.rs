@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+
+use crate::model::board::board::Board;
+use crate::model::board::square::Square;
+use crate::model::piece::bishop::Bishop;
+use crate::model::piece::color::Colour;
+use crate::model::piece::king::King;
+use crate::model::piece::knight::Knight;
+use crate::model::piece::pawn::Pawn;
+use crate::model::piece::piece::Piece;
+use crate::model::piece::queen::Queen;
+use crate::model::piece::rook::Rook;
+use crate::utils;
+use crate::variant::three_check::CHECKS;
+use crate::variant::variant::{Variant, VariantType};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// A position read from a Forsyth-Edwards Notation record.
+pub struct Position {
+    board: Board,
+    colour: Colour,
+    en_passant: Option<Square>,
+    consecutive_moves: u8,
+    turn: u16,
+}
+
+impl Position {
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn into_board(self) -> Board {
+        self.board
+    }
+
+    /// Player to move.
+    pub fn get_colour(&self) -> Colour {
+        self.colour
+    }
+
+    /// Square skipped by a pawn moving two squares, where it could be taken en-passant. The
+    /// board itself ignores it, as the engine doesn't capture en-passant.
+    pub fn get_en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    /// Half-moves since the last capture or pawn move.
+    pub fn get_consecutive_moves(&self) -> u8 {
+        self.consecutive_moves
+    }
+
+    /// Number of the full move to be played.
+    pub fn get_turn(&self) -> u16 {
+        self.turn
+    }
+}
+
+/// Read a FEN record. The move counters are optional, as in EPD records. Castling rights may
+/// also be given as the files of the rooks, as in X-FEN and Shredder-FEN for Chess960.
+pub fn parse_fen(text: &str) -> Result<Position, Error> {
+    parse_variant_fen(text, VariantType::default())
+}
+
+/// Read a FEN record of a position of the variant, whose rules decide where pawns may stand and
+/// which kings are required. In Three-check, the checks may be given as the checks left before
+/// the move counters, e.g. "3+3", or as the checks given after them, e.g. "+0+0".
+pub fn parse_variant_fen(text: &str, variant: VariantType) -> Result<Position, Error> {
+    let invalid = |reason: &str| Error::msg(format!("Invalid FEN {}: {}", text, reason));
+    let mut fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() < 2 {
+        return Err(invalid("missing fields"));
+    }
+    let mut checks = (0, 0);
+    if matches!(variant, VariantType::ThreeCheck(_)) {
+        if let Some(index) = (4..fields.len()).find(|i| fields[*i].contains('+')) {
+            checks = parse_checks(fields[index]).ok_or(invalid("bad check counters"))?;
+            fields.remove(index);
+        }
+    }
+
+    // Pieces, from the 8th rank down
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(invalid("there must be 8 ranks"));
+    }
+    let mut white_pieces: HashMap<Square, Box<dyn Piece>> = HashMap::new();
+    let mut black_pieces: HashMap<Square, Box<dyn Piece>> = HashMap::new();
+    for (index, row) in ranks.iter().enumerate() {
+        let rank = 7 - index as u8;
+        let mut file = 0;
+        for c in row.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                file += empty as u8;
+                continue;
+            }
+            if file > 7 {
+                return Err(invalid("too many squares in a rank"));
+            }
+            let colour = match c.is_ascii_uppercase() {
+                true => Colour::White,
+                false => Colour::Black,
+            };
+            let piece: Box<dyn Piece> = match c.to_ascii_uppercase() {
+                'K' => Box::new(King::new(colour)),
+                'Q' => Box::new(Queen::new(colour)),
+                'R' => Box::new(Rook::new(colour)),
+                'B' => Box::new(Bishop::new(colour)),
+                'N' => Box::new(Knight::new(colour)),
+                'P' if (rank == 0 || rank == 7) && !variant.is_pawn_start(colour, rank) => {
+                    return Err(invalid("pawns can't stand on the first and last ranks"))
+                }
+                'P' => {
+                    let mut pawn = Pawn::new(colour);
+                    pawn.set_first_move(variant.is_pawn_start(colour, rank));
+                    Box::new(pawn)
+                }
+                _ => return Err(invalid("unknown piece")),
+            };
+            match colour {
+                Colour::White => white_pieces.insert(Square::new(rank, file), piece),
+                Colour::Black => black_pieces.insert(Square::new(rank, file), piece),
+            };
+            file += 1;
+        }
+        if file != 8 {
+            return Err(invalid("each rank must have 8 squares"));
+        }
+    }
+    for (colour, pieces) in [
+        (Colour::White, &white_pieces),
+        (Colour::Black, &black_pieces),
+    ] {
+        let kings = pieces.values().filter(|p| p.get_symbol() == 'K').count();
+        if variant.is_royal(colour) && kings != 1 {
+            return Err(invalid("each player must have one king"));
+        }
+    }
+
+    let colour = match fields[1] {
+        "w" => Colour::White,
+        "b" => Colour::Black,
+        _ => return Err(invalid("the player to move must be 'w' or 'b'")),
+    };
+
+    // Kings and rooks keep their first move only with a castling right: "K" and "Q" for the
+    // outermost rooks, or the file of the rook as in Shredder-FEN and X-FEN
+    let castling = fields.get(2).copied().unwrap_or("-");
+    let mut rooks = vec![];
+    for symbol in castling.chars().filter(|c| *c != '-') {
+        let colour = match symbol.is_ascii_uppercase() {
+            true => Colour::White,
+            false => Colour::Black,
+        };
+        let pieces = match colour {
+            Colour::White => &white_pieces,
+            Colour::Black => &black_pieces,
+        };
+        let rank = get_back_rank(colour);
+        let files = |symbol| {
+            pieces
+                .iter()
+                .filter(move |(s, p)| p.get_symbol() == symbol && s.get_rank() == rank)
+                .map(|(s, _)| s.get_file())
+        };
+        // Rights without a king or a rook on the back rank are ignored
+        let Some(king_file) = files('K').next() else {
+            continue;
+        };
+        let rook_file = match symbol.to_ascii_uppercase() {
+            'K' => files('R').filter(|f| *f > king_file).max(),
+            'Q' => files('R').filter(|f| *f < king_file).min(),
+            c @ 'A'..='H' => files('R').find(|f| *f == c as u8 - b'A'),
+            _ => return Err(invalid("unknown castling right")),
+        };
+        rooks.extend(rook_file.map(|file| Square::new(rank, file)));
+    }
+    for pieces in [&mut white_pieces, &mut black_pieces] {
+        for (square, piece) in pieces.iter_mut() {
+            match piece.get_symbol() {
+                'K' => piece.set_first_move(rooks.iter().any(|r| {
+                    r.get_rank() == square.get_rank()
+                        && r.get_rank() == get_back_rank(piece.get_colour())
+                })),
+                'R' => piece.set_first_move(rooks.contains(square)),
+                _ => {}
+            }
+        }
+    }
+
+    let mut board = Board::new(utils::init_squares(), white_pieces, black_pieces);
+    board.set_variant(variant);
+    board.set_checks(Colour::White, checks.0);
+    board.set_checks(Colour::Black, checks.1);
+    if board.is_under_check(colour.get_opposite()) {
+        return Err(invalid("the player who just moved can't be in check"));
+    }
+
+    let en_passant = match fields.get(3).copied().unwrap_or("-") {
+        "-" => None,
+        square => Some(parse_en_passant(square, colour).ok_or(invalid("bad en-passant square"))?),
+    };
+
+    // Move counters, missing in EPD records
+    let consecutive_moves = match fields.get(4) {
+        Some(counter) => counter.parse().map_err(|_| invalid("bad halfmove clock"))?,
+        None => 0,
+    };
+    let turn = match fields.get(5) {
+        Some(counter) => counter
+            .parse()
+            .map_err(|_| invalid("bad fullmove number"))?,
+        None => 1,
+    };
+
+    Ok(Position {
+        board,
+        colour,
+        en_passant,
+        consecutive_moves,
+        turn,
+    })
+}
+
+/// Square skipped by the last pawn of the opponent of the player to move, e.g. "e3" when Black
+/// is to move.
+fn parse_en_passant(text: &str, colour: Colour) -> Option<Square> {
+    let mut chars = text.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?;
+    if chars.next().is_some() || !('a'..='h').contains(&file) {
+        return None;
+    }
+    let expected = match colour {
+        Colour::White => '6',
+        Colour::Black => '3',
+    };
+    (rank == expected).then(|| Square::new(rank as u8 - b'1', file as u8 - b'a'))
+}
+
+/// Checks given by White and Black, from the checks left, e.g. "3+3", or the checks given, e.g.
+/// "+0+0".
+fn parse_checks(text: &str) -> Option<(u8, u8)> {
+    let (given, counters) = match text.strip_prefix('+') {
+        Some(counters) => (true, counters),
+        None => (false, text),
+    };
+    let (white, black) = counters.split_once('+')?;
+    let (white, black): (u8, u8) = (white.parse().ok()?, black.parse().ok()?);
+    if white > CHECKS || black > CHECKS {
+        return None;
+    }
+    match given {
+        true => Some((white, black)),
+        false => Some((CHECKS - white, CHECKS - black)),
+    }
+}
+
+/// FEN record of a position, with X-FEN castling rights: "K" and "Q" unless another rook stands
+/// further on the same side. There is never an en-passant square, as the engine doesn't capture
+/// en-passant. In Three-check, the checks left follow, e.g. "3+3".
+pub fn to_fen(board: &Board, colour: Colour, consecutive_moves: u8, turn: u16) -> String {
+    write_fen(board, colour, consecutive_moves, turn, false)
+}
+
+/// FEN record of a position, with Shredder-FEN castling rights: the files of the rooks.
+pub fn to_shredder_fen(board: &Board, colour: Colour, consecutive_moves: u8, turn: u16) -> String {
+    write_fen(board, colour, consecutive_moves, turn, true)
+}
+
+fn write_fen(
+    board: &Board,
+    colour: Colour,
+    consecutive_moves: u8,
+    turn: u16,
+    shredder: bool,
+) -> String {
+    let mut ranks = vec![];
+    for rank in (0..8).rev() {
+        let mut row = String::new();
+        let mut empty = 0;
+        for file in 0..8 {
+            let square = Square::new(rank, file);
+            match board.get_piece(square, None) {
+                Some(piece) => {
+                    if empty > 0 {
+                        row.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    row.push(match piece.get_colour() {
+                        Colour::White => piece.get_symbol(),
+                        Colour::Black => piece.get_symbol().to_ascii_lowercase(),
+                    });
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            row.push_str(&empty.to_string());
+        }
+        ranks.push(row);
+    }
+
+    let castling: String = [Colour::White, Colour::Black]
+        .into_iter()
+        .flat_map(|c| get_castling_rights(board, c, shredder))
+        .collect();
+    let castling = match castling.is_empty() {
+        true => String::from("-"),
+        false => castling,
+    };
+    let side = match colour {
+        Colour::White => "w",
+        Colour::Black => "b",
+    };
+    let checks = match board.get_variant() {
+        VariantType::ThreeCheck(_) => format!(
+            " {}+{}",
+            CHECKS.saturating_sub(board.get_checks(Colour::White)),
+            CHECKS.saturating_sub(board.get_checks(Colour::Black))
+        ),
+        _ => String::new(),
+    };
+
+    format!(
+        "{} {} {} -{} {} {}",
+        ranks.join("/"),
+        side,
+        castling,
+        checks,
+        consecutive_moves,
+        turn
+    )
+}
+
+/// Whether castling in the position needs Chess960 rules, a king or a rook allowed to castle
+/// standing away from its standard square.
+pub fn is_chess960(text: &str) -> bool {
+    let Ok(position) = parse_fen(text) else {
+        return false;
+    };
+    [Colour::White, Colour::Black].into_iter().any(|colour| {
+        position
+            .get_board()
+            .get_pieces(colour)
+            .iter()
+            .any(|(square, piece)| {
+                piece.is_first_move()
+                    && match piece.get_symbol() {
+                        'K' => square.get_file() != 4,
+                        'R' => square.get_file() != 0 && square.get_file() != 7,
+                        _ => false,
+                    }
+            })
+    })
+}
+
+/// Rank where the pieces of the player start.
+fn get_back_rank(colour: Colour) -> u8 {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 7,
+    }
+}
+
+/// Castling rights of the player, the kingside first: king and rook have never moved.
+fn get_castling_rights(board: &Board, colour: Colour, shredder: bool) -> Vec<char> {
+    let rank = get_back_rank(colour);
+    let unmoved = |symbol| {
+        let mut files: Vec<u8> = board
+            .get_pieces(colour)
+            .iter()
+            .filter(|(s, p)| p.get_symbol() == symbol && s.get_rank() == rank && p.is_first_move())
+            .map(|(s, _)| s.get_file())
+            .collect();
+        files.sort();
+        files
+    };
+    let Some(king_file) = unmoved('K').first().copied() else {
+        return vec![];
+    };
+    let rooks = unmoved('R');
+    let all_rooks: Vec<u8> = board
+        .get_pieces(colour)
+        .iter()
+        .filter(|(s, p)| p.get_symbol() == 'R' && s.get_rank() == rank)
+        .map(|(s, _)| s.get_file())
+        .collect();
+
+    let kingside = rooks.iter().rev().filter(|f| **f > king_file);
+    let queenside = rooks.iter().filter(|f| **f < king_file);
+    kingside
+        .chain(queenside)
+        .map(|file| {
+            let is_kingside = *file > king_file;
+            let is_outermost = all_rooks.iter().all(|other| match is_kingside {
+                true => other <= file,
+                false => other >= file,
+            });
+            let symbol = match (shredder || !is_outermost, is_kingside) {
+                (true, _) => (b'A' + file) as char,
+                (false, true) => 'K',
+                (false, false) => 'Q',
+            };
+            match colour {
+                Colour::White => symbol,
+                Colour::Black => symbol.to_ascii_lowercase(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::three_check::ThreeCheck;
+
+    fn get_checks(text: &str) -> (u8, u8) {
+        let board = parse_variant_fen(text, ThreeCheck.into())
+            .unwrap()
+            .into_board();
+        (
+            board.get_checks(Colour::White),
+            board.get_checks(Colour::Black),
+        )
+    }
+
+    #[test]
+    fn three_check_counters() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq -";
+        assert_eq!(get_checks(&format!("{} 0 1", fen)), (0, 0));
+        assert_eq!(get_checks(&format!("{} 2+3 0 3", fen)), (1, 0));
+        assert_eq!(get_checks(&format!("{} 0 3 +1+2", fen)), (1, 2));
+        assert!(parse_variant_fen(&format!("{} 4+3 0 3", fen), ThreeCheck.into()).is_err());
+        // Standard positions have no counters
+        assert!(parse_fen(&format!("{} 2+3 0 3", fen)).is_err());
+
+        let board = parse_variant_fen(&format!("{} 0 3 +1+2", fen), ThreeCheck.into())
+            .unwrap()
+            .into_board();
+        assert_eq!(
+            to_fen(&board, Colour::White, 0, 3),
+            format!("{} 2+1 0 3", fen)
+        );
+        let board = parse_fen(&format!("{} 0 3", fen)).unwrap().into_board();
+        assert_eq!(to_fen(&board, Colour::White, 0, 3), format!("{} 0 3", fen));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod epd;
+pub mod fen;
+pub mod pgn;
+pub mod san;
+pub mod uci;
@@ -0,0 +1,449 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crate::formats::{fen, san};
+use crate::game::tree::{GameTree, Node};
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::variant::variant::{Variant, VariantType};
+
+const MAX_LINE_LENGTH: usize = 80;
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    tags: Vec<(String, String)>,
+    moves: Vec<String>,
+    movetext: String,
+    result: String,
+}
+
+impl PgnGame {
+    pub fn new(
+        tags: Vec<(String, String)>,
+        moves: Vec<String>,
+        movetext: String,
+        result: String,
+    ) -> PgnGame {
+        PgnGame {
+            tags,
+            moves,
+            movetext,
+            result,
+        }
+    }
+
+    pub fn get_tags(&self) -> &Vec<(String, String)> {
+        &self.tags
+    }
+
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Main line moves in SAN, without move numbers, comments, NAGs or variations.
+    pub fn get_moves(&self) -> &Vec<String> {
+        &self.moves
+    }
+
+    /// Whole move text, with comments, NAGs and variations.
+    pub fn get_movetext(&self) -> &str {
+        &self.movetext
+    }
+
+    pub fn get_result(&self) -> &str {
+        &self.result
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<Vec<PgnGame>, Error> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Error::msg(format!("Cannot read {}: {}", path.display(), e)))?;
+
+    Ok(read_games(&text))
+}
+
+pub fn read_games(text: &str) -> Vec<PgnGame> {
+    let mut games = vec![];
+    let mut tags = vec![];
+    let mut moves = vec![];
+    let mut movetext = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Tag pair: a tag after some moves starts a new game
+            '[' => {
+                if !moves.is_empty() {
+                    games.push(PgnGame::new(tags, moves, movetext, String::from("*")));
+                    tags = vec![];
+                    moves = vec![];
+                    movetext = String::new();
+                }
+                let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                if let Some((name, value)) = tag.trim().split_once(' ') {
+                    let value = value.trim();
+                    let value = value.strip_prefix('"').unwrap_or(value);
+                    let value = value.strip_suffix('"').unwrap_or(value);
+                    let value = value.replace("\\\"", "\"").replace("\\\\", "\\");
+                    tags.push((name.to_string(), value));
+                }
+            }
+            // Comments
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                movetext.push_str(&format!("{{{}}} ", comment));
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                movetext.push_str(&format!("{{{}}} ", comment.trim()));
+            }
+            // Variations, possibly nested
+            '(' => {
+                let mut level = 1;
+                let mut variation = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        // Parentheses inside comments don't count
+                        '{' => {
+                            let comment: String =
+                                chars.by_ref().take_while(|c| *c != '}').collect();
+                            variation.push_str(&format!("{{{}}}", comment));
+                            continue;
+                        }
+                        ';' => {
+                            let comment: String =
+                                chars.by_ref().take_while(|c| *c != '\n').collect();
+                            variation.push_str(&format!("{{{}}} ", comment.trim()));
+                            continue;
+                        }
+                        '(' => level += 1,
+                        ')' => level -= 1,
+                        _ => {}
+                    }
+                    if level == 0 {
+                        break;
+                    }
+                    variation.push(c);
+                }
+                movetext.push_str(&format!("({}) ", variation));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = String::from(c);
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{;([".contains(*c)) {
+                    token.push(c);
+                }
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        games.push(PgnGame::new(tags, moves, movetext, token));
+                        tags = vec![];
+                        moves = vec![];
+                        movetext = String::new();
+                    }
+                    _ => {
+                        if let Some(san) = strip_move_number(&token) {
+                            moves.push(san);
+                        }
+                        movetext.push_str(&token);
+                        movetext.push(' ');
+                    }
+                }
+            }
+        }
+    }
+
+    // Last game without a result
+    if !moves.is_empty() {
+        games.push(PgnGame::new(tags, moves, movetext, String::from("*")));
+    }
+
+    games
+}
+
+fn strip_move_number(token: &str) -> Option<String> {
+    // NAG
+    if token.starts_with('$') {
+        return None;
+    }
+
+    // "12.", "12...", or "12.e4"
+    let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let san = match san.len() == token.len() {
+        true => token,
+        false if token.contains('.') => san,
+        false => token,
+    };
+
+    match san.is_empty() {
+        true => None,
+        false => Some(san.to_string()),
+    }
+}
+
+/// Build the move tree of a game, with its variations, comments and NAGs, played with the rules
+/// of its Variant tag.
+pub fn read_tree(game: &PgnGame) -> Result<GameTree, Error> {
+    let variant = match game.get_tag("Variant") {
+        Some(name) => VariantType::parse(name)?,
+        None => VariantType::default(),
+    };
+    let mut tree = match game.get_tag("FEN") {
+        Some(text) => GameTree::from_variant_fen(text, variant)?,
+        None if variant.is_standard() => GameTree::new(),
+        None => GameTree::from_variant_fen(variant.get_start_fen(), variant)?,
+    };
+    let mut branches: Vec<Vec<usize>> = vec![];
+    let mut is_line_start = true;
+    let mut starting_comment: Option<String> = None;
+    let mut chars = game.get_movetext().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let comment = comment.trim().to_string();
+                match is_line_start && !branches.is_empty() {
+                    // Comment before the first move of a variation
+                    true => starting_comment = Some(comment),
+                    false => {
+                        let comment = match tree.get_current().get_comment() {
+                            Some(previous) => format!("{} {}", previous, comment),
+                            None => comment,
+                        };
+                        tree.set_comment(Some(comment));
+                    }
+                }
+            }
+            '(' => {
+                branches.push(tree.get_path().clone());
+                if tree.back().is_none() {
+                    return Err(Error::msg("Variation without a move to replace"));
+                }
+                is_line_start = true;
+            }
+            ')' => {
+                let path = branches.pop().ok_or(Error::msg("Unbalanced variation"))?;
+                tree.go_to(&path);
+                is_line_start = false;
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = String::from(c);
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{;()".contains(*c)) {
+                    token.push(c);
+                }
+                if let Some(nag) = token.strip_prefix('$') {
+                    let nag = nag
+                        .parse()
+                        .map_err(|_| Error::msg(format!("Invalid NAG {}", token)))?;
+                    tree.add_nag(nag);
+                    continue;
+                }
+                if let Some(nag) = parse_nag(&token) {
+                    tree.add_nag(nag);
+                    continue;
+                }
+                if matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    continue;
+                }
+                let Some(token) = strip_move_number(&token) else {
+                    continue;
+                };
+
+                // Annotations attached to the move, e.g. "Nf3!?"
+                let annotation_start = token.trim_end_matches(['!', '?']).len();
+                let (text, annotation) = token.split_at(annotation_start);
+                let play = san::parse_san(&tree.get_board(), tree.get_colour(), text)
+                    .ok_or(Error::msg(format!("Cannot play {}", token)))?;
+                tree.add_move(play);
+                if let Some(nag) = parse_nag(annotation) {
+                    tree.add_nag(nag);
+                }
+                if starting_comment.is_some() {
+                    tree.set_starting_comment(starting_comment.take());
+                }
+                is_line_start = false;
+            }
+        }
+    }
+
+    tree.go_to_start();
+    Ok(tree)
+}
+
+/// PGN of a game tree, with the given tags and result.
+pub fn write_game(tags: &[(String, String)], tree: &GameTree, result: &str) -> String {
+    let mut text = String::new();
+    for (name, value) in tags {
+        text.push_str(&format!(
+            "[{} \"{}\"]\n",
+            name,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    }
+    let has_tag = |tag: &str| tags.iter().any(|(name, _)| name == tag);
+    let variant = tree.get_variant();
+    if !variant.is_standard() && !has_tag("Variant") {
+        text.push_str(&format!("[Variant \"{}\"]\n", variant.get_name()));
+    }
+    let is_custom_start = tree.get_start_fen() != variant.get_start_fen();
+    if is_custom_start && !has_tag("FEN") {
+        if fen::is_chess960(tree.get_start_fen()) && !has_tag("Variant") {
+            text.push_str("[Variant \"Chess960\"]\n");
+        }
+        text.push_str("[SetUp \"1\"]\n");
+        text.push_str(&format!("[FEN \"{}\"]\n", tree.get_start_fen()));
+    }
+    text.push('\n');
+
+    let mut tokens = vec![];
+    if let Some(comment) = tree.get_root().get_comment() {
+        tokens.push(format!("{{{}}}", comment));
+    }
+    let mut start = tree.clone();
+    start.go_to_start();
+
+    // Plies are counted from the start of a standard game, to number the moves
+    let ply = 2 * (tree.get_first_turn().max(1) as usize - 1)
+        + match tree.get_start_colour() {
+            Colour::White => 0,
+            Colour::Black => 1,
+        };
+    write_moves(tree.get_root(), &start.get_board(), ply, true, &mut tokens);
+    tokens.push(result.to_string());
+
+    // Wrap lines, without spaces inside variation parentheses
+    let mut line = String::new();
+    for token in tokens {
+        let separator = match line.is_empty() || line.ends_with('(') || token == ")" {
+            true => "",
+            false => " ",
+        };
+        let is_too_long = line.len() + separator.len() + token.len() > MAX_LINE_LENGTH;
+        if !line.is_empty() && is_too_long && token != ")" {
+            // A variation starts on the line of its first move
+            let opening = line.strip_suffix('(').is_some_and(|rest| !rest.is_empty());
+            if opening {
+                line.pop();
+            }
+            text.push_str(line.trim_end());
+            text.push('\n');
+            line.clear();
+            if opening {
+                line.push('(');
+            }
+            line.push_str(&token);
+        } else {
+            line.push_str(separator);
+            line.push_str(&token);
+        }
+    }
+    text.push_str(&line);
+    text.push_str("\n\n");
+
+    text
+}
+
+fn write_moves(
+    node: &Node,
+    board: &Board,
+    ply: usize,
+    is_line_start: bool,
+    tokens: &mut Vec<String>,
+) {
+    let Some(main) = node.get_children().first() else {
+        return;
+    };
+    write_move(main, board, ply, is_line_start, tokens);
+
+    // Variations replace the main move
+    for variation in &node.get_children()[1..] {
+        tokens.push(String::from("("));
+        write_move(variation, board, ply, true, tokens);
+        let mut next = board.duplicate();
+        next.execute_move(&variation.get_move().unwrap());
+        write_moves(variation, &next, ply + 1, false, tokens);
+        tokens.push(String::from(")"));
+    }
+
+    let mut next = board.duplicate();
+    next.execute_move(&main.get_move().unwrap());
+    let is_interrupted = node.get_children().len() > 1 || main.get_comment().is_some();
+    write_moves(main, &next, ply + 1, is_interrupted, tokens);
+}
+
+fn write_move(
+    node: &Node,
+    board: &Board,
+    ply: usize,
+    is_line_start: bool,
+    tokens: &mut Vec<String>,
+) {
+    let play = node.get_move().unwrap();
+    if let Some(comment) = node.get_starting_comment() {
+        tokens.push(format!("{{{}}}", comment));
+    }
+    match ply % 2 {
+        0 => tokens.push(format!("{}.", ply / 2 + 1)),
+        _ if is_line_start || node.get_starting_comment().is_some() => {
+            tokens.push(format!("{}...", ply / 2 + 1))
+        }
+        _ => {}
+    }
+    tokens.push(san::to_san(board, &play));
+    for nag in node.get_nags() {
+        tokens.push(format!("${}", nag));
+    }
+    if let Some(comment) = node.get_comment() {
+        tokens.push(format!("{{{}}}", comment));
+    }
+}
+
+/// Numeric Annotation Glyph of a traditional annotation symbol.
+fn parse_nag(symbol: &str) -> Option<u8> {
+    match symbol {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parentheses_in_variation_comments() {
+        let text = "1. e4 (1. d4 {idea :) central} d5 ; (sic\n 2. c4) 1... e5 2. Nf3 *";
+        let games = read_games(text);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].get_moves(), &vec!["e4", "e5", "Nf3"]);
+        let tree = read_tree(&games[0]).unwrap();
+        let variation = &tree.get_root().get_children()[1];
+        assert_eq!(variation.get_comment(), Some("idea :) central"));
+        let reply = &variation.get_children()[0];
+        assert_eq!(reply.get_comment(), Some("(sic"));
+        assert_eq!(reply.get_children().len(), 1);
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "[Event \"Test\"]\n[White \"A\"]\n[Black \"B\"]\n\n\
+            {Start} 1. e4 $1 e5 (1... c5 {Sicilian (open)} 2. Nf3 (2. c3 d5) 2... d6 $6)\n\
+            (1... e6) 2. Nf3 Nc6 {Then} 3. Bb5 (3. Bc4 Bc5 (3... Nf6 $5 {Two knights :)}))\n\
+            3... a6 1-0\n\n";
+        let games = read_games(text);
+        let tree = read_tree(&games[0]).unwrap();
+        let written = write_game(games[0].get_tags(), &tree, games[0].get_result());
+        assert_eq!(written, text);
+    }
+}
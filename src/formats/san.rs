@@ -0,0 +1,154 @@
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::{Piece, PieceType};
+
+/// Find the legal move described by a move in Standard Algebraic Notation (e.g. "Nbd7", "exd5",
+/// "e8=Q+", "O-O"). Moves the engine cannot play (under-promotions) are not found.
+pub fn parse_san(board: &Board, colour: Colour, san: &str) -> Option<HalfMove> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let castling = match san.replace('0', "O").as_str() {
+        "O-O" => Some(true),
+        "O-O-O" => Some(false),
+        _ => None,
+    };
+    if let Some(kingside) = castling {
+        return Game::select_legal_moves(board, colour)
+            .into_iter()
+            .find(|m| m.is_castling() && m.is_kingside_castling() == kingside);
+    }
+
+    // Promotion, only to queen
+    let (san, promotion) = match san.split_once('=') {
+        Some((san, piece)) => (san, Some(piece)),
+        None => match san.chars().last() {
+            Some(c) if "QRBN".contains(c) && san.len() > 2 => {
+                (&san[..san.len() - 1], Some(&san[san.len() - 1..]))
+            }
+            _ => (san, None),
+        },
+    };
+    if promotion.is_some_and(|p| p != "Q") {
+        return None;
+    }
+
+    // Moving piece
+    let symbol = match san.chars().next()? {
+        c if "KQRBN".contains(c) => c,
+        _ => 'P',
+    };
+    let san = match symbol {
+        'P' => san,
+        _ => &san[1..],
+    };
+
+    // Target square, eventually preceded by disambiguation and capture
+    if san.len() < 2 {
+        return None;
+    }
+    let (disambiguation, target) = san.split_at(san.len() - 2);
+    let mut target = target.chars();
+    let file = target.next()? as i8 - 'a' as i8;
+    let rank = target.next()?.to_digit(10)? as i8 - 1;
+    let target = *board.get_square(rank, file)?;
+    let disambiguation = disambiguation.replace('x', "");
+
+    Game::select_legal_moves(board, colour)
+        .into_iter()
+        .filter(|m| {
+            m.get_piece().get_symbol() == symbol && m.get_to() == target && !m.is_castling()
+        })
+        .find(|m| {
+            disambiguation.chars().all(|c| match c {
+                'a'..='h' => m.get_from().get_file() == c as u8 - b'a',
+                '1'..='8' => m.get_from().get_rank() == c as u8 - b'1',
+                _ => false,
+            })
+        })
+}
+
+/// Standard Algebraic Notation of a legal move, disambiguated against the other legal moves.
+pub fn to_san(board: &Board, play: &HalfMove) -> String {
+    let colour = play.get_piece().get_colour();
+    let from = play.get_from();
+    let mut san = String::new();
+
+    match play.get_piece() {
+        _ if play.is_castling() => match play.is_kingside_castling() {
+            true => san.push_str("O-O"),
+            false => san.push_str("O-O-O"),
+        },
+        PieceType::Pawn(_) => {
+            if play.is_capture() {
+                san.push((b'a' + from.get_file()) as char);
+            }
+        }
+        piece => {
+            san.push(piece.get_symbol());
+            let others: Vec<HalfMove> = Game::select_legal_moves(board, colour)
+                .into_iter()
+                .filter(|m| {
+                    m.get_piece() == piece && m.get_to() == play.get_to() && m.get_from() != from
+                })
+                .collect();
+            if !others.is_empty() {
+                let same_file = others
+                    .iter()
+                    .any(|m| m.get_from().get_file() == from.get_file());
+                let same_rank = others
+                    .iter()
+                    .any(|m| m.get_from().get_rank() == from.get_rank());
+                if !same_file {
+                    san.push((b'a' + from.get_file()) as char);
+                } else if !same_rank {
+                    san.push((b'1' + from.get_rank()) as char);
+                } else {
+                    san.push_str(&format!("{}", from));
+                }
+            }
+        }
+    }
+    if play.is_capture() {
+        san.push('x');
+    }
+    if !play.is_castling() {
+        san.push_str(&format!("{}", play.get_to()));
+    }
+    if play.is_promotion() {
+        san.push_str("=Q");
+    }
+
+    // Check and checkmate, whatever the move says about itself
+    let mut next = board.duplicate();
+    next.execute_move(play);
+    if next.is_under_check(colour.get_opposite()) {
+        match Game::select_legal_moves(&next, colour.get_opposite()).is_empty() {
+            true => san.push('#'),
+            false => san.push('+'),
+        }
+    }
+
+    san
+}
+
+/// A line of moves in SAN with move numbers, e.g. "12... Nf6 13. e5", played from the given
+/// position with the given move number.
+pub fn format_line(board: &Board, moves: &[HalfMove], turn: u16) -> String {
+    let mut board = board.duplicate();
+    let mut turn = turn as usize;
+    let mut tokens = vec![];
+    for (index, play) in moves.iter().enumerate() {
+        match play.get_piece().get_colour() {
+            Colour::White => tokens.push(format!("{}.", turn)),
+            Colour::Black if index == 0 => tokens.push(format!("{}...", turn)),
+            Colour::Black => {}
+        }
+        tokens.push(to_san(&board, play));
+        if play.get_piece().get_colour() == Colour::Black {
+            turn += 1;
+        }
+        board.execute_move(play);
+    }
+    tokens.join(" ")
+}
@@ -0,0 +1,76 @@
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::utils;
+
+/// Long algebraic notation used by UCI, e.g. "e2e4" or "e7e8q". Castling is the move of the king
+/// two squares aside, e.g. "e1g1", or king takes rook when the king moves otherwise in Chess960.
+pub fn to_uci(play: &HalfMove) -> String {
+    let (king_to, _) = play.get_castling_squares();
+    match play.is_castling() && play.get_from().get_file().abs_diff(king_to.get_file()) == 2 {
+        true => format!("{}{}", play.get_from(), king_to),
+        false => to_uci_chess960(play),
+    }
+}
+
+/// Long algebraic notation used by UCI with the UCI_Chess960 option: castling is always king
+/// takes rook, e.g. "e1h1".
+pub fn to_uci_chess960(play: &HalfMove) -> String {
+    let promotion = match play.is_promotion() {
+        true => "q",
+        false => "",
+    };
+    format!("{}{}{}", play.get_from(), play.get_to(), promotion)
+}
+
+/// Find the legal move described in long algebraic notation, castling being written either
+/// way. Moves the engine cannot play (under-promotions) are not found.
+pub fn parse_uci(board: &Board, colour: Colour, text: &str) -> Option<HalfMove> {
+    let text = text.trim();
+    if !text.is_ascii() || text.len() < 4 || text.len() > 5 {
+        return None;
+    }
+    let square = |text: &str| {
+        let mut chars = text.chars();
+        let file = chars.next()? as i8 - 'a' as i8;
+        let rank = chars.next()?.to_digit(10)? as i8 - 1;
+        board.get_square(rank, file).copied()
+    };
+    let from = square(&text[0..2])?;
+    let to = square(&text[2..4])?;
+    let promotion = &text[4..];
+    if !promotion.is_empty() && promotion != "q" {
+        return None;
+    }
+
+    let moves = Game::select_legal_moves(board, colour);
+    let play = moves.iter().find(|m| {
+        m.get_from() == from
+            && m.get_to() == to
+            && m.is_promotion() != promotion.is_empty()
+            && !m.is_castling()
+    });
+    // A king moving to a square it could reach normally isn't castling
+    play.or_else(|| {
+        moves.iter().find(|m| {
+            m.is_castling()
+                && m.get_from() == from
+                && (m.get_to() == to || m.get_castling_squares().0 == to)
+        })
+    })
+    .copied()
+}
+
+/// Score of an evaluation as sent by UCI engines: "cp <centipawns>" or "mate <moves>", negative
+/// when the player is getting mated.
+pub fn format_score(evaluation: f32) -> String {
+    if utils::is_mate(evaluation) {
+        format!("mate {}", utils::get_mate_distance(evaluation))
+    } else if utils::is_decisive(evaluation) {
+        // Tablebase results, beyond any material advantage
+        format!("cp {}", 20_000 * evaluation.signum() as i32)
+    } else {
+        format!("cp {}", (100.0 * evaluation).round() as i32)
+    }
+}
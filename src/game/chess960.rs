@@ -0,0 +1,138 @@
+use anyhow::Error;
+
+use crate::utils;
+
+/// Number of starting positions of Chess960.
+pub const POSITIONS: u16 = 960;
+
+/// Number of the standard starting position.
+pub const STANDARD: u16 = 518;
+
+/// Placements of the two knights among the five squares left by the bishops and the queen.
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// White pieces of the first rank, from the a-file, of the starting position with this number
+/// (Scharnagl numbering, e.g. "RNBQKBNR" for 518). The king always stands between the rooks.
+pub fn get_back_rank(index: u16) -> Result<[char; 8], Error> {
+    if index >= POSITIONS {
+        return Err(Error::msg(format!(
+            "No Chess960 position {}, they go from 0 to {}",
+            index,
+            POSITIONS - 1
+        )));
+    }
+    let mut rank = [' '; 8];
+    let index = index as usize;
+
+    // Bishops on squares of both colours
+    rank[2 * (index % 4) + 1] = 'B';
+    rank[2 * (index / 4 % 4)] = 'B';
+
+    // Queen and knights on the empty squares, then rook, king and rook
+    let place = |rank: &mut [char; 8], nth: usize, piece: char| {
+        let file = (0..8).filter(|f| rank[*f] == ' ').nth(nth).unwrap();
+        rank[file] = piece;
+    };
+    place(&mut rank, index / 16 % 6, 'Q');
+    let (first, second) = KNIGHTS[index / 96];
+    place(&mut rank, second, 'N');
+    place(&mut rank, first, 'N');
+    for piece in ['R', 'K', 'R'] {
+        place(&mut rank, 0, piece);
+    }
+    Ok(rank)
+}
+
+/// FEN record of the starting position with this number, Black mirroring White.
+pub fn get_fen(index: u16) -> Result<String, Error> {
+    let white: String = get_back_rank(index)?.iter().collect();
+    Ok(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        white.to_lowercase(),
+        white
+    ))
+}
+
+/// Number of a starting position drawn at random.
+pub fn get_random_index() -> u16 {
+    (utils::random_u64() % POSITIONS as u64) as u16
+}
+
+/// All the starting position numbers, shuffled.
+pub fn get_shuffled_indexes() -> Vec<u16> {
+    let mut indexes: Vec<u16> = (0..POSITIONS).collect();
+    for i in (1..indexes.len()).rev() {
+        let j = (utils::random_u64() % (i as u64 + 1)) as usize;
+        indexes.swap(i, j);
+    }
+    indexes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::formats::fen;
+    use crate::game::perft::perft;
+
+    fn back_rank(index: u16) -> String {
+        get_back_rank(index).unwrap().iter().collect()
+    }
+
+    fn perft_fen(text: &str, depth: u8) -> u64 {
+        let position = fen::parse_fen(text).unwrap();
+        perft(position.get_board(), position.get_colour(), depth)
+    }
+
+    #[test]
+    fn scharnagl_numbering() {
+        assert_eq!(back_rank(STANDARD), "RNBQKBNR");
+        assert_eq!(back_rank(0), "BBQNNRKR");
+        assert!(get_back_rank(POSITIONS).is_err());
+    }
+
+    #[test]
+    fn all_positions_are_valid_and_distinct() {
+        let ranks: HashSet<String> = (0..POSITIONS).map(back_rank).collect();
+        assert_eq!(ranks.len(), POSITIONS as usize);
+        for rank in ranks {
+            let files = |piece: char| -> Vec<usize> {
+                rank.char_indices()
+                    .filter(|(_, c)| *c == piece)
+                    .map(|(f, _)| f)
+                    .collect()
+            };
+            let bishops = files('B');
+            let rooks = files('R');
+            let king = files('K');
+            assert_eq!(bishops.len(), 2);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", rank);
+            assert_eq!((files('Q').len(), files('N').len(), rooks.len()), (1, 2, 2));
+            assert!(rooks[0] < king[0] && king[0] < rooks[1], "{}", rank);
+        }
+    }
+
+    #[test]
+    fn castling_perft() {
+        assert_eq!(perft_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -", 2), 568);
+        assert_eq!(
+            perft_fen(
+                "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+                3
+            ),
+            14569
+        );
+    }
+}
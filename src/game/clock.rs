@@ -1,65 +1,544 @@
+use std::fmt::{Display, Formatter};
 use std::marker::PhantomData;
-use std::time::Instant;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use enum_dispatch::enum_dispatch;
+
+/// Time given back to a player for each move.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Bonus {
+    None,
+    /// Fischer: seconds added after each move
+    Increment(f32),
+    /// Bronstein: the time spent on the move is given back, up to the given seconds
+    Bronstein(f32),
+    /// Simple (US) delay: the clock starts running only after the given seconds
+    Delay(f32),
+}
+
+/// A period of the time control: `seconds` to play `moves` moves (or the rest of the game).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Period {
+    moves: Option<u32>,
+    seconds: f32,
+    bonus: Bonus,
+}
+
+impl Period {
+    pub fn new(moves: Option<u32>, seconds: f32, bonus: Bonus) -> Period {
+        Period {
+            moves,
+            seconds,
+            bonus,
+        }
+    }
+
+    pub fn get_moves(&self) -> Option<u32> {
+        self.moves
+    }
+
+    pub fn get_seconds(&self) -> f32 {
+        self.seconds
+    }
+
+    pub fn get_bonus(&self) -> Bonus {
+        self.bonus
+    }
+}
+
+/// Time budget of a player, made of one or more periods. When the moves of a period have been
+/// played, the time of the next one is added to the clock; the last period repeats if it has a
+/// number of moves too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+    periods: Vec<Period>,
+}
+
+impl TimeControl {
+    pub fn new(periods: Vec<Period>) -> Result<TimeControl, Error> {
+        if periods.is_empty() {
+            return Err(Error::msg("A time control needs at least one period"));
+        }
+        if periods
+            .iter()
+            .any(|p| p.moves == Some(0) || p.seconds < 0.0)
+        {
+            return Err(Error::msg("Invalid time control period"));
+        }
+
+        Ok(TimeControl { periods })
+    }
+
+    pub fn sudden_death(seconds: f32) -> TimeControl {
+        TimeControl {
+            periods: vec![Period::new(None, seconds, Bonus::None)],
+        }
+    }
+
+    pub fn fischer(seconds: f32, increment: f32) -> TimeControl {
+        TimeControl {
+            periods: vec![Period::new(None, seconds, Bonus::Increment(increment))],
+        }
+    }
+
+    pub fn bronstein(seconds: f32, delay: f32) -> TimeControl {
+        TimeControl {
+            periods: vec![Period::new(None, seconds, Bonus::Bronstein(delay))],
+        }
+    }
+
+    pub fn simple_delay(seconds: f32, delay: f32) -> TimeControl {
+        TimeControl {
+            periods: vec![Period::new(None, seconds, Bonus::Delay(delay))],
+        }
+    }
+
+    /// Parse a time control in an extended PGN `TimeControl` form: periods separated by ':',
+    /// each one as `[moves/]seconds` followed by an optional bonus, `+N` for a Fischer increment,
+    /// `bN` for a Bronstein delay or `dN` for a simple delay (e.g. "40/5400+30:1800+30").
+    pub fn parse(text: &str) -> Result<TimeControl, Error> {
+        let invalid = || Error::msg(format!("Invalid time control: {}", text));
+        let periods = text
+            .split(':')
+            .map(|period| {
+                let (moves, rest) = match period.split_once('/') {
+                    Some((moves, rest)) => (Some(moves.parse().map_err(|_| invalid())?), rest),
+                    None => (None, period),
+                };
+                let (seconds, bonus) = match rest.find(['+', 'b', 'd']) {
+                    Some(index) => {
+                        let value = rest[index + 1..].parse().map_err(|_| invalid())?;
+                        let bonus = match &rest[index..index + 1] {
+                            "+" => Bonus::Increment(value),
+                            "b" => Bonus::Bronstein(value),
+                            _ => Bonus::Delay(value),
+                        };
+                        (&rest[..index], bonus)
+                    }
+                    None => (rest, Bonus::None),
+                };
+                let seconds = seconds.parse().map_err(|_| invalid())?;
+                Ok(Period::new(moves, seconds, bonus))
+            })
+            .collect::<Result<Vec<Period>, Error>>()?;
+
+        TimeControl::new(periods)
+    }
+
+    pub fn get_periods(&self) -> &Vec<Period> {
+        &self.periods
+    }
+
+    /// Period in use after the given number of completed periods.
+    pub fn get_period(&self, index: usize) -> &Period {
+        match self.periods.get(index) {
+            Some(period) => period,
+            None => self.periods.last().unwrap(),
+        }
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let periods: Vec<String> = self
+            .periods
+            .iter()
+            .map(|p| {
+                let moves = match p.moves {
+                    Some(moves) => format!("{}/", moves),
+                    None => String::new(),
+                };
+                let bonus = match p.bonus {
+                    Bonus::None => String::new(),
+                    Bonus::Increment(s) => format!("+{}", s),
+                    Bonus::Bronstein(s) => format!("b{}", s),
+                    Bonus::Delay(s) => format!("d{}", s),
+                };
+                format!("{}{}{}", moves, p.seconds, bonus)
+            })
+            .collect();
+        f.write_str(&periods.join(":"))
+    }
+}
+
+/// Source of the current time, so that clocks can run on real time or on a time driven by hand
+/// (to replay games or test clocks without waiting).
+#[enum_dispatch]
+pub trait TimeSource: Clone {
+    /// Time elapsed since an arbitrary but fixed origin.
+    fn now(&self) -> Duration;
+}
+
+/// Real, monotonic time.
+#[derive(Debug, Copy, Clone)]
+pub struct MonotonicTime {
+    origin: Instant,
+}
+
+impl MonotonicTime {
+    pub fn new() -> MonotonicTime {
+        MonotonicTime {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicTime {
+    fn default() -> MonotonicTime {
+        MonotonicTime::new()
+    }
+}
+
+impl TimeSource for MonotonicTime {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Time that only moves forward when told to. Copies share the same time, so a clock built on a
+/// copy follows every `advance`.
+#[derive(Debug, Clone, Default)]
+pub struct ManualTime {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualTime {
+    pub fn new() -> ManualTime {
+        ManualTime::default()
+    }
+
+    pub fn advance(&self, seconds: f32) {
+        let nanos = Duration::from_secs_f32(seconds).as_nanos() as u64;
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// Any of the time sources, chosen when the game is set up rather than at compile time.
+#[enum_dispatch(TimeSource)]
+#[derive(Debug, Clone)]
+pub enum TimeSourceType {
+    MonotonicTime(MonotonicTime),
+    ManualTime(ManualTime),
+}
+
+impl Default for TimeSourceType {
+    fn default() -> TimeSourceType {
+        MonotonicTime::new().into()
+    }
+}
 
 pub struct OpenClock;
+pub struct PausedClock;
 pub struct ClosedClock;
 
-pub struct Clock<State = ClosedClock> {
-    start_time: Option<Instant>,
+pub struct Clock<State = ClosedClock, Source = TimeSourceType> {
+    source: Source,
+    start_time: Option<Duration>,
+    running_seconds: f32,
     total_seconds: f32,
     last_seconds: f32,
+    time_control: Option<TimeControl>,
+    remaining_seconds: f32,
+    period: usize,
+    period_moves: u32,
     state: PhantomData<State>,
 }
 
-impl Clock<ClosedClock> {
-    pub fn open(&self) -> Clock<OpenClock> {
-        Clock {
-            start_time: Some(Instant::now()),
-            total_seconds: self.total_seconds,
-            last_seconds: 0.0,
-            state: PhantomData::<OpenClock>,
-        }
+impl<Source: TimeSource> Clock<ClosedClock, Source> {
+    pub fn open(&self) -> Clock<OpenClock, Source> {
+        let mut clock = self.with_state(Some(self.source.now()), 0.0);
+        clock.last_seconds = 0.0;
+        clock
     }
 }
 
-impl Clock<OpenClock> {
-    pub fn close(&self) -> Clock<ClosedClock> {
-        let last_seconds = match self.start_time {
-            Some(time) => Instant::now().duration_since(time).as_secs_f32(),
-            None => 0.0,
-        };
-        Clock {
-            start_time: None,
-            total_seconds: self.total_seconds + last_seconds,
-            last_seconds,
-            state: PhantomData::<ClosedClock>,
+impl<Source: TimeSource> Clock<OpenClock, Source> {
+    /// Stop the time of the current move, e.g. for an adjournment, without ending the move.
+    pub fn pause(&self) -> Clock<PausedClock, Source> {
+        self.with_state(None, self.get_move_seconds())
+    }
+
+    pub fn close(&self) -> Clock<ClosedClock, Source> {
+        let last_seconds = self.get_move_seconds();
+        let mut remaining_seconds = self.get_remaining_after(last_seconds);
+        let mut period = self.period;
+        let mut period_moves = self.period_moves;
+
+        // Time is given back only if the flag has not fallen yet
+        if let Some(time_control) = self
+            .time_control
+            .as_ref()
+            .filter(|_| remaining_seconds > 0.0)
+        {
+            let current = time_control.get_period(period);
+            remaining_seconds += match current.bonus {
+                Bonus::Increment(seconds) => seconds,
+                Bonus::Bronstein(seconds) => last_seconds.min(seconds),
+                Bonus::None | Bonus::Delay(_) => 0.0,
+            };
+
+            // Move to the next period once its moves have been played
+            period_moves += 1;
+            if current.moves.is_some_and(|moves| period_moves >= moves) {
+                period += 1;
+                period_moves = 0;
+                remaining_seconds += time_control.get_period(period).seconds;
+            }
         }
+
+        let mut clock = self.with_state(None, 0.0);
+        clock.total_seconds = self.total_seconds + last_seconds;
+        clock.last_seconds = last_seconds;
+        clock.remaining_seconds = remaining_seconds;
+        clock.period = period;
+        clock.period_moves = period_moves;
+        clock
     }
 }
 
-impl<State> Clock<State> {
+impl<Source: TimeSource> Clock<PausedClock, Source> {
+    pub fn resume(&self) -> Clock<OpenClock, Source> {
+        self.with_state(Some(self.source.now()), self.running_seconds)
+    }
+}
+
+impl<State, Source: TimeSource> Clock<State, Source> {
     pub fn get_total_seconds(&self) -> f32 {
-        match self.start_time {
-            Some(time) => self.total_seconds + Instant::now().duration_since(time).as_secs_f32(),
-            None => self.total_seconds,
-        }
+        self.total_seconds + self.get_move_seconds()
     }
 
+    /// Time spent on the current move, or on the last one if the clock is closed.
     pub fn get_last_seconds(&self) -> f32 {
+        self.last_seconds + self.get_move_seconds()
+    }
+
+    pub fn get_time_control(&self) -> Option<&TimeControl> {
+        self.time_control.as_ref()
+    }
+
+    /// Period of the time control the player is in.
+    pub fn get_period(&self) -> Option<&Period> {
+        self.time_control
+            .as_ref()
+            .map(|t| t.get_period(self.period))
+    }
+
+    /// Time left before the flag falls, if the clock has a time control.
+    pub fn get_remaining_seconds(&self) -> Option<f32> {
+        self.time_control
+            .as_ref()
+            .map(|_| self.get_remaining_after(self.get_move_seconds()))
+    }
+
+    /// Moves still to be played before the next time is added to the clock.
+    pub fn get_moves_to_go(&self) -> Option<u32> {
+        self.time_control
+            .as_ref()
+            .and_then(|t| t.get_period(self.period).moves)
+            .map(|moves| moves - self.period_moves)
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        self.get_remaining_seconds()
+            .is_some_and(|seconds| seconds <= 0.0)
+    }
+
+    /// Time spent on the move being played (zero when the clock is closed).
+    fn get_move_seconds(&self) -> f32 {
         match self.start_time {
-            Some(time) => Instant::now().duration_since(time).as_secs_f32(),
-            None => self.last_seconds,
+            Some(time) => {
+                let running = self.source.now().saturating_sub(time).as_secs_f32();
+                self.running_seconds + running
+            }
+            None => self.running_seconds,
+        }
+    }
+
+    fn get_remaining_after(&self, seconds: f32) -> f32 {
+        match &self.time_control {
+            Some(time_control) => {
+                let seconds = match time_control.get_period(self.period).bonus {
+                    Bonus::Delay(delay) => (seconds - delay).max(0.0),
+                    _ => seconds,
+                };
+                self.remaining_seconds - seconds
+            }
+            None => f32::INFINITY,
+        }
+    }
+
+    fn with_state<Next>(
+        &self,
+        start_time: Option<Duration>,
+        running_seconds: f32,
+    ) -> Clock<Next, Source> {
+        Clock {
+            source: self.source.clone(),
+            start_time,
+            running_seconds,
+            total_seconds: self.total_seconds,
+            last_seconds: self.last_seconds,
+            time_control: self.time_control.clone(),
+            remaining_seconds: self.remaining_seconds,
+            period: self.period,
+            period_moves: self.period_moves,
+            state: PhantomData::<Next>,
         }
     }
 }
 
-impl Clock {
-    pub fn new() -> Clock {
+impl<State, Source: TimeSource> Clone for Clock<State, Source> {
+    fn clone(&self) -> Self {
+        self.with_state(self.start_time, self.running_seconds)
+    }
+}
+
+impl<Source: TimeSource> Clock<ClosedClock, Source> {
+    pub fn with_source(
+        source: Source,
+        time_control: Option<TimeControl>,
+    ) -> Clock<ClosedClock, Source> {
         Clock {
+            source,
             start_time: None,
+            running_seconds: 0.0,
             total_seconds: 0.0,
             last_seconds: 0.0,
+            remaining_seconds: time_control
+                .as_ref()
+                .map_or(f32::INFINITY, |t| t.get_period(0).seconds),
+            time_control,
+            period: 0,
+            period_moves: 0,
             state: PhantomData::default(),
         }
     }
 }
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock::with_source(TimeSourceType::default(), None)
+    }
+
+    pub fn with_time_control(time_control: TimeControl) -> Clock {
+        Clock::with_source(TimeSourceType::default(), Some(time_control))
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(time_control: TimeControl) -> (ManualTime, Clock<ClosedClock, ManualTime>) {
+        let time = ManualTime::new();
+        let clock = Clock::with_source(time.clone(), Some(time_control));
+        (time, clock)
+    }
+
+    fn play(
+        time: &ManualTime,
+        clock: &Clock<ClosedClock, ManualTime>,
+        seconds: f32,
+    ) -> Clock<ClosedClock, ManualTime> {
+        let clock = clock.open();
+        time.advance(seconds);
+        clock.close()
+    }
+
+    #[test]
+    fn fischer_increment() {
+        let (time, clock) = start(TimeControl::fischer(60.0, 2.0));
+        let clock = play(&time, &clock, 5.0);
+        assert_eq!(clock.get_remaining_seconds(), Some(57.0));
+        assert_eq!(clock.get_last_seconds(), 5.0);
+    }
+
+    #[test]
+    fn bronstein_delay() {
+        // Time used is given back, up to the delay
+        let (time, clock) = start(TimeControl::bronstein(60.0, 3.0));
+        let clock = play(&time, &clock, 2.0);
+        assert_eq!(clock.get_remaining_seconds(), Some(60.0));
+        let clock = play(&time, &clock, 5.0);
+        assert_eq!(clock.get_remaining_seconds(), Some(58.0));
+    }
+
+    #[test]
+    fn simple_delay() {
+        // The clock only runs once the delay is over
+        let (time, clock) = start(TimeControl::simple_delay(60.0, 3.0));
+        let open = clock.open();
+        time.advance(2.0);
+        assert_eq!(open.get_remaining_seconds(), Some(60.0));
+        time.advance(3.0);
+        assert_eq!(open.get_remaining_seconds(), Some(58.0));
+        assert_eq!(open.close().get_remaining_seconds(), Some(58.0));
+    }
+
+    #[test]
+    fn next_period() {
+        let (time, mut clock) = start(TimeControl::parse("40/5400+30:1800+30").unwrap());
+        for _ in 0..39 {
+            clock = play(&time, &clock, 10.0);
+        }
+        assert_eq!(clock.get_moves_to_go(), Some(1));
+        assert_eq!(
+            clock.get_remaining_seconds(),
+            Some(5400.0 - 390.0 + 39.0 * 30.0)
+        );
+
+        // The time of the next period is added once the 40th move is played
+        clock = play(&time, &clock, 10.0);
+        assert_eq!(clock.get_moves_to_go(), None);
+        assert_eq!(
+            clock.get_remaining_seconds(),
+            Some(5400.0 - 400.0 + 40.0 * 30.0 + 1800.0)
+        );
+        assert_eq!(clock.get_period().unwrap().get_seconds(), 1800.0);
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let (time, clock) = start(TimeControl::sudden_death(60.0));
+        let open = clock.open();
+        time.advance(5.0);
+        let paused = open.pause();
+        time.advance(100.0);
+        assert_eq!(paused.get_remaining_seconds(), Some(55.0));
+        let open = paused.resume();
+        time.advance(5.0);
+        let clock = open.close();
+        assert_eq!(clock.get_last_seconds(), 10.0);
+        assert_eq!(clock.get_remaining_seconds(), Some(50.0));
+    }
+
+    #[test]
+    fn flag_fall() {
+        // No increment once the flag has fallen
+        let (time, clock) = start(TimeControl::fischer(10.0, 5.0));
+        let open = clock.open();
+        time.advance(9.0);
+        assert!(!open.is_flagged());
+        time.advance(2.0);
+        assert!(open.is_flagged());
+        let clock = open.close();
+        assert!(clock.is_flagged());
+        assert_eq!(clock.get_remaining_seconds(), Some(-1.0));
+    }
+}
@@ -1,23 +1,60 @@
 use std::fmt::{Display, Formatter};
+use std::sync::atomic::AtomicBool;
 
 use anyhow::Error;
 
-use crate::game::clock::{Clock, OpenClock};
-use crate::game::play::{FullMove, HalfMove};
+use crate::book::polyglot::PolyglotBook;
+use crate::formats::fen;
+use crate::game::chess960;
+use crate::game::clock::{Clock, OpenClock, TimeControl, TimeSourceType};
+use crate::game::play::{FullMove, HalfMove, Line};
+use crate::game::search;
+use crate::game::search::{Report, SearchOptions};
+use crate::game::tree::GameTree;
 use crate::game::turn::Turn;
 use crate::model::board::board::Board;
 use crate::model::piece::color::Colour;
 use crate::model::piece::pawn::Pawn;
-use crate::model::piece::piece::{Piece, PieceType};
+use crate::model::piece::piece::PieceType;
+use crate::player::player::{Action, Player};
+use crate::tablebase::retrograde::EndgameTables;
+use crate::tablebase::syzygy::Tablebase;
 use crate::utils;
+use crate::variant::variant::{Outcome, Variant, VariantType};
 
 pub struct Game {
     turns: Vec<Turn>,
     board: Board,
     consecutive_moves: u8,
     positions: Vec<u64>,
+    time_source: TimeSourceType,
     white_clock: Clock,
     black_clock: Clock,
+    principal_variations: Vec<Line>,
+    book: Option<PolyglotBook>,
+    tablebase: Option<Tablebase>,
+    endgame_tables: Option<EndgameTables>,
+    last_move: Option<HalfMove>,
+    tree: GameTree,
+    result: String,
+    verbose: bool,
+    history: Vec<Snapshot>,
+    undone: Vec<Snapshot>,
+}
+
+/// What a move changes in the game, to take it back or play it again. Only the last turn and
+/// the last position are kept, as a move can't change the ones before.
+struct Snapshot {
+    turn_count: usize,
+    last_turn: Option<Turn>,
+    board: Board,
+    consecutive_moves: u8,
+    position_count: usize,
+    last_position: Option<u64>,
+    white_clock: Clock,
+    black_clock: Clock,
+    last_move: Option<HalfMove>,
+    result: String,
 }
 
 impl Display for Game {
@@ -34,6 +71,16 @@ impl Display for Game {
             };
         f.write_str("\n\n")?;
         f.write_str(&format!("{}s", time))?;
+        if let (Some(white), Some(black)) = (
+            self.white_clock.get_remaining_seconds(),
+            self.black_clock.get_remaining_seconds(),
+        ) {
+            f.write_str(&format!(
+                " (remaining: White {}, Black {})",
+                utils::format_seconds(white),
+                utils::format_seconds(black)
+            ))?;
+        }
         Ok(())
     }
 }
@@ -51,11 +98,94 @@ impl Game {
             board,
             consecutive_moves: 0,
             positions: vec![hash],
+            time_source: TimeSourceType::default(),
             white_clock: Clock::new(),
             black_clock: Clock::new(),
+            principal_variations: vec![],
+            book: None,
+            tablebase: None,
+            endgame_tables: None,
+            last_move: None,
+            tree: GameTree::new(),
+            result: String::from("*"),
+            verbose: true,
+            history: vec![],
+            undone: vec![],
         }
     }
 
+    /// Game starting from the position of a FEN record.
+    pub fn from_fen(text: &str) -> Result<Game, Error> {
+        Game::from_variant_fen(text, VariantType::default())
+    }
+
+    /// Game of the variant starting from its own starting position.
+    pub fn from_variant(variant: VariantType) -> Game {
+        Game::from_variant_fen(variant.get_start_fen(), variant)
+            .expect("The starting position of each variant is valid")
+    }
+
+    /// Game of the variant starting from the position of a FEN record.
+    pub fn from_variant_fen(text: &str, variant: VariantType) -> Result<Game, Error> {
+        let position = fen::parse_variant_fen(text, variant)?;
+        let mut game = Game::new();
+        game.tree = GameTree::from_variant_fen(text, variant)?;
+        game.consecutive_moves = position.get_consecutive_moves();
+        game.positions = vec![utils::hash_position(
+            position.get_board(),
+            position.get_colour(),
+        )];
+        game.board = position.into_board();
+
+        Ok(game)
+    }
+
+    /// Game starting from the Chess960 position with this number.
+    pub fn from_chess960(index: u16) -> Result<Game, Error> {
+        Game::from_fen(&chess960::get_fen(index)?)
+    }
+
+    /// Whether the game is played with Chess960 castling, its king or rooks starting away from
+    /// their standard squares.
+    pub fn is_chess960(&self) -> bool {
+        fen::is_chess960(self.get_start_fen())
+    }
+
+    /// Rules the game is played with.
+    pub fn get_variant(&self) -> VariantType {
+        self.board.get_variant()
+    }
+
+    /// FEN record of the current position.
+    pub fn get_fen(&self) -> String {
+        fen::to_fen(
+            &self.board,
+            self.get_side_to_move(),
+            self.consecutive_moves,
+            self.get_move_number(),
+        )
+    }
+
+    /// Number of the full move being played, as in FEN records.
+    pub fn get_move_number(&self) -> u16 {
+        let plies = self.positions.len() - 1
+            + match self.tree.get_start_colour() {
+                Colour::White => 0,
+                Colour::Black => 1,
+            };
+        self.tree.get_first_turn() + (plies / 2) as u16
+    }
+
+    /// FEN record of the position the game started from.
+    pub fn get_start_fen(&self) -> &str {
+        self.tree.get_start_fen()
+    }
+
+    /// Print the moves and the board while playing.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
     pub fn get_board(&self) -> &Board {
         &self.board
     }
@@ -64,6 +194,86 @@ impl Game {
         &self.turns
     }
 
+    pub fn get_consecutive_moves(&self) -> u8 {
+        self.consecutive_moves
+    }
+
+    /// Hashes of the positions played so far, to detect repetitions.
+    pub fn get_positions(&self) -> &Vec<u64> {
+        &self.positions
+    }
+
+    /// Player who has to move.
+    pub fn get_side_to_move(&self) -> Colour {
+        match self.positions.len() % 2 {
+            1 => self.tree.get_start_colour(),
+            _ => self.tree.get_start_colour().get_opposite(),
+        }
+    }
+
+    /// Moves played from the start of the game.
+    pub fn get_moves(&self) -> Vec<HalfMove> {
+        self.tree.get_moves()
+    }
+
+    /// Moves played, with the alternatives considered by the engine as variations.
+    pub fn get_tree(&self) -> &GameTree {
+        &self.tree
+    }
+
+    /// Result in PGN form: "1-0", "0-1", "1/2-1/2", or "*" while the game is not over.
+    pub fn get_result(&self) -> &str {
+        &self.result
+    }
+
+    /// Best line found by the last search.
+    pub fn get_principal_variation(&self) -> Option<&Line> {
+        self.principal_variations.first()
+    }
+
+    /// Best lines found by the last search, one for each of the MultiPV root moves.
+    pub fn get_principal_variations(&self) -> &Vec<Line> {
+        &self.principal_variations
+    }
+
+    pub fn set_book(&mut self, book: Option<PolyglotBook>) {
+        self.book = book;
+    }
+
+    pub fn get_tablebase(&self) -> Option<&Tablebase> {
+        self.tablebase.as_ref()
+    }
+
+    pub fn set_tablebase(&mut self, tablebase: Option<Tablebase>) {
+        self.tablebase = tablebase;
+    }
+
+    pub fn set_endgame_tables(&mut self, endgame_tables: Option<EndgameTables>) {
+        self.endgame_tables = endgame_tables;
+    }
+
+    /// Give both players the same time budget. Must be set before the game starts.
+    pub fn set_time_control(&mut self, time_control: TimeControl) {
+        let source = self.time_source.clone();
+        self.white_clock = Clock::with_source(source.clone(), Some(time_control.clone()));
+        self.black_clock = Clock::with_source(source, Some(time_control));
+    }
+
+    /// Time the clocks run on, real time by default. Must be set before the game starts.
+    pub fn set_time_source(&mut self, source: impl Into<TimeSourceType>) {
+        self.time_source = source.into();
+        let time_control = self.white_clock.get_time_control().cloned();
+        self.white_clock = Clock::with_source(self.time_source.clone(), time_control.clone());
+        self.black_clock = Clock::with_source(self.time_source.clone(), time_control);
+    }
+
+    pub fn get_clock(&self, colour: Colour) -> &Clock {
+        match colour {
+            Colour::White => &self.white_clock,
+            Colour::Black => &self.black_clock,
+        }
+    }
+
     fn open_clock(&self, colour: Colour) -> Clock<OpenClock> {
         match colour {
             Colour::White => self.white_clock.open(),
@@ -78,98 +288,439 @@ impl Game {
         }
     }
 
-    pub fn play(&mut self, max_depth: u8) -> () {
-        let mut count = 0;
-        let mut white_move = None;
-        let mut black_move = None;
+    /// Play the game until it is over, asking each player for their moves.
+    pub fn play(&mut self, white: &mut dyn Player, black: &mut dyn Player) {
         loop {
-            match self.is_over(count, white_move, black_move) {
-                Ok(_) => {
-                    count = count + 1;
-                    let mut full_move = FullMove::new();
-
-                    // Play as White
-                    white_move = self.play_turn(Colour::White, max_depth);
-                    if white_move.is_none() {
-                        black_move = self.eventually_set_checkmate(black_move);
-                        black_move = self.eventually_set_stalemate(black_move);
-                        let turn = self.turns.pop().unwrap();
-                        let mut full_move = FullMove::new();
-                        full_move.set_white_move(turn.get_moves().get_white_move().unwrap());
-                        full_move.set_black_move(black_move.unwrap());
-                        self.turns.push(Turn::new(turn.get_index(), full_move));
-                        continue;
-                    }
-                    full_move.set_white_move(white_move.unwrap());
-                    full_move.set_white_seconds(self.white_clock.get_last_seconds());
-
-                    // Play as Black
-                    black_move = self.play_turn(Colour::Black, max_depth);
-                    if black_move.is_none() {
-                        white_move = self.eventually_set_checkmate(white_move);
-                        white_move = self.eventually_set_stalemate(white_move);
-                        full_move.set_white_move(white_move.unwrap());
-                        self.turns.push(Turn::new(count, full_move));
-                        continue;
-                    }
-                    full_move.set_black_move(black_move.unwrap());
-                    full_move.set_black_seconds(self.black_clock.get_last_seconds());
+            if let Err(error) = self.is_over() {
+                self.print(&format!("Game over. Reason: {}", error));
+                self.set_result();
+                break;
+            }
 
-                    let turn = Turn::new(count, full_move);
-                    println!("{}", turn);
-                    println!("{}", self.board);
-                    self.turns.push(turn);
+            let colour = self.get_side_to_move();
+            let result = match colour {
+                Colour::White => self.play_move(white),
+                Colour::Black => self.play_move(black),
+            };
+            match result {
+                Ok(Some(Action::Undo)) => self.take_back(),
+                Ok(Some(Action::Redo)) => self.play_again(),
+                Ok(Some(Action::Move(_))) => {
+                    if colour == Colour::Black {
+                        if let Some(turn) = self.turns.last() {
+                            self.print(&format!("{}", turn));
+                        }
+                        self.print(&format!("{}", self.board));
+                    }
+                }
+                Ok(None) => {
+                    self.set_final_move(colour.get_opposite());
+                    self.print(&format!(
+                        "Game over. Reason: {:?} doesn't have any legal move to play",
+                        colour
+                    ));
+                    self.set_result();
+                    break;
+                }
+                Err(error) if self.get_clock(colour).is_flagged() => {
+                    self.print(&format!(
+                        "Game over. Reason: {:?} ran out of time ({})",
+                        colour, error
+                    ));
+                    self.set_result();
+                    break;
                 }
                 Err(error) => {
-                    println!("Game over. Reason: {}", error);
+                    self.print(&format!(
+                        "Game over. Reason: {:?} resigns ({})",
+                        colour, error
+                    ));
+                    self.result = match colour {
+                        Colour::White => String::from("0-1"),
+                        Colour::Black => String::from("1-0"),
+                    };
                     break;
                 }
             }
         }
     }
 
-    pub fn play_turn(&mut self, colour: Colour, max_depth: u8) -> Option<HalfMove> {
+    /// Take back the last move of the player to move and the reply to it, so that the same
+    /// player is to move again.
+    fn take_back(&mut self) {
+        match self.history.len() >= 2 {
+            true => {
+                self.undo();
+                self.undo();
+                self.print(&format!("{}", self.board));
+            }
+            false => self.print("No move to take back"),
+        }
+    }
+
+    /// Play again the two moves last taken back.
+    fn play_again(&mut self) {
+        match self.undone.len() >= 2 {
+            true => {
+                self.redo();
+                self.redo();
+                self.print(&format!("{}", self.board));
+            }
+            false => self.print("No move to play again"),
+        }
+    }
+
+    /// Ask the player to move for their move and play it. Returns `None` if there are no legal
+    /// moves, and an error if the player can't give a legal move. Taking back or playing again
+    /// moves is left to the caller.
+    pub fn play_move(&mut self, player: &mut dyn Player) -> Result<Option<Action>, Error> {
+        let colour = self.get_side_to_move();
+        let moves = Game::select_legal_moves(&self.board, colour);
+        if moves.is_empty() {
+            // End of the game
+            return Ok(None);
+        }
+
+        // Remember the position, to be able to take the move back
+        let snapshot = self.take_snapshot();
+
         // Start timer
         let clock = self.open_clock(colour);
 
-        let moves = self.select_legal_moves(&self.board, colour);
+        // Only accept legal moves, keeping the evaluation of the player
+        let choice = match player.choose(self) {
+            Ok(Action::Move(play)) => play,
+            Ok(action) => return Ok(Some(action)),
+            Err(error) => {
+                // Stop the clock, for a player too slow to answer to lose on time
+                self.close_clock(colour, clock);
+                return Err(error);
+            }
+        };
+        let mut chosen_one = Game::find_move(&moves, &choice)
+            .ok_or(Error::msg(format!("Illegal move {}", choice)))?;
+        chosen_one.set_evaluation(choice.get_evaluation());
+        self.board.execute_move(&chosen_one);
 
-        // Choose best move
-        if !moves.is_empty() {
-            let chosen_one = self
-                .choose_move(
-                    &self.board.duplicate(),
-                    colour,
-                    colour,
-                    moves,
-                    self.consecutive_moves,
-                    &self.positions.clone(),
-                    0,
-                    max_depth,
-                )
-                .0
-                .expect("There are no moves to play");
-            self.board.execute_move(&chosen_one);
+        // Stop timer
+        self.close_clock(colour, clock);
+
+        self.principal_variations = match player.get_lines() {
+            lines
+                if lines
+                    .first()
+                    .and_then(|l| l.get_first_move())
+                    .is_some_and(|m| {
+                        m.get_from() == chosen_one.get_from() && m.get_to() == chosen_one.get_to()
+                    }) =>
+            {
+                lines.to_vec()
+            }
+            _ => vec![Line::new(vec![chosen_one], chosen_one.get_evaluation())],
+        };
+        self.record_move(colour, chosen_one, snapshot);
+
+        Ok(Some(Action::Move(chosen_one)))
+    }
+
+    /// Why the game is over, if it is: checkmate, stalemate, a win condition of the variant, a
+    /// draw rule or a fallen flag.
+    pub fn get_end_reason(&self) -> Option<String> {
+        if let Some(outcome) = self.get_outcome() {
+            return Some(outcome.get_reason().to_string());
+        }
+        self.is_over().err().map(|error| error.to_string())
+    }
+
+    /// How the position ends the game by the rules of its variant, if it does: checkmate,
+    /// stalemate or a win condition of the variant. Draw rules and clocks are not considered.
+    pub fn get_outcome(&self) -> Option<Outcome> {
+        let colour = self.get_side_to_move();
+        let variant = self.board.get_variant();
+        variant.get_outcome(&self.board, colour).or_else(|| {
+            Game::select_legal_moves(&self.board, colour)
+                .is_empty()
+                .then(|| variant.get_outcome_without_moves(&self.board, colour))
+        })
+    }
+
+    /// Play a move without asking any player nor running the clocks, e.g. for an opening.
+    pub fn make_move(&mut self, play: HalfMove) -> Result<(), Error> {
+        let colour = self.get_side_to_move();
+        let moves = Game::select_legal_moves(&self.board, colour);
+        let play =
+            Game::find_move(&moves, &play).ok_or(Error::msg(format!("Illegal move {}", play)))?;
+
+        let snapshot = self.take_snapshot();
+        self.board.execute_move(&play);
+        self.principal_variations = vec![];
+        self.record_move(colour, play, snapshot);
+
+        Ok(())
+    }
+
+    /// The move among the given ones going from and to the same squares.
+    fn find_move(moves: &[HalfMove], play: &HalfMove) -> Option<HalfMove> {
+        moves.iter().copied().find(|m| {
+            m.get_from() == play.get_from()
+                && m.get_to() == play.get_to()
+                && m.is_promotion() == play.is_promotion()
+        })
+    }
+
+    fn print(&self, text: &str) {
+        if self.verbose {
+            println!("{}", text);
+        }
+    }
+
+    /// Best lines for the player to move, from the opening book, the endgame tables or the
+    /// search with the given options.
+    pub fn think(&self, options: &SearchOptions, max_depth: u8) -> Vec<Line> {
+        let colour = self.get_side_to_move();
+        let moves = Game::select_legal_moves(&self.board, colour);
+        if moves.is_empty() {
+            return vec![];
+        }
+
+        let book_move = self
+            .book
+            .as_ref()
+            .filter(|_| self.get_variant().is_standard())
+            .and_then(|book| {
+                book.choose_move(&self.board, colour, self.last_move.as_ref(), &moves)
+            });
+        match book_move {
+            Some(m) => vec![Line::new(vec![m], m.get_evaluation())],
+            None => self.choose_move(options, colour, moves, max_depth),
+        }
+    }
+
+    /// Take back the last move, restoring the game exactly as it was before it. Returns the
+    /// move taken back, if any.
+    pub fn undo(&mut self) -> Option<HalfMove> {
+        let previous = self.history.pop()?;
+        let undone = self.last_move;
+        let current = self.take_snapshot();
+        self.restore_snapshot(previous);
+        self.tree.back();
+        self.undone.push(current);
+
+        undone
+    }
+
+    /// Play again the last move taken back, if no other move has been played since.
+    pub fn redo(&mut self) -> Option<HalfMove> {
+        let next = self.undone.pop()?;
+        let current = self.take_snapshot();
+        self.restore_snapshot(next);
+        if let Some(play) = self.last_move {
+            self.tree.add_move(play);
+        }
+        self.history.push(current);
+
+        self.last_move
+    }
+
+    /// Bookkeeping after a move has been played on the board.
+    fn record_move(&mut self, colour: Colour, play: HalfMove, snapshot: Snapshot) {
+        // Keep track of how many consecutive moves have been done without moving a pawn or capturing
+        self.consecutive_moves = Game::update_consecutive_moves(&play, self.consecutive_moves);
+
+        // Keep track of played positions
+        self.positions
+            .push(utils::hash_position(&self.board, colour.get_opposite()));
+        self.last_move = Some(play);
+
+        // Keep track of the moves, White ones opening a new turn
+        let seconds = self.get_clock(colour).get_last_seconds();
+        let line = self.get_principal_variation().cloned();
+        match colour {
+            Colour::White => {
+                let mut full_move = FullMove::new();
+                full_move.set_white_move(play);
+                full_move.set_white_seconds(seconds);
+                full_move.set_white_line(line);
+                let index = self.tree.get_first_turn() + self.turns.len() as u16;
+                self.turns.push(Turn::new(index, full_move));
+            }
+            Colour::Black => {
+                if self.turns.is_empty() {
+                    let index = self.tree.get_first_turn();
+                    self.turns.push(Turn::new(index, FullMove::new()));
+                }
+                let full_move = self.turns.last_mut().unwrap().get_moves_mut();
+                full_move.set_black_move(play);
+                full_move.set_black_seconds(seconds);
+                full_move.set_black_line(line);
+            }
+        }
+
+        // Record the move in the tree, other lines of the search as variations
+        self.tree.add_move(play);
+        self.tree.back();
+        let alternatives: Vec<Line> = self.principal_variations.iter().skip(1).cloned().collect();
+        for line in &alternatives {
+            self.add_variation(line);
+        }
+        self.tree.add_move(play);
+
+        // Moves taken back stay in the tree, as variations of the move played instead
+        self.tree.promote_variation();
+
+        // A new move makes the moves taken back unreachable
+        self.history.push(snapshot);
+        self.undone.clear();
+    }
+
+    /// Add a line as a variation of the next move, commented with its evaluation.
+    fn add_variation(&mut self, line: &Line) {
+        for play in line.get_moves() {
+            self.tree.add_move(*play);
+        }
+        self.tree
+            .set_comment(Some(utils::format_evaluation(line.get_evaluation())));
+        for _ in line.get_moves() {
+            self.tree.back();
+        }
+    }
+
+    /// Result of a game that is over.
+    fn set_result(&mut self) {
+        let flagged = [Colour::White, Colour::Black]
+            .into_iter()
+            .find(|c| self.get_clock(*c).is_flagged());
+        let winner = match flagged {
+            Some(colour) if self.board.can_checkmate(colour.get_opposite()) => {
+                Some(colour.get_opposite())
+            }
+            Some(_) => None,
+            None => self.get_outcome().and_then(|o| o.get_winner()),
+        };
+        self.result = match winner {
+            Some(Colour::White) => String::from("1-0"),
+            Some(Colour::Black) => String::from("0-1"),
+            None => String::from("1/2-1/2"),
+        };
+    }
+
+    /// Mark the last move of the given player as checkmate or stalemate, as the opponent can't move.
+    fn set_final_move(&mut self, colour: Colour) {
+        let play = self.turns.last().and_then(|t| match colour {
+            Colour::White => *t.get_moves().get_white_move(),
+            Colour::Black => *t.get_moves().get_black_move(),
+        });
+        let play = self.eventually_set_checkmate(play);
+        let play = self.eventually_set_stalemate(play);
+        if let (Some(play), Some(turn)) = (play, self.turns.last_mut()) {
+            self.last_move = Some(play);
+            match colour {
+                Colour::White => turn.get_moves_mut().set_white_move(play),
+                Colour::Black => turn.get_moves_mut().set_black_move(play),
+            }
+        }
+    }
+
+    fn take_snapshot(&self) -> Snapshot {
+        Snapshot {
+            turn_count: self.turns.len(),
+            last_turn: self.turns.last().cloned(),
+            board: self.board.duplicate(),
+            consecutive_moves: self.consecutive_moves,
+            position_count: self.positions.len(),
+            last_position: self.positions.last().copied(),
+            white_clock: self.white_clock.clone(),
+            black_clock: self.black_clock.clone(),
+            last_move: self.last_move,
+            result: self.result.clone(),
+        }
+    }
+
+    /// Restore a snapshot taken one move before or after the current position.
+    fn restore_snapshot(&mut self, snapshot: Snapshot) {
+        self.turns.truncate(snapshot.turn_count.saturating_sub(1));
+        self.turns.extend(snapshot.last_turn);
+        self.board = snapshot.board;
+        self.consecutive_moves = snapshot.consecutive_moves;
+        self.positions
+            .truncate(snapshot.position_count.saturating_sub(1));
+        self.positions.extend(snapshot.last_position);
+        self.white_clock = snapshot.white_clock;
+        self.black_clock = snapshot.black_clock;
+        self.last_move = snapshot.last_move;
+        self.result = snapshot.result;
+    }
+
+    pub fn choose_move(
+        &self,
+        options: &SearchOptions,
+        colour: Colour,
+        moves: Vec<HalfMove>,
+        max_depth: u8,
+    ) -> Vec<Line> {
+        let moves = self.filter_known_moves(colour, moves);
+
+        // A forced move is still searched for its evaluation, but not as deep
+        let max_depth = match moves.len() {
+            1 => (max_depth / 2).max(1),
+            _ => max_depth,
+        };
 
-            // Stop timer
-            self.close_clock(colour, clock);
+        // A search stopped too early may not have evaluated any move
+        let first = moves[0];
+        match search::search(self, options, colour, moves, max_depth) {
+            lines if lines.is_empty() => vec![Line::new(vec![first], first.get_evaluation())],
+            lines => lines,
+        }
+    }
 
-            // Keep track of how many consecutive moves have been done without moving a pawn or capturing
-            self.consecutive_moves =
-                Game::update_consecutive_moves(&chosen_one, self.consecutive_moves);
+    /// Search the position with the side to move until the maximum depth, the move time or the
+    /// interrupt raised from another thread, reporting each completed depth. Unlike `think`, the
+    /// book is ignored and a single legal move is searched to the full depth.
+    pub fn analyse(
+        &self,
+        options: &SearchOptions,
+        max_depth: u8,
+        interrupt: Option<&AtomicBool>,
+        report: &mut dyn FnMut(&Report),
+    ) -> Vec<Line> {
+        let colour = self.get_side_to_move();
+        let moves = Game::select_legal_moves(&self.board, colour);
+        if moves.is_empty() {
+            return vec![];
+        }
 
-            // Keep track of played positions
-            self.positions
-                .push(utils::hash_position(&self.board, colour.get_opposite()));
+        let moves = self.filter_known_moves(colour, moves);
+        search::analyse(self, options, colour, moves, max_depth, interrupt, report)
+    }
 
-            return Some(chosen_one);
+    /// With few pieces left, only keep the moves preserving the result known by the tablebase
+    /// or by the tables generated by the engine itself, both for standard chess only. The
+    /// generated tables only promote to queens, so under-promotions are never kept by them.
+    fn filter_known_moves(&self, colour: Colour, moves: Vec<HalfMove>) -> Vec<HalfMove> {
+        if !self.get_variant().is_standard() {
+            return moves;
         }
+        let moves = match self
+            .tablebase
+            .as_ref()
+            .filter(|t| t.is_probable(&self.board))
+        {
+            Some(tablebase) => tablebase
+                .select_moves(&self.board, colour, &moves, self.consecutive_moves)
+                .unwrap_or(moves),
+            None => moves,
+        };
 
-        // End of the game
-        None
+        match &self.endgame_tables {
+            Some(tables) => tables
+                .select_moves(&self.board, colour, &moves)
+                .unwrap_or(moves),
+            None => moves,
+        }
     }
 
-    fn update_consecutive_moves(play: &HalfMove, consecutive_moves: u8) -> u8 {
+    pub(crate) fn update_consecutive_moves(play: &HalfMove, consecutive_moves: u8) -> u8 {
         if play.is_capture() || play.get_piece() == PieceType::Pawn(Pawn::new(Colour::White)) {
             0
         } else {
@@ -177,7 +728,9 @@ impl Game {
         }
     }
 
-    fn select_legal_moves(&self, board: &Board, colour: Colour) -> Vec<HalfMove> {
+    /// Moves of the player allowed by the rules of the variant, by default those which don't
+    /// leave their king in check.
+    pub fn select_legal_moves(board: &Board, colour: Colour) -> Vec<HalfMove> {
         let mut moves = vec![];
         for (square, piece) in board.get_pieces(colour) {
             for m in piece.available_moves(board, square) {
@@ -185,8 +738,11 @@ impl Game {
             }
         }
 
+        // Same order whatever the order of the pieces in the board, for a deterministic search
+        moves.sort_by_key(|m| (m.get_from(), m.get_to(), m.is_promotion()));
+
         // Keep only legal moves
-        let moves = self.remove_forbidden_moves(board, moves, colour);
+        let moves = board.get_variant().select_moves(board, colour, moves);
 
         // Check for checking moves
         let mut final_moves = vec![];
@@ -198,24 +754,6 @@ impl Game {
         final_moves
     }
 
-    fn remove_forbidden_moves(
-        &self,
-        board: &Board,
-        moves: Vec<HalfMove>,
-        colour: Colour,
-    ) -> Vec<HalfMove> {
-        let mut valid_moves = vec![];
-        for m in moves {
-            let mut board = board.duplicate();
-            board.execute_move(&m);
-            if !board.is_under_check(colour) {
-                valid_moves.push(m);
-            }
-        }
-
-        valid_moves
-    }
-
     fn eventually_set_checkmate(&self, play: Option<HalfMove>) -> Option<HalfMove> {
         match play {
             Some(mut p) => {
@@ -236,150 +774,256 @@ impl Game {
         }
     }
 
-    fn choose_move(
-        &mut self,
-        board: &Board,
-        original_colour: Colour,
-        colour: Colour,
-        moves: Vec<HalfMove>,
-        consecutive_moves: u8,
-        positions: &Vec<u64>,
-        depth: u8,
-        max_depth: u8,
-    ) -> (Option<HalfMove>, f32) {
-        // Keep track of best move
-        let mut best: (Option<HalfMove>, f32) = (None, utils::LOSS);
-
-        // If there is only 1 move, just play it
-        if moves.iter().count() == 1 {
-            best.0 = Some(moves[0]);
-            return best;
-        }
-
-        let is_king_alone = board.get_pieces(original_colour).iter().count() == 1;
-
-        // Try all moves
-        for m in moves {
-            // The final position that will be evaluated
-            let mut final_board = board.duplicate();
-            final_board.execute_move(&m);
-            let updated_consecutive_moves = Game::update_consecutive_moves(&m, consecutive_moves);
-            let mut actual = (Some(m), utils::LOSS);
-            let mut positions = positions.clone();
-            positions.push(utils::hash_position(&final_board, colour.get_opposite()));
-
-            if Game::is_max_consecutive_moves(consecutive_moves) {
-                // Base case: draw for 50 consecutive moves
-                actual.1 = Game::compute_draw_value(is_king_alone);
-            } else if Game::is_triple_repetition(&final_board, colour.get_opposite(), &positions) {
-                // Base case: draw for triple repetition
-                actual.1 = Game::compute_draw_value(is_king_alone);
-            } else if depth >= max_depth {
-                // Base case: stop recursion and evaluate final position
-                actual.1 = self.evaluate(&final_board, original_colour);
-            } else {
-                // Play as enemy
-                let new_colour = colour.get_opposite();
-                let new_moves = self.select_legal_moves(&final_board, new_colour);
-
-                // Base case: game ended
-                if new_moves.is_empty() {
-                    if !m.is_check() {
-                        // Stalemate
-                        actual.1 = Game::compute_draw_value(is_king_alone);
-                    } else if m.get_piece().get_colour() == original_colour {
-                        // Checkmate for enemy
-                        actual.1 = utils::WIN;
-                    } else {
-                        // Checkmate for player
-                        actual.1 = utils::LOSS;
-                    }
-                } else {
-                    // Go to next play
-                    actual = self.choose_move(
-                        &final_board,
-                        original_colour,
-                        new_colour,
-                        new_moves,
-                        updated_consecutive_moves,
-                        &positions,
-                        depth + 1,
-                        max_depth,
-                    );
-                }
+    fn is_over(&self) -> Result<(), Error> {
+        let outcome = self
+            .board
+            .get_variant()
+            .get_outcome(&self.board, self.get_side_to_move());
+        if let Some(outcome) = outcome {
+            Err(Error::msg(outcome.get_reason().to_string()))
+        } else if self.turns.len() >= u8::MAX as usize {
+            Err(Error::msg("Max number of turns"))
+        } else if Game::is_max_consecutive_moves(self.consecutive_moves) {
+            Err(Error::msg(
+                "50 consecutive turns without captures or pawn moves",
+            ))
+        } else if Game::is_triple_repetition(&self.board, self.get_side_to_move(), &self.positions)
+        {
+            Err(Error::msg("Same position repeated for 3 times"))
+        } else if let Some(colour) = [Colour::White, Colour::Black]
+            .into_iter()
+            .find(|c| self.get_clock(*c).is_flagged())
+        {
+            match self.board.can_checkmate(colour.get_opposite()) {
+                true => Err(Error::msg(format!("{:?} ran out of time", colour))),
+                false => Err(Error::msg(format!(
+                    "{:?} ran out of time, but {:?} can't checkmate: draw",
+                    colour,
+                    colour.get_opposite()
+                ))),
             }
+        } else {
+            Ok(())
+        }
+    }
 
-            // Select the move if is better than best one or if is the first one evaluated
-            if actual.1 > best.1 || best.0.is_none() {
-                best.1 = actual.1;
-                let mut best_move = m;
-                best_move.set_evaluation(actual.1);
-                best.0 = Some(best_move);
-            }
+    pub(crate) fn is_triple_repetition(board: &Board, colour: Colour, positions: &[u64]) -> bool {
+        let hash = utils::hash_position(board, colour);
 
-            // If there is a winning move, just play it
-            if best.1 == utils::WIN {
-                return best;
-            }
+        positions.iter().filter(|p| hash == **p).count() >= 3
+    }
+
+    pub(crate) fn is_max_consecutive_moves(consecutive_moves: u8) -> bool {
+        // 100 moves (50 for each player)
+        consecutive_moves >= utils::MAX_CONSECUTIVE_MOVES
+    }
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+
+    use crate::formats::uci;
+    use crate::game::clock::{ManualTime, TimeControl};
+    use crate::game::game::Game;
+    use crate::game::search::SearchOptions;
+    use crate::model::piece::color::Colour;
+    use crate::player::player::{Action, Player};
+
+    #[test]
+    fn forced_move_is_evaluated() {
+        let game = Game::from_fen("r3k3/8/8/8/8/8/2r5/K6N w - - 0 1").unwrap();
+        let colour = game.get_side_to_move();
+        assert_eq!(Game::select_legal_moves(game.get_board(), colour).len(), 1);
+
+        let mut options = SearchOptions::new();
+        options.set_threads(1);
+        let lines = game.think(&options, 2);
+        assert_eq!(uci::to_uci(&lines[0].get_first_move().unwrap()), "a1b1");
+        assert_ne!(lines[0].get_evaluation(), 0.0);
+    }
+
+    /// Plays the first legal move, taking the given time for it.
+    struct SlowPlayer {
+        time: ManualTime,
+        seconds: f32,
+    }
+
+    impl Player for SlowPlayer {
+        fn get_name(&self) -> String {
+            String::from("Slow")
         }
 
-        best
+        fn choose(&mut self, game: &Game) -> Result<Action, Error> {
+            self.time.advance(self.seconds);
+            let moves = Game::select_legal_moves(game.get_board(), game.get_side_to_move());
+            Ok(Action::Move(moves[0]))
+        }
     }
 
-    fn compute_draw_value(is_king_alone: bool) -> f32 {
-        // If only the king is on the board, draw is equal to win
-        match is_king_alone {
-            true => utils::WIN,
-            false => utils::DRAW,
+    #[test]
+    fn lose_on_time() {
+        let time = ManualTime::new();
+        let mut game = Game::new();
+        game.set_verbose(false);
+        game.set_time_control(TimeControl::sudden_death(60.0));
+        game.set_time_source(time.clone());
+
+        let mut white = SlowPlayer {
+            time: time.clone(),
+            seconds: 1.0,
+        };
+        let mut black = SlowPlayer {
+            time: time.clone(),
+            seconds: 25.0,
+        };
+        game.play(&mut white, &mut black);
+
+        assert_eq!(game.get_result(), "1-0");
+        assert!(game.get_clock(Colour::Black).is_flagged());
+        assert_eq!(
+            game.get_clock(Colour::White).get_remaining_seconds(),
+            Some(57.0)
+        );
+        assert_eq!(game.get_turns().len(), 3);
+    }
+
+    /// Thinks for too long and gives up.
+    struct FrozenPlayer {
+        time: ManualTime,
+    }
+
+    impl Player for FrozenPlayer {
+        fn get_name(&self) -> String {
+            String::from("Frozen")
+        }
+
+        fn choose(&mut self, _game: &Game) -> Result<Action, Error> {
+            self.time.advance(61.0);
+            Err(Error::msg("No answer"))
         }
     }
 
-    fn evaluate(&self, board: &Board, colour: Colour) -> f32 {
-        // Evaluate material
-        let material = board.evaluate_material(colour);
-        // Evaluate activity
-        let activity = board.evaluate_activity(colour);
-        // Evaluate proximity
-        let proximity = board.evaluate_proximity(colour);
+    #[test]
+    fn player_not_answering_loses_on_time() {
+        for (fen, result) in [
+            ("4k3/8/8/8/8/8/3Q4/4K3 b - - 0 1", "1-0"),
+            ("4k3/8/8/8/8/8/3N4/4K3 b - - 0 1", "1/2-1/2"),
+        ] {
+            let time = ManualTime::new();
+            let mut game = Game::from_fen(fen).unwrap();
+            game.set_verbose(false);
+            game.set_time_control(TimeControl::sudden_death(60.0));
+            game.set_time_source(time.clone());
 
-        // TODO other evaluations (king safety, ...)
+            let mut white = FrozenPlayer { time: time.clone() };
+            let mut black = FrozenPlayer { time };
+            game.play(&mut white, &mut black);
+            assert!(game.get_clock(Colour::Black).is_flagged());
+            assert_eq!(game.get_result(), result);
+        }
+    }
 
-        (material * utils::MATERIAL_FACTOR)
-            + (activity * utils::ACTIVITY_FACTOR)
-            + (proximity + utils::PROXIMITY_FACTOR)
+    /// Takes its actions from a script of moves, "undo" and "redo", resigning at its end.
+    struct ScriptedPlayer {
+        script: Vec<&'static str>,
     }
 
-    fn is_over(
-        &self,
-        count: u8,
-        last_white_move: Option<HalfMove>,
-        last_black_move: Option<HalfMove>,
-    ) -> Result<(), Error> {
-        if count >= u8::MAX {
-            Err(Error::msg("Max number of turns"))
-        } else if Game::is_max_consecutive_moves(self.consecutive_moves) {
-            Err(Error::msg(
-                "50 consecutive turns without captures or pawn moves",
-            ))
-        } else if Game::is_triple_repetition(&self.board, Colour::White, &self.positions) {
-            Err(Error::msg("Same position repeated for 3 times"))
-        } else if !self.turns.is_empty() && last_white_move.is_none() {
-            Err(Error::msg("White doesn't have any legal move to play"))
-        } else if !self.turns.is_empty() && last_black_move.is_none() {
-            Err(Error::msg("Black doesn't have any legal move to play"))
-        } else {
-            Ok(())
+    impl Player for ScriptedPlayer {
+        fn get_name(&self) -> String {
+            String::from("Scripted")
         }
+
+        fn choose(&mut self, game: &Game) -> Result<Action, Error> {
+            let text = self.script.pop().ok_or(Error::msg("End of script"))?;
+            Ok(match text {
+                "undo" => Action::Undo,
+                "redo" => Action::Redo,
+                _ => Action::Move(
+                    uci::parse_uci(game.get_board(), game.get_side_to_move(), text).unwrap(),
+                ),
+            })
+        }
+    }
+
+    fn play_script(white: &[&'static str], black: &[&'static str]) -> Game {
+        let mut game = Game::new();
+        game.set_verbose(false);
+        let script = |moves: &[&'static str]| ScriptedPlayer {
+            script: moves.iter().rev().copied().collect(),
+        };
+        game.play(&mut script(white), &mut script(black));
+        game
     }
 
-    fn is_triple_repetition(board: &Board, colour: Colour, positions: &Vec<u64>) -> bool {
-        let hash = utils::hash_position(&board, colour);
+    #[test]
+    fn undo_in_play() {
+        // White takes back 1. e4 e5 and plays 1. d4 instead, nothing is left to play again
+        let game = play_script(&["e2e4", "undo", "d2d4", "redo"], &["e7e5", "d7d5"]);
+        let moves: Vec<String> = game.get_moves().iter().map(uci::to_uci).collect();
+        assert_eq!(moves, ["d2d4", "d7d5"]);
+        assert_eq!(game.get_turns().len(), 1);
+        assert_eq!(game.get_positions().len(), 3);
+        assert_eq!(game.get_result(), "0-1");
 
-        positions.iter().filter(|p| hash == **p).count() >= 3
+        // The moves taken back are kept as a variation
+        let root = game.get_tree().get_root();
+        let first: Vec<String> = root
+            .get_children()
+            .iter()
+            .map(|n| uci::to_uci(&n.get_move().unwrap()))
+            .collect();
+        assert_eq!(first, ["d2d4", "e2e4"]);
     }
 
-    fn is_max_consecutive_moves(consecutive_moves: u8) -> bool {
-        // 100 moves (50 for each player)
-        consecutive_moves >= utils::MAX_CONSECUTIVE_MOVES
+    #[test]
+    fn redo_in_play() {
+        let game = play_script(&["e2e4", "g1f3", "undo", "redo", "f1c4"], &["e7e5", "b8c6"]);
+        let moves: Vec<String> = game.get_moves().iter().map(uci::to_uci).collect();
+        assert_eq!(moves, ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4"]);
+        assert_eq!(game.get_tree().get_root().get_children().len(), 1);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_game() {
+        let mut game = Game::new();
+        for text in ["e2e4", "e7e5", "g1f3"] {
+            let play = uci::parse_uci(game.get_board(), game.get_side_to_move(), text).unwrap();
+            game.make_move(play).unwrap();
+        }
+        let fen = game.get_fen();
+        let positions = game.get_positions().clone();
+
+        assert!(game.undo().is_some());
+        assert!(game.undo().is_some());
+        assert_eq!(game.get_turns().len(), 1);
+        assert_eq!(game.get_positions().len(), 2);
+        assert_eq!(game.get_tree().get_ply(), 1);
+
+        assert!(game.redo().is_some());
+        assert!(game.redo().is_some());
+        assert!(game.redo().is_none());
+        assert_eq!(game.get_fen(), fen);
+        assert_eq!(game.get_positions(), &positions);
+        assert_eq!(game.get_turns().len(), 2);
+        assert_eq!(game.get_tree().get_ply(), 3);
+    }
+
+    #[test]
+    fn late_move_numbers() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w - - 0 300").unwrap();
+        for text in ["a1a2", "e8d8"] {
+            let play = uci::parse_uci(game.get_board(), game.get_side_to_move(), text).unwrap();
+            game.make_move(play).unwrap();
+        }
+        assert_eq!(game.get_turns()[0].get_index(), 300);
+        assert_eq!(game.get_move_number(), 301);
+        assert!(game.get_fen().ends_with(" 2 301"));
     }
 }
@@ -1,4 +1,10 @@
-pub(crate) mod clock;
-pub(crate) mod game;
-pub(crate) mod play;
-mod turn;
+pub mod chess960;
+pub mod clock;
+#[allow(clippy::module_inception)]
+pub mod game;
+pub mod perft;
+pub mod play;
+pub mod search;
+pub(crate) mod transposition;
+pub mod tree;
+pub mod turn;
@@ -0,0 +1,89 @@
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::variant::variant::Variant;
+
+/// Number of positions reached by all the sequences of legal moves of the given length, to check
+/// the move generation against known counts. Positions where the variant ends the game have no
+/// moves.
+pub fn perft(board: &Board, colour: Colour, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if board.get_variant().get_outcome(board, colour).is_some() {
+        return 0;
+    }
+    let moves = Game::select_legal_moves(board, colour);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves
+        .iter()
+        .map(|play| {
+            let mut board = board.duplicate();
+            board.execute_move(play);
+            perft(&board, colour.get_opposite(), depth - 1)
+        })
+        .sum()
+}
+
+/// Perft counts after each legal move, to find where the move generation goes wrong.
+pub fn divide(board: &Board, colour: Colour, depth: u8) -> Vec<(HalfMove, u64)> {
+    Game::select_legal_moves(board, colour)
+        .into_iter()
+        .map(|play| {
+            let mut board = board.duplicate();
+            board.execute_move(&play);
+            (play, perft(&board, colour.get_opposite(), depth.max(1) - 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fen;
+    use crate::variant::variant::VariantType;
+
+    fn perft_counts(key: &str, depth: u8) -> Vec<u64> {
+        let variant = VariantType::parse(key).unwrap();
+        let position = fen::parse_variant_fen(variant.get_start_fen(), variant).unwrap();
+        (1..=depth)
+            .map(|d| perft(position.get_board(), position.get_colour(), d))
+            .collect()
+    }
+
+    #[test]
+    fn standard() {
+        assert_eq!(perft_counts("chess", 3), vec![20, 400, 8902]);
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        assert_eq!(perft_counts("kingofthehill", 3), vec![20, 400, 8902]);
+    }
+
+    #[test]
+    fn three_check() {
+        assert_eq!(perft_counts("3check", 3), vec![20, 400, 8902]);
+    }
+
+    #[test]
+    fn antichess() {
+        assert_eq!(perft_counts("antichess", 3), vec![20, 400, 8067]);
+    }
+
+    #[test]
+    fn atomic() {
+        assert_eq!(perft_counts("atomic", 4), vec![20, 400, 8902, 197326]);
+    }
+
+    #[test]
+    fn horde() {
+        // The published counts go on with 1274 and 23310, which include en-passant captures
+        // of the white pawns, not made by the engine
+        assert_eq!(perft_counts("horde", 2), vec![8, 128]);
+    }
+}
@@ -12,6 +12,7 @@ pub struct HalfMove {
     to: Square,
     capture: bool,
     promotion: bool,
+    castling: bool,
     check: bool,
     checkmate: bool,
     stalemate: bool,
@@ -19,30 +20,38 @@ pub struct HalfMove {
 }
 
 impl HalfMove {
-    pub fn new(
-        piece: PieceType,
-        from: Square,
-        to: Square,
-        capture: bool,
-        promotion: bool,
-        check: bool,
-        checkmate: bool,
-        stalemate: bool,
-        evaluation: f32,
-    ) -> HalfMove {
+    /// Quiet move of a piece, to be completed with the `with_` methods.
+    pub fn new(piece: PieceType, from: Square, to: Square) -> HalfMove {
         HalfMove {
             piece,
             from,
             to,
-            capture,
-            promotion,
-            check,
-            checkmate,
-            stalemate,
-            evaluation,
+            capture: false,
+            promotion: false,
+            castling: false,
+            check: false,
+            checkmate: false,
+            stalemate: false,
+            evaluation: 0.0,
         }
     }
 
+    pub fn with_capture(mut self, capture: bool) -> HalfMove {
+        self.capture = capture;
+        self
+    }
+
+    pub fn with_promotion(mut self, promotion: bool) -> HalfMove {
+        self.promotion = promotion;
+        self
+    }
+
+    /// Castling, encoded as the king moving to the square of its rook as in Chess960.
+    pub fn with_castling(mut self, castling: bool) -> HalfMove {
+        self.castling = castling;
+        self
+    }
+
     pub fn get_piece(&self) -> PieceType {
         self.piece
     }
@@ -63,6 +72,25 @@ impl HalfMove {
         self.promotion
     }
 
+    pub fn is_castling(&self) -> bool {
+        self.castling
+    }
+
+    /// Whether the move is a castling with the rook on the side of the h-file.
+    pub fn is_kingside_castling(&self) -> bool {
+        self.castling && self.to.get_file() > self.from.get_file()
+    }
+
+    /// Squares where the king and the rook of a castling end, the standard ones whatever their
+    /// starting files.
+    pub fn get_castling_squares(&self) -> (Square, Square) {
+        let rank = self.from.get_rank();
+        match self.is_kingside_castling() {
+            true => (Square::new(rank, 6), Square::new(rank, 5)),
+            false => (Square::new(rank, 2), Square::new(rank, 3)),
+        }
+    }
+
     pub fn is_check(&self) -> bool {
         self.check
     }
@@ -105,12 +133,12 @@ impl Display for HalfMove {
         let symbol = match self.piece {
             PieceType::Pawn(_) => {
                 if self.capture {
-                    utils::normalize_file(self.from.get_file())
+                    utils::normalize_file(self.from.get_file()).to_string()
                 } else {
-                    '\0'
+                    String::new()
                 }
             }
-            _ => self.piece.get_symbol(),
+            _ => self.piece.get_symbol().to_string(),
         };
         let capture = match self.capture {
             true => "x",
@@ -131,6 +159,13 @@ impl Display for HalfMove {
             true => "(=)",
             false => "",
         };
+        if self.castling {
+            let castling = match self.is_kingside_castling() {
+                true => "O-O",
+                false => "O-O-O",
+            };
+            return f.write_str(&format!("{}{}{}", castling, check, stale));
+        }
         f.write_str(&format!(
             "{}{}{}{}{}{}",
             symbol, capture, self.to, promotion, check, stale
@@ -138,12 +173,46 @@ impl Display for HalfMove {
     }
 }
 
-#[derive(Debug)]
+/// A sequence of moves expected by the search, with the evaluation of the position it leads to.
+#[derive(Debug, Clone)]
+pub struct Line {
+    moves: Vec<HalfMove>,
+    evaluation: f32,
+}
+
+impl Line {
+    pub fn new(moves: Vec<HalfMove>, evaluation: f32) -> Line {
+        Line { moves, evaluation }
+    }
+
+    pub fn get_moves(&self) -> &Vec<HalfMove> {
+        &self.moves
+    }
+
+    pub fn get_first_move(&self) -> Option<HalfMove> {
+        self.moves.first().copied()
+    }
+
+    pub fn get_evaluation(&self) -> f32 {
+        self.evaluation
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let moves: Vec<String> = self.moves.iter().map(|m| format!("{}", m)).collect();
+        f.write_str(&moves.join(" "))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct FullMove {
     white_move: Option<HalfMove>,
     white_seconds: f32,
+    white_line: Option<Line>,
     black_move: Option<HalfMove>,
     black_seconds: f32,
+    black_line: Option<Line>,
 }
 
 impl FullMove {
@@ -151,8 +220,10 @@ impl FullMove {
         FullMove {
             white_move: None,
             white_seconds: 0.0,
+            white_line: None,
             black_move: None,
             black_seconds: 0.0,
+            black_line: None,
         }
     }
 
@@ -164,6 +235,10 @@ impl FullMove {
         self.white_seconds
     }
 
+    pub fn get_white_line(&self) -> &Option<Line> {
+        &self.white_line
+    }
+
     pub fn get_black_move(&self) -> &Option<HalfMove> {
         &self.black_move
     }
@@ -172,6 +247,10 @@ impl FullMove {
         self.black_seconds
     }
 
+    pub fn get_black_line(&self) -> &Option<Line> {
+        &self.black_line
+    }
+
     pub fn set_white_move(&mut self, m: HalfMove) -> () {
         self.white_move = Some(m);
     }
@@ -180,6 +259,10 @@ impl FullMove {
         self.white_seconds = seconds;
     }
 
+    pub fn set_white_line(&mut self, line: Option<Line>) {
+        self.white_line = line;
+    }
+
     pub fn set_black_move(&mut self, m: HalfMove) -> () {
         self.black_move = Some(m);
     }
@@ -187,6 +270,16 @@ impl FullMove {
     pub fn set_black_seconds(&mut self, seconds: f32) -> () {
         self.black_seconds = seconds;
     }
+
+    pub fn set_black_line(&mut self, line: Option<Line>) {
+        self.black_line = line;
+    }
+}
+
+impl Default for FullMove {
+    fn default() -> FullMove {
+        FullMove::new()
+    }
 }
 
 impl Display for FullMove {
@@ -203,14 +296,40 @@ impl Display for FullMove {
         let white_value = self.get_white_move().map_or(0.0, |v| v.evaluation);
         let black_value = self.get_black_move().map_or(0.0, |v| v.evaluation);
 
+        let white_line = self
+            .get_white_line()
+            .as_ref()
+            .map_or(String::from("..."), |line| format!("{}", line));
+        let black_line = self
+            .get_black_line()
+            .as_ref()
+            .map_or(String::from("..."), |line| format!("{}", line));
+
         f.write_str(&format!(
-            "{} {} ({:010.6} - {:010.6}) [{:07.4} - {:07.4}]",
+            "{} {} ({:010.6} - {:010.6}) [{} - {}] {{{} - {}}}",
             white_move,
             black_move,
             self.white_seconds,
             self.black_seconds,
-            white_value,
-            black_value
+            utils::format_evaluation(white_value),
+            utils::format_evaluation(black_value),
+            white_line,
+            black_line
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{fen, uci};
+    use crate::model::piece::color::Colour;
+
+    #[test]
+    fn pawn_moves_without_symbol() {
+        let board = fen::parse_fen(fen::START_FEN).unwrap().into_board();
+        let e4 = uci::parse_uci(&board, Colour::White, "e2e4").unwrap();
+        let nf3 = uci::parse_uci(&board, Colour::White, "g1f3").unwrap();
+        assert_eq!(Line::new(vec![e4, nf3], 0.0).to_string(), "e4 Nf3");
+    }
+}
@@ -0,0 +1,865 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::game::game::Game;
+use crate::game::play::{HalfMove, Line};
+use crate::game::transposition::{Bound, TableEntry, TranspositionTable};
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::{Piece, PieceType};
+use crate::tablebase::syzygy::{Tablebase, Wdl};
+use crate::utils;
+use crate::variant::variant::{Outcome, Variant, VariantType};
+
+#[derive(Debug, Copy, Clone)]
+pub struct SearchOptions {
+    threads: usize,
+    table_size: usize,
+    multi_pv: usize,
+    null_move_pruning: bool,
+    late_move_reductions: bool,
+    check_extensions: bool,
+    move_time: Option<f32>,
+}
+
+impl SearchOptions {
+    pub fn new() -> SearchOptions {
+        SearchOptions {
+            threads: 1,
+            table_size: utils::TRANSPOSITION_TABLE_SIZE,
+            multi_pv: 1,
+            null_move_pruning: true,
+            late_move_reductions: true,
+            check_extensions: true,
+            move_time: None,
+        }
+    }
+
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn get_table_size(&self) -> usize {
+        self.table_size
+    }
+
+    pub fn get_multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    pub fn is_null_move_pruning(&self) -> bool {
+        self.null_move_pruning
+    }
+
+    pub fn is_late_move_reductions(&self) -> bool {
+        self.late_move_reductions
+    }
+
+    pub fn is_check_extensions(&self) -> bool {
+        self.check_extensions
+    }
+
+    /// Seconds after which the search stops, keeping the deepest completed iteration.
+    pub fn get_move_time(&self) -> Option<f32> {
+        self.move_time
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    pub fn set_null_move_pruning(&mut self, null_move_pruning: bool) {
+        self.null_move_pruning = null_move_pruning;
+    }
+
+    pub fn set_late_move_reductions(&mut self, late_move_reductions: bool) {
+        self.late_move_reductions = late_move_reductions;
+    }
+
+    pub fn set_check_extensions(&mut self, check_extensions: bool) {
+        self.check_extensions = check_extensions;
+    }
+
+    pub fn set_move_time(&mut self, move_time: Option<f32>) {
+        self.move_time = move_time;
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> SearchOptions {
+        SearchOptions::new()
+    }
+}
+
+/// Result of the deepest iteration completed by a search worker.
+type Iteration = (u8, Vec<Line>);
+
+/// Progress of a search, reported each time the main worker completes an iteration.
+#[derive(Debug, Clone)]
+pub struct Report {
+    depth: u8,
+    lines: Vec<Line>,
+    nodes: u64,
+    elapsed: Duration,
+}
+
+impl Report {
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Best lines found by the iteration, the best one first.
+    pub fn get_lines(&self) -> &Vec<Line> {
+        &self.lines
+    }
+
+    /// Positions searched so far by all the workers.
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn get_nodes_per_second(&self) -> u64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-6)) as u64
+    }
+}
+
+/// Lazy SMP: every worker runs its own iterative deepening on its own copy of the board, and
+/// they only cooperate through the shared transposition table. Helpers start one ply deeper
+/// every other thread so that they fill the table ahead of the main worker.
+pub fn search(
+    game: &Game,
+    options: &SearchOptions,
+    colour: Colour,
+    moves: Vec<HalfMove>,
+    max_depth: u8,
+) -> Vec<Line> {
+    analyse(game, options, colour, moves, max_depth, None, &mut |_| {})
+}
+
+/// Same as `search`, but also stopping once the interrupt is raised from another thread, and
+/// reporting the progress after each completed iteration.
+pub fn analyse(
+    game: &Game,
+    options: &SearchOptions,
+    colour: Colour,
+    moves: Vec<HalfMove>,
+    max_depth: u8,
+    interrupt: Option<&AtomicBool>,
+    report: &mut dyn FnMut(&Report),
+) -> Vec<Line> {
+    let board = game.get_board();
+    let consecutive_moves = game.get_consecutive_moves();
+    let positions = game.get_positions();
+    // Tablebases only know the results of standard chess
+    let tablebase = game
+        .get_tablebase()
+        .filter(|_| game.get_variant().is_standard());
+    let table = TranspositionTable::new(options.get_table_size());
+    let start = Instant::now();
+    let deadline = options
+        .get_move_time()
+        .map(|seconds| start + Duration::from_secs_f32(seconds.max(0.0)));
+    let shared = Shared::new(deadline, interrupt);
+    let mut on_iteration = |depth: u8, lines: &[Line]| {
+        report(&Report {
+            depth,
+            lines: lines.to_vec(),
+            nodes: shared.nodes.load(Ordering::Relaxed),
+            elapsed: start.elapsed(),
+        })
+    };
+
+    let best = match options.get_threads() {
+        // Deterministic single thread search
+        1 => Worker::new(options, &table, &shared, tablebase, colour, 0, positions).iterate(
+            board,
+            moves,
+            consecutive_moves,
+            max_depth,
+            &mut on_iteration,
+        ),
+        threads => thread::scope(|scope| {
+            let helpers: Vec<_> = (1..threads)
+                .map(|index| {
+                    let (table, shared, moves) = (&table, &shared, moves.clone());
+                    scope.spawn(move || {
+                        let board = board.duplicate();
+                        Worker::new(options, table, shared, tablebase, colour, index, positions)
+                            .iterate(&board, moves, consecutive_moves, max_depth, &mut |_, _| {})
+                    })
+                })
+                .collect();
+
+            let main = Worker::new(options, &table, &shared, tablebase, colour, 0, positions)
+                .iterate(
+                    board,
+                    moves,
+                    consecutive_moves,
+                    max_depth,
+                    &mut on_iteration,
+                );
+
+            // Helpers are useless once the main worker is done
+            shared.stop();
+
+            // Combine results from the deepest completed iteration, preferring the main worker
+            let mut best = main;
+            for helper in helpers {
+                if let Ok(Some(result)) = helper.join() {
+                    if best.as_ref().is_none_or(|b| result.0 > b.0) {
+                        best = Some(result);
+                    }
+                }
+            }
+            best
+        }),
+    };
+
+    best.map_or(vec![], |(_, lines)| lines)
+}
+
+/// State shared by the workers: the signal to end the search, raised by hand, from outside or
+/// once the deadline passes, and the number of positions searched.
+struct Shared<'a> {
+    flag: AtomicBool,
+    interrupt: Option<&'a AtomicBool>,
+    deadline: Option<Instant>,
+    nodes: AtomicU64,
+}
+
+impl<'a> Shared<'a> {
+    fn new(deadline: Option<Instant>, interrupt: Option<&'a AtomicBool>) -> Shared<'a> {
+        Shared {
+            flag: AtomicBool::new(false),
+            interrupt,
+            deadline,
+            nodes: AtomicU64::new(0),
+        }
+    }
+
+    fn stop(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    fn is_stopped(&self) -> bool {
+        if self.deadline.is_some_and(|d| Instant::now() >= d)
+            || self.interrupt.is_some_and(|i| i.load(Ordering::Relaxed))
+        {
+            self.stop();
+        }
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+struct Worker<'a> {
+    options: &'a SearchOptions,
+    table: &'a TranspositionTable,
+    shared: &'a Shared<'a>,
+    tablebase: Option<&'a Tablebase>,
+    root_colour: Colour,
+    index: usize,
+    positions: Vec<u64>,
+    ply: u8,
+    max_ply: u8,
+    after_null_move: bool,
+    principal_variations: Vec<Vec<HalfMove>>,
+    nodes: u64,
+}
+
+impl<'a> Worker<'a> {
+    fn new(
+        options: &'a SearchOptions,
+        table: &'a TranspositionTable,
+        shared: &'a Shared<'a>,
+        tablebase: Option<&'a Tablebase>,
+        root_colour: Colour,
+        index: usize,
+        positions: &[u64],
+    ) -> Worker<'a> {
+        Worker {
+            options,
+            table,
+            shared,
+            tablebase,
+            root_colour,
+            index,
+            positions: positions.to_vec(),
+            ply: 0,
+            max_ply: 0,
+            after_null_move: false,
+            principal_variations: vec![],
+            nodes: 0,
+        }
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.shared.is_stopped()
+    }
+
+    /// Add the positions searched since the last call to the shared count.
+    fn flush_nodes(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.shared.nodes.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    fn iterate(
+        &mut self,
+        board: &Board,
+        moves: Vec<HalfMove>,
+        consecutive_moves: u8,
+        max_depth: u8,
+        on_iteration: &mut dyn FnMut(u8, &[Line]),
+    ) -> Option<Iteration> {
+        let mut best = None;
+
+        let mut depth = (self.index % 2) as u8;
+        while depth <= max_depth {
+            let lines = self.search_root(board, moves.clone(), consecutive_moves, depth);
+
+            // An interrupted iteration cannot be trusted, unless there is nothing better
+            if self.is_stopped() {
+                if best.is_none() && !lines.is_empty() {
+                    best = Some((depth, lines));
+                }
+                break;
+            }
+            if !lines.is_empty() {
+                on_iteration(depth, &lines);
+                best = Some((depth, lines));
+            }
+            depth += 1;
+        }
+
+        best
+    }
+
+    fn search_root(
+        &mut self,
+        board: &Board,
+        moves: Vec<HalfMove>,
+        consecutive_moves: u8,
+        depth: u8,
+    ) -> Vec<Line> {
+        let hash = utils::hash_position(board, self.root_colour);
+        let multi_pv = self.options.get_multi_pv();
+        let mut lines: Vec<Line> = vec![];
+
+        // Check extensions can at most double the nominal depth
+        self.max_ply = 2 * (depth + 1);
+
+        // Helpers try root moves in a different order, so they don't all walk the same tree
+        let mut moves = self.order_moves(board, hash, moves);
+        let shift = self.index % moves.len().max(1);
+        moves.rotate_left(shift);
+
+        for m in moves {
+            // Only moves beating the worst of the lines kept so far need an exact evaluation
+            let alpha = match lines.len() >= multi_pv {
+                true => lines[multi_pv - 1].get_evaluation(),
+                false => f32::NEG_INFINITY,
+            };
+            let depth = depth + self.extension(&m);
+            let evaluation =
+                self.search_move(board, &m, consecutive_moves, depth, alpha, f32::INFINITY);
+            self.flush_nodes();
+
+            if self.is_stopped() {
+                break;
+            }
+
+            // Keep the move if it is among the best ones or if it is the first one evaluated
+            if evaluation > alpha || lines.is_empty() {
+                let mut first_move = m;
+                first_move.set_evaluation(evaluation);
+                let mut moves = vec![first_move];
+                moves.extend(self.get_principal_variation(1));
+
+                let index = lines.partition_point(|l| l.get_evaluation() >= evaluation);
+                lines.insert(index, Line::new(moves, evaluation));
+                lines.truncate(multi_pv);
+            }
+        }
+
+        if let Some(m) = lines.first().and_then(|l| l.get_first_move()) {
+            let entry = TableEntry::new(
+                depth + 1,
+                Bound::Exact,
+                lines[0].get_evaluation(),
+                Some((m.get_from(), m.get_to())),
+            );
+            self.table.store(hash, entry);
+        }
+
+        lines
+    }
+
+    /// Minimax with alpha-beta pruning, always evaluating from the point of view of the colour
+    /// that started the search.
+    fn alpha_beta(
+        &mut self,
+        board: &Board,
+        colour: Colour,
+        consecutive_moves: u8,
+        depth: u8,
+        mut alpha: f32,
+        mut beta: f32,
+    ) -> f32 {
+        if self.is_stopped() {
+            return 0.0;
+        }
+
+        self.set_principal_variation(vec![]);
+        let after_null_move = std::mem::replace(&mut self.after_null_move, false);
+        let is_king_alone = board.get_pieces(self.root_colour).len() == 1;
+
+        // Base case: draw for 50 consecutive moves or triple repetition
+        if Game::is_max_consecutive_moves(consecutive_moves)
+            || Game::is_triple_repetition(board, colour, &self.positions)
+        {
+            return Worker::compute_draw_value(is_king_alone);
+        }
+
+        // Base case: game won or lost by the rules of the variant
+        let variant = board.get_variant();
+        if let Some(outcome) = variant.get_outcome(board, colour) {
+            return self.evaluate_outcome(outcome, is_king_alone);
+        }
+
+        // Base case: result known by the endgame tablebase
+        let wdl = self
+            .tablebase
+            .filter(|t| t.is_probable(board))
+            .and_then(|t| t.probe_wdl(board, colour));
+        if let Some(wdl) = wdl {
+            return self.evaluate_tablebase(wdl, colour, is_king_alone);
+        }
+
+        // Base case: stop recursion and evaluate final position, once quiet
+        if depth == 0 {
+            return self.quiescence(board, colour, utils::QUIESCENCE_PLIES, alpha, beta);
+        }
+
+        let hash = utils::hash_position(board, colour);
+        let entry = self.table.probe(hash);
+        if let Some(entry) = entry.filter(|e| e.get_depth() >= depth) {
+            let evaluation = self.adjust_from_table(entry.get_evaluation());
+            match entry.get_bound() {
+                Bound::Exact => return evaluation,
+                Bound::Lower => alpha = alpha.max(evaluation),
+                Bound::Upper => beta = beta.min(evaluation),
+            }
+            if alpha >= beta {
+                return evaluation;
+            }
+        }
+
+        // Base case: game ended
+        let moves = Game::select_legal_moves(board, colour);
+        if moves.is_empty() {
+            let outcome = variant.get_outcome_without_moves(board, colour);
+            return self.evaluate_outcome(outcome, is_king_alone);
+        }
+
+        let is_maximizing = colour == self.root_colour;
+        let is_in_check = board.is_under_check(colour);
+
+        // Null move pruning: if passing still beats the window, a real move would too
+        if self.options.is_null_move_pruning()
+            && !after_null_move
+            && !is_in_check
+            && depth > utils::NULL_MOVE_REDUCTION
+            && !Worker::is_zugzwang_prone(board, colour)
+        {
+            let evaluation =
+                self.search_null_move(board, colour, consecutive_moves, depth, alpha, beta);
+            if is_maximizing && evaluation >= beta {
+                return beta;
+            }
+            if !is_maximizing && evaluation <= alpha {
+                return alpha;
+            }
+        }
+
+        let (original_alpha, original_beta) = (alpha, beta);
+        let mut best: (Option<HalfMove>, f32) = match is_maximizing {
+            true => (None, f32::NEG_INFINITY),
+            false => (None, f32::INFINITY),
+        };
+
+        for (index, m) in self.order_moves(board, hash, moves).into_iter().enumerate() {
+            let new_depth = depth - 1 + self.extension(&m);
+
+            // Late move reductions: quiet moves ordered late are searched one ply shallower
+            // first, and searched again at full depth only if they look better than expected
+            let is_late_move = self.options.is_late_move_reductions()
+                && !is_in_check
+                && index >= utils::LATE_MOVE_INDEX
+                && new_depth >= 2
+                && !m.is_capture()
+                && !m.is_promotion()
+                && !m.is_check();
+            let mut evaluation = match is_late_move {
+                true => self.search_move(board, &m, consecutive_moves, new_depth - 1, alpha, beta),
+                false => self.search_move(board, &m, consecutive_moves, new_depth, alpha, beta),
+            };
+            let is_improving = match is_maximizing {
+                true => evaluation > alpha,
+                false => evaluation < beta,
+            };
+            if is_late_move && is_improving && !self.is_stopped() {
+                evaluation = self.search_move(board, &m, consecutive_moves, new_depth, alpha, beta);
+            }
+
+            if self.is_stopped() {
+                return 0.0;
+            }
+
+            let is_better = match is_maximizing {
+                true => evaluation > best.1,
+                false => evaluation < best.1,
+            };
+            if is_better {
+                best = (Some(m), evaluation);
+                let mut line = vec![m];
+                line.extend(self.get_principal_variation(self.ply + 1));
+                self.set_principal_variation(line);
+            }
+            match is_maximizing {
+                true => alpha = alpha.max(evaluation),
+                false => beta = beta.min(evaluation),
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best.1 <= original_alpha {
+            Bound::Upper
+        } else if best.1 >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let best_move = best.0.map(|m| (m.get_from(), m.get_to()));
+        let evaluation = self.adjust_to_table(best.1);
+        self.table
+            .store(hash, TableEntry::new(depth, bound, evaluation, best_move));
+
+        best.1
+    }
+
+    fn search_move(
+        &mut self,
+        board: &Board,
+        play: &HalfMove,
+        consecutive_moves: u8,
+        depth: u8,
+        alpha: f32,
+        beta: f32,
+    ) -> f32 {
+        let colour = play.get_piece().get_colour();
+        let mut final_board = board.duplicate();
+        final_board.execute_move(play);
+
+        self.nodes += 1;
+        self.ply += 1;
+        self.positions
+            .push(utils::hash_position(&final_board, colour.get_opposite()));
+        let evaluation = self.alpha_beta(
+            &final_board,
+            colour.get_opposite(),
+            Game::update_consecutive_moves(play, consecutive_moves),
+            depth,
+            alpha,
+            beta,
+        );
+        self.positions.pop();
+        self.ply -= 1;
+
+        evaluation
+    }
+
+    /// Search captures only, until the position is quiet, so that it isn't evaluated in the
+    /// middle of an exchange. Captures losing material are skipped, and each player may stand
+    /// pat with the static evaluation instead of capturing, unless in check: then all the
+    /// evasions are searched.
+    fn quiescence(
+        &mut self,
+        board: &Board,
+        colour: Colour,
+        depth: u8,
+        mut alpha: f32,
+        mut beta: f32,
+    ) -> f32 {
+        if self.is_stopped() {
+            return 0.0;
+        }
+
+        let is_king_alone = board.get_pieces(self.root_colour).len() == 1;
+        let variant = board.get_variant();
+        if let Some(outcome) = variant.get_outcome(board, colour) {
+            return self.evaluate_outcome(outcome, is_king_alone);
+        }
+        let moves = Game::select_legal_moves(board, colour);
+        if moves.is_empty() {
+            let outcome = variant.get_outcome_without_moves(board, colour);
+            return self.evaluate_outcome(outcome, is_king_alone);
+        }
+
+        let is_maximizing = colour == self.root_colour;
+        let is_in_check = variant.is_in_check(board, colour);
+        let stand_pat = variant.evaluate(board, self.root_colour);
+        if depth == 0 {
+            return stand_pat;
+        }
+        let mut best = match is_maximizing {
+            true => utils::LOSS,
+            false => utils::WIN,
+        };
+        if !is_in_check {
+            match is_maximizing {
+                true if stand_pat >= beta => return stand_pat,
+                false if stand_pat <= alpha => return stand_pat,
+                true => alpha = alpha.max(stand_pat),
+                false => beta = beta.min(stand_pat),
+            }
+            best = stand_pat;
+        }
+
+        // Most valuable exchanges first. Explosions make exchanges meaningless in Atomic
+        let is_atomic = matches!(variant, VariantType::Atomic(_));
+        let mut captures: Vec<(i32, HalfMove)> = moves
+            .into_iter()
+            .filter(|m| is_in_check || m.is_capture())
+            .map(|m| match m.is_capture() && !is_atomic {
+                true => (board.see(&m), m),
+                false => (0, m),
+            })
+            .filter(|(see, _)| is_in_check || *see >= 0)
+            .collect();
+        captures.sort_by_key(|(see, m)| (!m.is_capture(), -see));
+
+        for (_, m) in captures {
+            let mut next = board.duplicate();
+            next.execute_move(&m);
+            self.nodes += 1;
+            self.ply += 1;
+            let evaluation = self.quiescence(&next, colour.get_opposite(), depth - 1, alpha, beta);
+            self.ply -= 1;
+            if self.is_stopped() {
+                return 0.0;
+            }
+
+            match is_maximizing {
+                true => {
+                    best = best.max(evaluation);
+                    alpha = alpha.max(evaluation);
+                }
+                false => {
+                    best = best.min(evaluation);
+                    beta = beta.min(evaluation);
+                }
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best
+    }
+
+    fn search_null_move(
+        &mut self,
+        board: &Board,
+        colour: Colour,
+        consecutive_moves: u8,
+        depth: u8,
+        alpha: f32,
+        beta: f32,
+    ) -> f32 {
+        self.ply += 1;
+        self.after_null_move = true;
+        self.positions
+            .push(utils::hash_position(board, colour.get_opposite()));
+        let evaluation = self.alpha_beta(
+            board,
+            colour.get_opposite(),
+            consecutive_moves + 1,
+            depth - 1 - utils::NULL_MOVE_REDUCTION,
+            alpha,
+            beta,
+        );
+        self.positions.pop();
+        self.after_null_move = false;
+        self.ply -= 1;
+
+        evaluation
+    }
+
+    fn adjust_to_table(&self, evaluation: f32) -> f32 {
+        // Mates are stored as distance from the position, not from the root of the search
+        match utils::is_decisive(evaluation) {
+            true if evaluation > 0.0 => evaluation + self.ply as f32,
+            true => evaluation - self.ply as f32,
+            false => evaluation,
+        }
+    }
+
+    fn adjust_from_table(&self, evaluation: f32) -> f32 {
+        match utils::is_decisive(evaluation) {
+            true if evaluation > 0.0 => evaluation - self.ply as f32,
+            true => evaluation + self.ply as f32,
+            false => evaluation,
+        }
+    }
+
+    fn get_principal_variation(&self, ply: u8) -> Vec<HalfMove> {
+        self.principal_variations
+            .get(ply as usize)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set_principal_variation(&mut self, line: Vec<HalfMove>) {
+        let ply = self.ply as usize;
+        if self.principal_variations.len() <= ply {
+            self.principal_variations.resize(ply + 1, vec![]);
+        }
+        self.principal_variations[ply] = line;
+    }
+
+    fn extension(&self, play: &HalfMove) -> u8 {
+        // The opponent will have to answer the check
+        match self.options.is_check_extensions() && play.is_check() && self.ply < self.max_ply {
+            true => 1,
+            false => 0,
+        }
+    }
+
+    fn is_zugzwang_prone(board: &Board, colour: Colour) -> bool {
+        // With only king and pawns, passing is often the best move and null moves lie
+        board
+            .get_pieces(colour)
+            .values()
+            .all(|p| matches!(p.get_type(), PieceType::King(_) | PieceType::Pawn(_)))
+    }
+
+    fn order_moves(&self, board: &Board, hash: u64, moves: Vec<HalfMove>) -> Vec<HalfMove> {
+        let mut moves = Worker::order_by_exchange(board, moves);
+
+        // Best move of a previous search first
+        let best_move = self.table.probe(hash).and_then(|e| e.get_best_move());
+        if let Some((from, to)) = best_move {
+            if let Some(index) = moves
+                .iter()
+                .position(|m| m.get_from() == from && m.get_to() == to)
+            {
+                let m = moves.remove(index);
+                moves.insert(0, m);
+            }
+        }
+
+        moves
+    }
+
+    fn order_by_exchange(board: &Board, moves: Vec<HalfMove>) -> Vec<HalfMove> {
+        // Winning and even captures first, then quiet moves, then losing captures
+        let mut moves: Vec<(i32, HalfMove)> = moves
+            .into_iter()
+            .map(|m| match m.is_capture() {
+                true => (board.see(&m), m),
+                false => (0, m),
+            })
+            .collect();
+        moves.sort_by_key(|(see, m)| match (m.is_capture(), *see >= 0) {
+            (true, true) => (0, -see),
+            (false, _) => (1, 0),
+            (true, false) => (2, -see),
+        });
+
+        moves.into_iter().map(|(_, m)| m).collect()
+    }
+
+    fn compute_draw_value(is_king_alone: bool) -> f32 {
+        // If only the king is on the board, draw is as good as it gets without mating
+        match is_king_alone {
+            true => utils::WIN_BY_DRAW,
+            false => utils::DRAW,
+        }
+    }
+
+    fn evaluate_outcome(&self, outcome: Outcome, is_king_alone: bool) -> f32 {
+        match outcome.get_winner() {
+            // Lose as late as possible
+            Some(colour) if colour != self.root_colour => utils::LOSS + self.ply as f32,
+            // Win as soon as possible
+            Some(_) => utils::WIN - self.ply as f32,
+            None => Worker::compute_draw_value(is_king_alone),
+        }
+    }
+
+    fn evaluate_tablebase(&self, wdl: Wdl, colour: Colour, is_king_alone: bool) -> f32 {
+        let wdl = match colour == self.root_colour {
+            true => wdl,
+            false => wdl.get_opposite(),
+        };
+
+        // Wins that can't be forced within 50 moves are draws
+        match wdl {
+            Wdl::Win => utils::TABLEBASE_WIN - self.ply as f32,
+            Wdl::Loss => utils::TABLEBASE_LOSS + self.ply as f32,
+            _ => Worker::compute_draw_value(is_king_alone),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::uci;
+    use crate::game::game::Game;
+    use crate::game::search::SearchOptions;
+
+    #[test]
+    fn quiescence_sees_recapture() {
+        let game = Game::from_fen("6k1/8/2p5/3p4/8/8/8/3Q2K1 w - - 0 1").unwrap();
+        let mut options = SearchOptions::new();
+        options.set_threads(1);
+        let lines = game.think(&options, 1);
+        let play = lines[0].get_first_move().unwrap();
+        assert_ne!(uci::to_uci(&play), "d1d5");
+    }
+
+    #[test]
+    fn quiescence_searches_check_evasions() {
+        // The checks fork the king and the knight, which only shows once Black gets out of check
+        let game = Game::from_fen("7k/n7/8/7p/6P1/8/8/3Q2K1 w - - 0 1").unwrap();
+        let mut options = SearchOptions::new();
+        options.set_threads(1);
+        options.set_check_extensions(false);
+        let lines = game.think(&options, 0);
+        let play = lines[0].get_first_move().unwrap();
+        assert!(["d1d4", "d1a1"].contains(&uci::to_uci(&play).as_str()));
+    }
+
+    #[test]
+    fn single_thread_search_is_deterministic() {
+        let fen = "r5k1/1p3ppp/2n5/8/3N4/8/1P3PPP/R5K1 w - - 0 1";
+        let mut options = SearchOptions::new();
+        options.set_threads(1);
+        let search = || {
+            let lines = Game::from_fen(fen).unwrap().think(&options, 2);
+            let moves: Vec<String> = lines[0].get_moves().iter().map(uci::to_uci).collect();
+            (moves, lines[0].get_evaluation())
+        };
+        assert_eq!(search(), search());
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::model::board::square::Square;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TableEntry {
+    depth: u8,
+    bound: Bound,
+    evaluation: f32,
+    best_move: Option<(Square, Square)>,
+}
+
+impl TableEntry {
+    pub fn new(
+        depth: u8,
+        bound: Bound,
+        evaluation: f32,
+        best_move: Option<(Square, Square)>,
+    ) -> TableEntry {
+        TableEntry {
+            depth,
+            bound,
+            evaluation,
+            best_move,
+        }
+    }
+
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn get_bound(&self) -> Bound {
+        self.bound
+    }
+
+    pub fn get_evaluation(&self) -> f32 {
+        self.evaluation
+    }
+
+    pub fn get_best_move(&self) -> Option<(Square, Square)> {
+        self.best_move
+    }
+
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let best_move = match self.best_move {
+            Some((from, to)) => {
+                1 << 12 | TableEntry::pack_square(from) << 6 | TableEntry::pack_square(to)
+            }
+            None => 0,
+        };
+
+        (self.evaluation.to_bits() as u64)
+            | (self.depth as u64) << 32
+            | bound << 40
+            | best_move << 42
+    }
+
+    fn unpack(data: u64) -> TableEntry {
+        let bound = match (data >> 40) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let best_move = match (data >> 54) & 1 {
+            1 => Some((
+                TableEntry::unpack_square(data >> 48),
+                TableEntry::unpack_square(data >> 42),
+            )),
+            _ => None,
+        };
+
+        TableEntry {
+            depth: (data >> 32) as u8,
+            bound,
+            evaluation: f32::from_bits(data as u32),
+            best_move,
+        }
+    }
+
+    fn pack_square(square: Square) -> u64 {
+        (square.get_rank() * 8 + square.get_file()) as u64
+    }
+
+    fn unpack_square(data: u64) -> Square {
+        let index = (data & 0b111111) as u8;
+        Square::new(index / 8, index % 8)
+    }
+}
+
+/// Transposition table shared by all the search threads without any lock.
+///
+/// Every slot stores the position hash XOR-ed with the packed entry next to the packed entry
+/// itself: a slot torn by two threads writing at the same time doesn't match any hash anymore,
+/// so it is simply seen as empty.
+pub struct TranspositionTable {
+    slots: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl TranspositionTable {
+    pub fn new(size: usize) -> TranspositionTable {
+        let slots = (0..size.max(1))
+            .map(|_| (AtomicU64::new(0), AtomicU64::new(0)))
+            .collect();
+
+        TranspositionTable { slots }
+    }
+
+    fn get_slot(&self, hash: u64) -> &(AtomicU64, AtomicU64) {
+        &self.slots[(hash % self.slots.len() as u64) as usize]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TableEntry> {
+        let (key, data) = self.get_slot(hash);
+        let key = key.load(Ordering::Relaxed);
+        let data = data.load(Ordering::Relaxed);
+
+        match key ^ data == hash && data != 0 {
+            true => Some(TableEntry::unpack(data)),
+            false => None,
+        }
+    }
+
+    pub fn store(&self, hash: u64, entry: TableEntry) {
+        let (key, data) = self.get_slot(hash);
+
+        // Keep deeper results of the same position
+        if let Some(old) = self.probe(hash) {
+            if old.get_depth() > entry.get_depth() {
+                return;
+            }
+        }
+
+        let packed = entry.pack();
+        key.store(hash ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+}
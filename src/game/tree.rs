@@ -0,0 +1,355 @@
+use anyhow::Error;
+
+use crate::formats::fen;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::variant::variant::VariantType;
+
+/// A move of the game tree with its annotations. The first child continues the main line, the
+/// others are variations.
+#[derive(Debug, Clone)]
+pub struct Node {
+    play: Option<HalfMove>,
+    starting_comment: Option<String>,
+    comment: Option<String>,
+    nags: Vec<u8>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(play: Option<HalfMove>) -> Node {
+        Node {
+            play,
+            starting_comment: None,
+            comment: None,
+            nags: vec![],
+            children: vec![],
+        }
+    }
+
+    /// Move leading to this node, `None` only for the root.
+    pub fn get_move(&self) -> Option<HalfMove> {
+        self.play
+    }
+
+    /// Comment written before the move, e.g. at the beginning of a variation.
+    pub fn get_starting_comment(&self) -> Option<&str> {
+        self.starting_comment.as_deref()
+    }
+
+    /// Comment written after the move (for the root, before the first move of the game).
+    pub fn get_comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Numeric Annotation Glyphs, e.g. 1 for "!" or 14 for "+=".
+    pub fn get_nags(&self) -> &Vec<u8> {
+        &self.nags
+    }
+
+    pub fn get_children(&self) -> &Vec<Node> {
+        &self.children
+    }
+}
+
+/// Moves of a game with their variations, comments and NAGs, and a cursor on the current move.
+#[derive(Debug, Clone)]
+pub struct GameTree {
+    root: Node,
+    path: Vec<usize>,
+    start_fen: String,
+    start_colour: Colour,
+    first_turn: u16,
+    variant: VariantType,
+}
+
+impl GameTree {
+    pub fn new() -> GameTree {
+        GameTree {
+            root: Node::new(None),
+            path: vec![],
+            start_fen: String::from(fen::START_FEN),
+            start_colour: Colour::White,
+            first_turn: 1,
+            variant: VariantType::default(),
+        }
+    }
+
+    /// Tree of a game starting from the position of the given FEN record.
+    pub fn from_fen(text: &str) -> Result<GameTree, Error> {
+        GameTree::from_variant_fen(text, VariantType::default())
+    }
+
+    /// Tree of a game of the variant starting from the position of the given FEN record.
+    pub fn from_variant_fen(text: &str, variant: VariantType) -> Result<GameTree, Error> {
+        let position = fen::parse_variant_fen(text, variant)?;
+        Ok(GameTree {
+            root: Node::new(None),
+            path: vec![],
+            start_fen: text.trim().to_string(),
+            start_colour: position.get_colour(),
+            first_turn: position.get_turn(),
+            variant,
+        })
+    }
+
+    pub fn get_variant(&self) -> VariantType {
+        self.variant
+    }
+
+    pub fn get_start_fen(&self) -> &str {
+        &self.start_fen
+    }
+
+    pub fn get_start_colour(&self) -> Colour {
+        self.start_colour
+    }
+
+    /// Number of the first full move of the game.
+    pub fn get_first_turn(&self) -> u16 {
+        self.first_turn
+    }
+
+    pub fn get_root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn get_current(&self) -> &Node {
+        self.path
+            .iter()
+            .fold(&self.root, |node, index| &node.children[*index])
+    }
+
+    fn get_current_mut(&mut self) -> &mut Node {
+        self.path
+            .iter()
+            .fold(&mut self.root, |node, index| &mut node.children[*index])
+    }
+
+    /// Indexes of the children chosen from the root to reach the current move.
+    pub fn get_path(&self) -> &Vec<usize> {
+        &self.path
+    }
+
+    /// Move the cursor to the node at the given path, if it exists.
+    pub fn go_to(&mut self, path: &[usize]) -> bool {
+        let mut node = &self.root;
+        for index in path {
+            match node.children.get(*index) {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+        self.path = path.to_vec();
+        true
+    }
+
+    pub fn get_ply(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Player to move at the current node.
+    pub fn get_colour(&self) -> Colour {
+        match self.path.len() % 2 {
+            0 => self.start_colour,
+            _ => self.start_colour.get_opposite(),
+        }
+    }
+
+    /// Moves played from the start of the game to the current node.
+    pub fn get_moves(&self) -> Vec<HalfMove> {
+        let mut node = &self.root;
+        let mut moves = vec![];
+        for index in &self.path {
+            node = &node.children[*index];
+            moves.extend(node.play);
+        }
+        moves
+    }
+
+    /// Moves of the main line, from the start to the end of the game.
+    pub fn get_main_line(&self) -> Vec<HalfMove> {
+        let mut node = &self.root;
+        let mut moves = vec![];
+        while let Some(child) = node.children.first() {
+            moves.extend(child.play);
+            node = child;
+        }
+        moves
+    }
+
+    /// Position at the current node.
+    pub fn get_board(&self) -> Board {
+        let mut board = fen::parse_variant_fen(&self.start_fen, self.variant)
+            .expect("The starting position has been checked")
+            .into_board();
+        for play in self.get_moves() {
+            board.execute_move(&play);
+        }
+        board
+    }
+
+    /// Play the given move from the current node: if it has already been played there, just go
+    /// to it, otherwise add it as the last variation.
+    pub fn add_move(&mut self, play: HalfMove) {
+        let node = self.get_current_mut();
+        let index = match node.children.iter().position(|c| {
+            c.play.is_some_and(|m| {
+                m.get_from() == play.get_from()
+                    && m.get_to() == play.get_to()
+                    && m.is_promotion() == play.is_promotion()
+            })
+        }) {
+            Some(index) => index,
+            None => {
+                node.children.push(Node::new(Some(play)));
+                node.children.len() - 1
+            }
+        };
+        self.path.push(index);
+    }
+
+    /// Go to the next move, of the main line (0) or of a variation.
+    pub fn forward(&mut self, variation: usize) -> Option<HalfMove> {
+        let child = self.get_current().children.get(variation)?.play;
+        self.path.push(variation);
+        child
+    }
+
+    /// Go to the previous move, returning the move taken back.
+    pub fn back(&mut self) -> Option<HalfMove> {
+        let play = self.get_current().play;
+        self.path.pop().and(play)
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.path.clear();
+    }
+
+    /// Follow the main line from the current node until its end.
+    pub fn go_to_end(&mut self) {
+        while self.forward(0).is_some() {}
+    }
+
+    /// Make the variation of the current move the main line at its branching point.
+    pub fn promote_variation(&mut self) -> bool {
+        let Some(index) = self.path.pop() else {
+            return false;
+        };
+        let parent = self.get_current_mut();
+        let node = parent.children.remove(index);
+        parent.children.insert(0, node);
+        self.path.push(0);
+        index != 0
+    }
+
+    /// Delete the current move with all the moves following it, going back to the previous one.
+    pub fn delete_variation(&mut self) -> bool {
+        let Some(index) = self.path.pop() else {
+            return false;
+        };
+        self.get_current_mut().children.remove(index);
+        true
+    }
+
+    pub fn set_starting_comment(&mut self, comment: Option<String>) {
+        self.get_current_mut().starting_comment = comment;
+    }
+
+    pub fn set_comment(&mut self, comment: Option<String>) {
+        self.get_current_mut().comment = comment;
+    }
+
+    pub fn add_nag(&mut self, nag: u8) {
+        let node = self.get_current_mut();
+        if !node.nags.contains(&nag) {
+            node.nags.push(nag);
+        }
+    }
+
+    pub fn clear_nags(&mut self) {
+        self.get_current_mut().nags.clear();
+    }
+}
+
+impl Default for GameTree {
+    fn default() -> GameTree {
+        GameTree::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::uci;
+
+    /// Tree with the main line 1. e4 e5 and the variations 1. d4 and 1... c5.
+    fn build_tree() -> GameTree {
+        let mut tree = GameTree::new();
+        for line in [&["e2e4", "e7e5"][..], &["d2d4"], &["e2e4", "c7c5"]] {
+            tree.go_to_start();
+            for text in line {
+                let play = uci::parse_uci(&tree.get_board(), tree.get_colour(), text).unwrap();
+                tree.add_move(play);
+            }
+        }
+        tree.go_to_start();
+        tree
+    }
+
+    fn get_main_line(tree: &GameTree) -> Vec<String> {
+        tree.get_main_line().iter().map(uci::to_uci).collect()
+    }
+
+    #[test]
+    fn forward_and_back() {
+        let mut tree = build_tree();
+        assert!(tree.back().is_none());
+        assert_eq!(
+            tree.forward(1).map(|m| uci::to_uci(&m)),
+            Some(String::from("d2d4"))
+        );
+        assert!(tree.forward(0).is_none());
+        assert_eq!(
+            tree.back().map(|m| uci::to_uci(&m)),
+            Some(String::from("d2d4"))
+        );
+        assert_eq!(tree.get_ply(), 0);
+
+        tree.go_to_end();
+        assert_eq!(tree.get_path(), &vec![0, 0]);
+        assert_eq!(tree.get_colour(), Colour::White);
+        assert_eq!(
+            tree.back().map(|m| uci::to_uci(&m)),
+            Some(String::from("e7e5"))
+        );
+        assert_eq!(
+            tree.forward(1).map(|m| uci::to_uci(&m)),
+            Some(String::from("c7c5"))
+        );
+    }
+
+    #[test]
+    fn promote_variation() {
+        let mut tree = build_tree();
+        tree.go_to(&[0, 1]);
+        assert!(tree.promote_variation());
+        assert_eq!(tree.get_path(), &vec![0, 0]);
+        assert_eq!(get_main_line(&tree), vec!["e2e4", "c7c5"]);
+        assert!(!tree.promote_variation());
+
+        tree.go_to_start();
+        assert!(!tree.promote_variation());
+    }
+
+    #[test]
+    fn delete_variation() {
+        let mut tree = build_tree();
+        tree.forward(0);
+        assert!(tree.delete_variation());
+        assert_eq!(tree.get_ply(), 0);
+        assert_eq!(get_main_line(&tree), vec!["d2d4"]);
+        assert!(!tree.delete_variation());
+    }
+}
@@ -2,23 +2,28 @@ use std::fmt::{Display, Formatter};
 
 use crate::game::play::FullMove;
 
+#[derive(Clone)]
 pub struct Turn {
-    index: u8,
+    index: u16,
     moves: FullMove,
 }
 
 impl Turn {
-    pub fn new(index: u8, moves: FullMove) -> Turn {
+    pub fn new(index: u16, moves: FullMove) -> Turn {
         Turn { index, moves }
     }
 
-    pub fn get_index(&self) -> u8 {
+    pub fn get_index(&self) -> u16 {
         self.index
     }
 
     pub fn get_moves(&self) -> &FullMove {
         &self.moves
     }
+
+    pub fn get_moves_mut(&mut self) -> &mut FullMove {
+        &mut self.moves
+    }
 }
 
 impl Display for Turn {
@@ -0,0 +1,41 @@
+//! Crust, a chess engine: positions and move generation, games with clocks, search, opening
+//! books, endgame tablebases, variants, and the PGN, FEN, SAN and UCI formats.
+//!
+//! The most used types are re-exported here:
+//!
+//! ```no_run
+//! use crust::{Colour, Game, SearchOptions};
+//!
+//! let game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+//! let moves = Game::select_legal_moves(game.get_board(), Colour::White);
+//! let lines = game.think(&SearchOptions::new(), 3);
+//! println!("{} moves, best {:?}", moves.len(), lines.first().and_then(|l| l.get_first_move()));
+//! ```
+
+pub mod analysis;
+pub mod book;
+pub mod competition;
+pub mod formats;
+pub mod game;
+pub mod model;
+pub mod player;
+pub mod protocol;
+pub mod tablebase;
+mod utils;
+pub mod variant;
+
+pub use crate::formats::fen::{Position, START_FEN};
+pub use crate::game::clock::{Clock, ManualTime, TimeControl};
+pub use crate::game::game::Game;
+pub use crate::game::play::{HalfMove, Line};
+pub use crate::game::search::{Report, SearchOptions};
+pub use crate::game::tree::GameTree;
+pub use crate::model::board::board::Board;
+pub use crate::model::board::square::Square;
+pub use crate::model::piece::color::Colour;
+pub use crate::model::piece::piece::{Piece, PieceType};
+pub use crate::player::player::{Action, Player};
+pub use crate::utils::{
+    format_evaluation, get_mate_distance, is_decisive, is_mate, DRAW, LOSS, WIN,
+};
+pub use crate::variant::variant::{Outcome, Variant, VariantType};
@@ -1,13 +1,15 @@
-use crate::game::game::Game;
+use std::env;
+use std::process::ExitCode;
 
-mod game;
-mod model;
-mod utils;
+mod cli;
 
-fn main() {
-    let mut game = Game::new();
-    println!("{}", game);
-
-    game.play(2);
-    println!("{}", game);
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match cli::run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -11,12 +11,15 @@ use crate::model::piece::king::King;
 use crate::model::piece::pawn::Pawn;
 use crate::model::piece::piece::{Piece, PieceType};
 use crate::model::piece::queen::Queen;
+use crate::variant::variant::{Variant, VariantType};
 
 #[derive(Debug)]
 pub struct Board {
     squares: [[Square; 8]; 8],
     white_pieces: HashMap<Square, Box<dyn Piece>>,
     black_pieces: HashMap<Square, Box<dyn Piece>>,
+    variant: VariantType,
+    checks: [u8; 2],
 }
 
 impl Board {
@@ -29,6 +32,8 @@ impl Board {
             squares,
             white_pieces,
             black_pieces,
+            variant: VariantType::default(),
+            checks: [0, 0],
         }
     }
 
@@ -43,7 +48,41 @@ impl Board {
             black_pieces.insert(s.clone(), bp.duplicate());
         }
 
-        Board::new(squares, white_pieces, black_pieces)
+        let mut board = Board::new(squares, white_pieces, black_pieces);
+        board.variant = self.variant;
+        board.checks = self.checks;
+        board
+    }
+
+    /// Rules the position is played with.
+    pub fn get_variant(&self) -> VariantType {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant: VariantType) {
+        self.variant = variant;
+    }
+
+    /// Checks given by the player so far, counted for Three-check.
+    pub fn get_checks(&self, colour: Colour) -> u8 {
+        match colour {
+            Colour::White => self.checks[0],
+            Colour::Black => self.checks[1],
+        }
+    }
+
+    pub fn set_checks(&mut self, colour: Colour, checks: u8) {
+        match colour {
+            Colour::White => self.checks[0] = checks,
+            Colour::Black => self.checks[1] = checks,
+        }
+    }
+
+    pub fn add_check(&mut self, colour: Colour) {
+        match colour {
+            Colour::White => self.checks[0] += 1,
+            Colour::Black => self.checks[1] += 1,
+        }
     }
 
     pub fn is_square_free(&self, square: &Square) -> bool {
@@ -93,6 +132,12 @@ impl Board {
         squares
     }
 
+    /// Take the piece of either player off the square, e.g. in an explosion.
+    pub fn remove_piece(&mut self, square: &Square) {
+        self.white_pieces.remove(square);
+        self.black_pieces.remove(square);
+    }
+
     fn get_white_pieces(&self) -> &HashMap<Square, Box<dyn Piece>> {
         &self.white_pieces
     }
@@ -120,23 +165,41 @@ impl Board {
         // Piece has been moved
         piece.set_first_move(false);
 
-        // Execute move for player
-        match colour {
-            Colour::White => self.execute_white_move(
-                piece,
-                from_square,
-                to_square,
-                play.is_capture(),
-                play.is_promotion(),
-            ),
-            Colour::Black => self.execute_black_move(
-                piece,
-                from_square,
-                to_square,
-                play.is_capture(),
-                play.is_promotion(),
-            ),
+        // Castling moves the king and the rook to their standard squares
+        if play.is_castling() {
+            let (king_to, rook_to) = play.get_castling_squares();
+            let pieces = match colour {
+                Colour::White => &mut self.white_pieces,
+                Colour::Black => &mut self.black_pieces,
+            };
+            let mut rook = pieces.remove(&to_square).unwrap();
+            rook.set_first_move(false);
+            pieces.remove(&from_square);
+            pieces.insert(king_to, piece);
+            pieces.insert(rook_to, rook);
+        } else {
+            // Execute move for player
+            match colour {
+                Colour::White => self.execute_white_move(
+                    piece,
+                    from_square,
+                    to_square,
+                    play.is_capture(),
+                    play.is_promotion(),
+                ),
+                Colour::Black => self.execute_black_move(
+                    piece,
+                    from_square,
+                    to_square,
+                    play.is_capture(),
+                    play.is_promotion(),
+                ),
+            }
         }
+
+        // Rules of the variant, e.g. explosions
+        let variant = self.variant;
+        variant.complete_move(self, play);
     }
 
     fn execute_white_move(
@@ -205,26 +268,136 @@ impl Board {
         board.is_under_check(play.get_piece().get_colour().get_opposite())
     }
 
+    /// Whether the player is in check by the rules of the variant.
     pub fn is_under_check(&self, colour: Colour) -> bool {
-        let mut king_square = None;
-        for (square, piece) in self.get_pieces(colour) {
-            if piece.get_type() == PieceType::King(King::new(colour)) {
-                king_square = Some(square);
-                break;
-            }
-        }
+        self.variant.is_in_check(self, colour)
+    }
 
-        let king_square = king_square.expect(&format!("Cannot find {} king", colour));
+    /// Whether the king of the player is attacked, false without king.
+    pub fn is_king_attacked(&self, colour: Colour) -> bool {
+        match self.get_king_square(colour) {
+            Some(king_square) => self.is_under_control(&king_square, colour.get_opposite()),
+            None => false,
+        }
+    }
 
-        self.is_under_control(king_square, colour.get_opposite())
+    pub fn get_king_square(&self, colour: Colour) -> Option<Square> {
+        self.get_pieces(colour)
+            .iter()
+            .find(|(_, p)| p.get_type() == PieceType::King(King::new(colour)))
+            .map(|(s, _)| *s)
     }
 
-    fn is_under_control(&self, square: &Square, colour: Colour) -> bool {
+    /// Whether a piece of the player attacks the square.
+    pub fn is_under_control(&self, square: &Square, colour: Colour) -> bool {
         self.get_pieces(colour)
             .iter()
             .any(|(s, p)| p.is_controlling(self, s, square))
     }
 
+    /// Static exchange evaluation of a capture: the material balance for the capturing side once
+    /// both sides have recaptured on the target square, always with their least valuable attacker.
+    /// Quiet moves are evaluated as 0.
+    pub fn see(&self, play: &HalfMove) -> i32 {
+        if !play.is_capture() {
+            return 0;
+        }
+
+        let target = play.get_to();
+        let mut colour = play.get_piece().get_colour();
+        let mut board = self.duplicate();
+
+        // Material won at each step of the exchange, assuming it stops there
+        let mut gains = vec![];
+        let captured = board
+            .get_piece(target, Some(colour.get_opposite()))
+            .map_or(0, |p| p.get_value() as i32);
+        gains.push(captured);
+        board.execute_move(play);
+
+        // Sliders hidden behind the last capturer are revealed once it has left its square,
+        // so attackers are looked up again after each capture
+        loop {
+            let on_target = board
+                .get_piece(target, Some(colour))
+                .map_or(0, |p| p.get_value() as i32);
+            colour = colour.get_opposite();
+            match board.least_valuable_attacker(&target, colour) {
+                Some(m) => {
+                    gains.push(on_target - gains.last().unwrap());
+                    board.execute_move(&m);
+                }
+                None => break,
+            }
+        }
+
+        // Each side can stand pat instead of recapturing
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+
+        gains[0]
+    }
+
+    fn least_valuable_attacker(&self, target: &Square, colour: Colour) -> Option<HalfMove> {
+        let attacker = self
+            .get_pieces(colour)
+            .iter()
+            .flat_map(|(s, p)| p.available_moves(self, s))
+            .filter(|m| m.get_to() == *target)
+            .min_by_key(|m| m.get_piece().get_value())?;
+
+        // The king can only join the exchange as the last capturer
+        if attacker.get_piece() == PieceType::King(King::new(colour)) {
+            let mut board = self.duplicate();
+            board.execute_move(&attacker);
+            if board.is_under_control(target, colour.get_opposite()) {
+                return None;
+            }
+        }
+
+        Some(attacker)
+    }
+
+    /// Whether the player could deliver checkmate with some sequence of legal moves. A lone
+    /// king, a king and a minor piece against a lone king, or bishops all on squares of the same
+    /// colour, can't.
+    pub fn can_checkmate(&self, colour: Colour) -> bool {
+        let pieces: Vec<char> = self
+            .get_pieces(colour)
+            .values()
+            .map(|p| p.get_symbol())
+            .filter(|s| *s != 'K')
+            .collect();
+        let is_enemy_king_alone = self.get_pieces(colour.get_opposite()).len() == 1;
+
+        // The mated king would stand on a square of the other colour, which no piece can attack
+        let others: Vec<(&Square, char)> = self
+            .get_pieces(colour)
+            .iter()
+            .chain(self.get_pieces(colour.get_opposite()))
+            .map(|(s, p)| (s, p.get_symbol()))
+            .filter(|(_, symbol)| *symbol != 'K')
+            .collect();
+        let is_bishop = |(_, symbol): &(&Square, char)| *symbol == 'B';
+        let is_light = |square: &Square| (square.get_rank() + square.get_file()) % 2 == 1;
+        if others.iter().all(is_bishop)
+            && others
+                .windows(2)
+                .all(|w| is_light(w[0].0) == is_light(w[1].0))
+        {
+            return false;
+        }
+
+        match pieces.as_slice() {
+            [] => false,
+            ['B'] | ['N'] => !is_enemy_king_alone,
+            _ => true,
+        }
+    }
+
     pub fn evaluate_material(&self, colour: Colour) -> f32 {
         let allie_material = self
             .get_pieces(colour)
@@ -300,6 +473,11 @@ impl Hash for Board {
                 }
             }
         }
+
+        // Positions only differ by the checks given in Three-check
+        if self.checks != [0, 0] {
+            self.checks.hash(state);
+        }
     }
 }
 
@@ -337,3 +515,101 @@ impl Display for Board {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::{fen, uci};
+    use crate::model::piece::color::Colour;
+
+    fn see(fen: &str, play: &str) -> i32 {
+        let position = fen::parse_fen(fen).unwrap();
+        let colour = position.get_colour();
+        let board = position.into_board();
+        let play = uci::parse_uci(&board, colour, play).unwrap();
+        board.see(&play)
+    }
+
+    #[test]
+    fn see_undefended_piece() {
+        assert_eq!(see("6k1/8/8/3n4/8/8/8/3R2K1 w - - 0 1", "d1d5"), 3);
+    }
+
+    #[test]
+    fn see_defended_pawn_taken_by_queen() {
+        assert_eq!(see("6k1/8/2p5/3p4/8/8/8/3Q2K1 w - - 0 1", "d1d5"), -8);
+    }
+
+    #[test]
+    fn see_rook_battery() {
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "d2d5"), 1);
+    }
+
+    #[test]
+    fn see_queen_behind_bishop() {
+        assert_eq!(see("6k1/3n4/8/4p3/8/2B5/1Q6/6K1 w - - 0 1", "c3e5"), 1);
+    }
+
+    #[test]
+    fn see_quiet_move() {
+        assert_eq!(see("6k1/8/8/3n4/8/8/8/3R2K1 w - - 0 1", "d1d2"), 0);
+    }
+
+    fn can_checkmate(fen: &str) -> [bool; 2] {
+        let board = fen::parse_fen(fen).unwrap().into_board();
+        [Colour::White, Colour::Black].map(|c| board.can_checkmate(c))
+    }
+
+    #[test]
+    fn insufficient_material() {
+        // Kings alone, a minor piece against a lone king, same-coloured bishops
+        assert_eq!(
+            can_checkmate("8/8/4k3/8/8/3K4/8/8 w - - 0 1"),
+            [false, false]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/8/8/3K4/3B4/8 w - - 0 1"),
+            [false, false]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/8/8/3K4/3N4/8 w - - 0 1"),
+            [false, false]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/2b5/8/3K4/3B4/8 w - - 0 1"),
+            [false, false]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4kb2/8/8/3K4/3B4/2B5 w - - 0 1"),
+            [false, false]
+        );
+    }
+
+    #[test]
+    fn sufficient_material() {
+        // Opposite-coloured bishops, or minor pieces on both sides, can still help mate
+        assert_eq!(
+            can_checkmate("8/8/4k3/3b4/8/3K4/3B4/8 w - - 0 1"),
+            [true, true]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/3n4/8/3K4/3B4/8 w - - 0 1"),
+            [true, true]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/8/8/3K4/3BB3/8 w - - 0 1"),
+            [true, false]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/8/8/3K4/3NN3/8 w - - 0 1"),
+            [true, false]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/8/8/3K4/3P4/8 w - - 0 1"),
+            [true, false]
+        );
+        assert_eq!(
+            can_checkmate("8/8/4k3/8/8/3K4/3R4/8 w - - 0 1"),
+            [true, false]
+        );
+    }
+}
@@ -1,2 +1,2 @@
-pub(crate) mod board;
-pub(crate) mod piece;
+pub mod board;
+pub mod piece;
@@ -32,6 +32,10 @@ impl Piece for Bishop {
         self.colour
     }
 
+    fn is_first_move(&self) -> bool {
+        false
+    }
+
     fn set_first_move(&mut self, _value: bool) -> () {}
 
     fn available_moves(&self, board: &Board, position: &Square) -> Vec<HalfMove> {
@@ -19,6 +19,43 @@ impl King {
     }
 }
 
+impl King {
+    /// Castling with any unmoved rook of the back rank, as in Chess960: the squares crossed by
+    /// the king and the rook must be empty, and the king can't castle out of or through check.
+    fn castling_moves(&self, board: &Board, position: &Square) -> Vec<HalfMove> {
+        let rank = match self.colour {
+            Colour::White => 0,
+            Colour::Black => 7,
+        };
+        if !self.first_move || position.get_rank() != rank {
+            return vec![];
+        }
+
+        let opponent = self.colour.get_opposite();
+        let span = |from: u8, to: u8| from.min(to)..=from.max(to);
+        let mut moves = vec![];
+        for (square, piece) in board.get_pieces(self.colour) {
+            if piece.get_symbol() != 'R' || !piece.is_first_move() || square.get_rank() != rank {
+                continue;
+            }
+            let play = HalfMove::new(self.get_type(), *position, *square).with_castling(true);
+            let (king_to, rook_to) = play.get_castling_squares();
+            let is_free = span(position.get_file(), king_to.get_file())
+                .chain(span(square.get_file(), rook_to.get_file()))
+                .map(|file| Square::new(rank, file))
+                .all(|s| s == *position || s == *square || board.is_square_free(&s));
+            let is_safe = || {
+                span(position.get_file(), king_to.get_file())
+                    .all(|file| !board.is_under_control(&Square::new(rank, file), opponent))
+            };
+            if is_free && is_safe() {
+                moves.push(play);
+            }
+        }
+        moves
+    }
+}
+
 impl Piece for King {
     fn get_type(&self) -> PieceType {
         PieceType::King(self.clone())
@@ -36,6 +73,10 @@ impl Piece for King {
         self.colour
     }
 
+    fn is_first_move(&self) -> bool {
+        self.first_move
+    }
+
     fn set_first_move(&mut self, value: bool) -> () {
         self.first_move = value;
     }
@@ -70,11 +111,19 @@ impl Piece for King {
 
         // Special moves
         // 1. Castling
-        // TODO
+        moves.extend(self.castling_moves(board, position));
 
         moves
     }
 
+    fn is_controlling(&self, board: &Board, position: &Square, target: &Square) -> bool {
+        // Without generating castling, which looks for the squares controlled by the opponent
+        let is_adjacent = position != target
+            && position.get_rank().abs_diff(target.get_rank()) <= 1
+            && position.get_file().abs_diff(target.get_file()) <= 1;
+        is_adjacent && board.get_piece(*target, Some(self.colour)).is_none()
+    }
+
     fn duplicate(&self) -> Box<dyn Piece> {
         let mut new = Box::new(King::new(self.colour));
         new.set_first_move(self.first_move);
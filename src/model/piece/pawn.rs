@@ -50,17 +50,10 @@ impl Pawn {
                     let promotion = board.is_promotion_square(square, self.get_colour());
 
                     // Add move
-                    moves.push(HalfMove::new(
-                        self.get_type(),
-                        original_position.clone(),
-                        square.clone(),
-                        false,
-                        promotion,
-                        false,
-                        false,
-                        false,
-                        0.0,
-                    ));
+                    moves.push(
+                        HalfMove::new(self.get_type(), original_position.clone(), square.clone())
+                            .with_promotion(promotion),
+                    );
 
                     // Go to next move
                     return self.next_move(
@@ -114,6 +107,10 @@ impl Piece for Pawn {
         self.colour
     }
 
+    fn is_first_move(&self) -> bool {
+        self.first_move
+    }
+
     fn set_first_move(&mut self, value: bool) -> () {
         self.first_move = value;
     }
@@ -33,7 +33,7 @@ impl PartialEq for PieceType {
 }
 
 #[enum_dispatch]
-pub trait Piece: Debug {
+pub trait Piece: Debug + Send + Sync {
     fn get_type(&self) -> PieceType;
 
     fn get_symbol(&self) -> char;
@@ -42,6 +42,8 @@ pub trait Piece: Debug {
 
     fn get_colour(&self) -> Colour;
 
+    fn is_first_move(&self) -> bool;
+
     fn set_first_move(&mut self, value: bool) -> ();
 
     fn available_moves(&self, board: &Board, position: &Square) -> Vec<HalfMove>;
@@ -68,17 +70,11 @@ pub trait Piece: Debug {
                         && board.is_promotion_square(square, self.get_colour());
 
                     // Add move
-                    Some(HalfMove::new(
-                        self.get_type(),
-                        position.clone(),
-                        square.clone(),
-                        true,
-                        promotion,
-                        false,
-                        false,
-                        false,
-                        0.0,
-                    ))
+                    Some(
+                        HalfMove::new(self.get_type(), position.clone(), square.clone())
+                            .with_capture(true)
+                            .with_promotion(promotion),
+                    )
                 } else {
                     None
                 }
@@ -125,17 +121,10 @@ pub trait Piece: Debug {
                 let can_capture = self.can_capture(board, target);
                 if board.is_square_free(&target) || can_capture {
                     // Add move
-                    moves.push(HalfMove::new(
-                        self.get_type(),
-                        original_position.clone(),
-                        target.clone(),
-                        can_capture,
-                        false,
-                        false,
-                        false,
-                        false,
-                        0.0,
-                    ));
+                    moves.push(
+                        HalfMove::new(self.get_type(), original_position.clone(), target.clone())
+                            .with_capture(can_capture),
+                    );
 
                     // Go to next move
                     return self.next_move_recursive(
@@ -172,17 +161,10 @@ pub trait Piece: Debug {
             Some(square) => {
                 if board.is_square_free(square) || self.can_capture(board, square) {
                     // Add move
-                    moves.push(HalfMove::new(
-                        self.get_type(),
-                        position.clone(),
-                        square.clone(),
-                        self.can_capture(board, square),
-                        false,
-                        false,
-                        false,
-                        false,
-                        0.0,
-                    ));
+                    moves.push(
+                        HalfMove::new(self.get_type(), position.clone(), square.clone())
+                            .with_capture(self.can_capture(board, square)),
+                    );
                 }
                 moves
             }
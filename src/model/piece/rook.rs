@@ -36,6 +36,10 @@ impl Piece for Rook {
         self.colour
     }
 
+    fn is_first_move(&self) -> bool {
+        self.first_move
+    }
+
     fn set_first_move(&mut self, value: bool) -> () {
         self.first_move = value;
     }
@@ -0,0 +1,86 @@
+use anyhow::Error;
+
+use crate::game::clock::Bonus;
+use crate::game::game::Game;
+use crate::game::play::Line;
+use crate::game::search::SearchOptions;
+use crate::player::player::{Action, Player};
+
+/// Moves expected to be left in the game when the time control doesn't say.
+const MOVES_TO_GO: u32 = 30;
+
+/// The built-in engine, with its own search settings.
+pub struct EnginePlayer {
+    max_depth: u8,
+    move_time: Option<f32>,
+    options: SearchOptions,
+    lines: Vec<Line>,
+}
+
+impl EnginePlayer {
+    pub fn new(max_depth: u8, options: SearchOptions) -> EnginePlayer {
+        EnginePlayer {
+            max_depth,
+            move_time: None,
+            options,
+            lines: vec![],
+        }
+    }
+
+    /// Seconds to think about each move at most, besides what the clock allows.
+    pub fn set_move_time(&mut self, move_time: Option<f32>) {
+        self.move_time = move_time;
+    }
+
+    /// Share of the remaining time to spend on the next move, if the game has a time control.
+    fn get_clock_time(game: &Game) -> Option<f32> {
+        let clock = game.get_clock(game.get_side_to_move());
+        let remaining = clock.get_remaining_seconds()?;
+        let bonus = match clock.get_period().map(|p| p.get_bonus()) {
+            Some(Bonus::Increment(seconds))
+            | Some(Bonus::Bronstein(seconds))
+            | Some(Bonus::Delay(seconds)) => seconds,
+            _ => 0.0,
+        };
+
+        Some(get_time_budget(remaining, clock.get_moves_to_go(), bonus))
+    }
+}
+
+/// Seconds to spend on the next move, out of the remaining ones, when the given number of moves
+/// must be played before the next time control, each move giving back some bonus seconds.
+pub fn get_time_budget(remaining: f32, moves_to_go: Option<u32>, bonus: f32) -> f32 {
+    let moves_to_go = moves_to_go.unwrap_or(MOVES_TO_GO).clamp(1, MOVES_TO_GO);
+
+    // Never risk more than half of what is left
+    (remaining / moves_to_go as f32 + 0.8 * bonus).min(remaining / 2.0)
+}
+
+impl Player for EnginePlayer {
+    fn get_name(&self) -> String {
+        match self.move_time {
+            Some(seconds) => format!("Crust (depth {}, {}s)", self.max_depth, seconds),
+            None => format!("Crust (depth {})", self.max_depth),
+        }
+    }
+
+    fn choose(&mut self, game: &Game) -> Result<Action, Error> {
+        let move_time = match (self.move_time, EnginePlayer::get_clock_time(game)) {
+            (Some(own), Some(clock)) => Some(own.min(clock)),
+            (own, clock) => own.or(clock),
+        };
+        let mut options = self.options;
+        options.set_move_time(move_time);
+
+        self.lines = game.think(&options, self.max_depth);
+        self.lines
+            .first()
+            .and_then(|line| line.get_first_move())
+            .map(Action::Move)
+            .ok_or(Error::msg("No legal moves"))
+    }
+
+    fn get_lines(&self) -> &[Line] {
+        &self.lines
+    }
+}
@@ -0,0 +1,57 @@
+use std::io;
+use std::io::Write;
+
+use anyhow::Error;
+
+use crate::formats::{san, uci};
+use crate::game::game::Game;
+use crate::player::player::{Action, Player};
+
+/// Reads moves from the standard input, in SAN ("Nf3") or in coordinates ("g1f3"), or "undo"
+/// and "redo" to take back and replay moves.
+pub struct HumanPlayer;
+
+impl HumanPlayer {
+    pub fn new() -> HumanPlayer {
+        HumanPlayer
+    }
+}
+
+impl Default for HumanPlayer {
+    fn default() -> HumanPlayer {
+        HumanPlayer::new()
+    }
+}
+
+impl Player for HumanPlayer {
+    fn get_name(&self) -> String {
+        String::from("Human")
+    }
+
+    fn choose(&mut self, game: &Game) -> Result<Action, Error> {
+        let board = game.get_board();
+        let colour = game.get_side_to_move();
+        loop {
+            print!("{:?} to move: ", colour);
+            io::stdout().flush()?;
+
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line)? == 0 {
+                return Err(Error::msg("End of input"));
+            }
+            let text = line.trim();
+            match text {
+                "resign" => return Err(Error::msg("Resigned")),
+                "undo" => return Ok(Action::Undo),
+                "redo" => return Ok(Action::Redo),
+                _ => {}
+            }
+
+            let play = san::parse_san(board, colour, text).or(uci::parse_uci(board, colour, text));
+            match play {
+                Some(play) => return Ok(Action::Move(play)),
+                None => println!("Illegal or unknown move: {}", text),
+            }
+        }
+    }
+}
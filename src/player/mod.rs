@@ -0,0 +1,6 @@
+pub mod engine;
+pub mod human;
+#[allow(clippy::module_inception)]
+pub mod player;
+pub mod random;
+pub mod uci;
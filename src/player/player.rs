@@ -0,0 +1,62 @@
+use anyhow::Error;
+
+use crate::game::game::Game;
+use crate::game::play::{HalfMove, Line};
+use crate::game::search::SearchOptions;
+use crate::player::engine::EnginePlayer;
+use crate::player::human::HumanPlayer;
+use crate::player::random::RandomPlayer;
+use crate::player::uci::UciPlayer;
+
+/// What a player does on their turn.
+#[derive(Debug, Copy, Clone)]
+pub enum Action {
+    Move(HalfMove),
+    /// Take back the last move of the player, and the reply to it.
+    Undo,
+    /// Play again the moves taken back.
+    Redo,
+}
+
+/// Someone, or something, choosing the moves of one side of a game.
+pub trait Player {
+    fn get_name(&self) -> String;
+
+    /// Move to play in the current position of the game, for the player to move, or a request
+    /// to take moves back. An error means the player can't go on (e.g. a human leaving or an
+    /// engine crashing) and loses the game.
+    fn choose(&mut self, game: &Game) -> Result<Action, Error>;
+
+    /// Lines considered for the last move chosen, the best one first.
+    fn get_lines(&self) -> &[Line] {
+        &[]
+    }
+}
+
+/// Build a player from its description: "engine[:depth[:seconds per move]]", "human", "random"
+/// or "uci:<engine command>".
+pub fn parse_player(description: &str, options: &SearchOptions) -> Result<Box<dyn Player>, Error> {
+    let (kind, parameters) = description.split_once(':').unwrap_or((description, ""));
+    let invalid = || Error::msg(format!("Invalid player: {}", description));
+
+    match kind {
+        "engine" => {
+            let mut parameters = parameters.split(':').filter(|p| !p.is_empty());
+            let max_depth = match parameters.next() {
+                Some(depth) => depth.parse().map_err(|_| invalid())?,
+                None => 2,
+            };
+            let move_time = match parameters.next() {
+                Some(seconds) => Some(seconds.parse().map_err(|_| invalid())?),
+                None => None,
+            };
+            let mut engine = EnginePlayer::new(max_depth, *options);
+            engine.set_move_time(move_time);
+            Ok(Box::new(engine))
+        }
+        "human" => Ok(Box::new(HumanPlayer::new())),
+        "random" => Ok(Box::new(RandomPlayer::new())),
+        "uci" if !parameters.is_empty() => Ok(Box::new(UciPlayer::start(parameters)?)),
+        _ => Err(invalid()),
+    }
+}
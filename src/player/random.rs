@@ -0,0 +1,36 @@
+use anyhow::Error;
+
+use crate::game::game::Game;
+use crate::player::player::{Action, Player};
+use crate::utils;
+
+/// Plays any legal move, with the same probability.
+pub struct RandomPlayer;
+
+impl RandomPlayer {
+    pub fn new() -> RandomPlayer {
+        RandomPlayer
+    }
+}
+
+impl Default for RandomPlayer {
+    fn default() -> RandomPlayer {
+        RandomPlayer::new()
+    }
+}
+
+impl Player for RandomPlayer {
+    fn get_name(&self) -> String {
+        String::from("Random")
+    }
+
+    fn choose(&mut self, game: &Game) -> Result<Action, Error> {
+        let moves = Game::select_legal_moves(game.get_board(), game.get_side_to_move());
+        match moves.is_empty() {
+            true => Err(Error::msg("No legal moves")),
+            false => Ok(Action::Move(
+                moves[(utils::random_u64() % moves.len() as u64) as usize],
+            )),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+
+use crate::formats::uci;
+use crate::game::clock::Bonus;
+use crate::game::game::Game;
+use crate::game::play::Line;
+use crate::model::piece::color::Colour;
+use crate::player::player::{Action, Player};
+use crate::utils;
+use crate::variant::variant::{Variant, VariantType};
+
+/// Seconds per move when neither the game nor the player set a limit.
+const DEFAULT_MOVE_TIME: f32 = 1.0;
+
+/// Seconds given to the engine on top of its time to send its move, before it loses on time.
+const TIME_MARGIN: f32 = 1.0;
+
+/// Seconds the engine has to answer outside of a search, e.g. to "isready".
+const ANSWER_TIME: f32 = 10.0;
+
+/// An external engine, run as a child process speaking the Universal Chess Interface.
+pub struct UciPlayer {
+    name: String,
+    child: Child,
+    input: ChildStdin,
+    output: Receiver<String>,
+    max_depth: Option<u8>,
+    move_time: Option<f32>,
+    lines: Vec<Line>,
+    chess960: bool,
+    variant: VariantType,
+}
+
+impl UciPlayer {
+    /// Start the engine with the given command line and wait until it's ready.
+    pub fn start(command: &str) -> Result<UciPlayer, Error> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or(Error::msg("Missing engine command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::msg(format!("Cannot start {}: {}", program, e)))?;
+        let input = child.stdin.take().ok_or(Error::msg("No engine input"))?;
+        let stdout = child.stdout.take().ok_or(Error::msg("No engine output"))?;
+
+        // Read on another thread, so that an engine not answering can be given up on
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut player = UciPlayer {
+            name: program.to_string(),
+            child,
+            input,
+            output,
+            max_depth: None,
+            move_time: None,
+            lines: vec![],
+            chess960: false,
+            variant: VariantType::default(),
+        };
+        player.send("uci")?;
+        loop {
+            let line = player.receive(Some(ANSWER_TIME))?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                player.name = name.trim().to_string();
+            }
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+        player.send("ucinewgame")?;
+        player.wait_ready()?;
+
+        Ok(player)
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: Option<u8>) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn set_move_time(&mut self, move_time: Option<f32>) {
+        self.move_time = move_time;
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.wait_ready()
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), Error> {
+        writeln!(self.input, "{}", command)
+            .and_then(|_| self.input.flush())
+            .map_err(|e| Error::msg(format!("{} doesn't listen: {}", self.name, e)))
+    }
+
+    /// Next line sent by the engine, waiting for it at most the given seconds if any.
+    fn receive(&mut self, seconds: Option<f32>) -> Result<String, Error> {
+        let line = match seconds {
+            Some(seconds) => self
+                .output
+                .recv_timeout(Duration::from_secs_f32(seconds.max(0.0))),
+            None => self
+                .output
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match line {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => {
+                Err(Error::msg(format!("{} ran out of time", self.name)))
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(Error::msg(format!("{} has quit", self.name)))
+            }
+        }
+    }
+
+    fn wait_ready(&mut self) -> Result<(), Error> {
+        self.send("isready")?;
+        while self.receive(Some(ANSWER_TIME))?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Seconds the engine may think before it loses on time: its clock if any, otherwise the
+    /// move time given, with a margin for the engine to answer. No limit for a search to a
+    /// given depth only.
+    fn get_deadline(&self, game: &Game) -> Option<f32> {
+        let clock = game
+            .get_clock(game.get_side_to_move())
+            .get_remaining_seconds();
+        let move_time = match (clock, self.move_time, self.max_depth) {
+            (Some(seconds), own, _) => Some(own.map_or(seconds, |own| own.min(seconds))),
+            (None, Some(own), _) => Some(own),
+            (None, None, None) => Some(DEFAULT_MOVE_TIME),
+            (None, None, Some(_)) => None,
+        };
+        move_time.map(|seconds| seconds + TIME_MARGIN)
+    }
+
+    /// Limits of the search: the clocks of the game if any, otherwise the player own settings.
+    fn get_limits(&self, game: &Game) -> String {
+        let mut limits = vec![];
+        let (white, black) = (game.get_clock(Colour::White), game.get_clock(Colour::Black));
+        if let (Some(white_time), Some(black_time)) =
+            (white.get_remaining_seconds(), black.get_remaining_seconds())
+        {
+            let increment = |bonus| match bonus {
+                Some(Bonus::Increment(seconds)) | Some(Bonus::Bronstein(seconds)) => seconds,
+                _ => 0.0,
+            };
+            limits.push(format!("wtime {}", (white_time * 1000.0) as i64));
+            limits.push(format!("btime {}", (black_time * 1000.0) as i64));
+            limits.push(format!(
+                "winc {}",
+                (increment(white.get_period().map(|p| p.get_bonus())) * 1000.0) as i64
+            ));
+            limits.push(format!(
+                "binc {}",
+                (increment(black.get_period().map(|p| p.get_bonus())) * 1000.0) as i64
+            ));
+            if let Some(moves) = game.get_clock(game.get_side_to_move()).get_moves_to_go() {
+                limits.push(format!("movestogo {}", moves));
+            }
+        }
+        if let Some(depth) = self.max_depth {
+            limits.push(format!("depth {}", depth));
+        }
+        let move_time = match (limits.is_empty(), self.move_time) {
+            (_, Some(seconds)) => Some(seconds),
+            (true, None) => Some(DEFAULT_MOVE_TIME),
+            (false, None) => None,
+        };
+        if let Some(seconds) = move_time {
+            limits.push(format!("movetime {}", (seconds * 1000.0) as i64));
+        }
+
+        limits.join(" ")
+    }
+
+    /// Line described by an "info" message, if it has a score and a principal variation.
+    fn parse_info(game: &Game, info: &str) -> Option<Line> {
+        let words: Vec<&str> = info.split_whitespace().collect();
+
+        // Only the best line matters when the engine shows several ones
+        if let Some(index) = words.iter().position(|w| *w == "multipv") {
+            if words.get(index + 1) != Some(&"1") {
+                return None;
+            }
+        }
+
+        let score = words.iter().position(|w| *w == "score")?;
+        let value: i32 = words.get(score + 2)?.parse().ok()?;
+        let evaluation = match *words.get(score + 1)? {
+            "cp" => value as f32 / 100.0,
+            "mate" if value > 0 => utils::WIN - (2 * value - 1) as f32,
+            "mate" => utils::LOSS + (-2 * value) as f32,
+            _ => return None,
+        };
+
+        let pv = words.iter().position(|w| *w == "pv")?;
+        let mut board = game.get_board().duplicate();
+        let mut colour = game.get_side_to_move();
+        let mut moves = vec![];
+        for word in &words[pv + 1..] {
+            let Some(play) = uci::parse_uci(&board, colour, word) else {
+                break;
+            };
+            board.execute_move(&play);
+            colour = colour.get_opposite();
+            moves.push(play);
+        }
+        if let Some(first) = moves.first_mut() {
+            first.set_evaluation(evaluation);
+        }
+
+        (!moves.is_empty()).then(|| Line::new(moves, evaluation))
+    }
+}
+
+impl Player for UciPlayer {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose(&mut self, game: &Game) -> Result<Action, Error> {
+        // Castling is written king takes rook once the engine plays Chess960
+        if game.is_chess960() != self.chess960 {
+            self.chess960 = game.is_chess960();
+            self.set_option("UCI_Chess960", &self.chess960.to_string())?;
+        }
+        if game.get_variant() != self.variant {
+            self.variant = game.get_variant();
+            let name = match self.variant.is_standard() {
+                true => "chess",
+                false => self.variant.get_key(),
+            };
+            self.set_option("UCI_Variant", name)?;
+        }
+        let format = match self.chess960 {
+            true => uci::to_uci_chess960,
+            false => uci::to_uci,
+        };
+        let moves: Vec<String> = game.get_moves().iter().map(format).collect();
+        let start = match game.get_start_fen() == self.variant.get_start_fen() {
+            true => String::from("startpos"),
+            false => format!("fen {}", game.get_start_fen()),
+        };
+        let position = match moves.is_empty() {
+            true => format!("position {}", start),
+            false => format!("position {} moves {}", start, moves.join(" ")),
+        };
+        self.send(&position)?;
+        let limits = self.get_limits(game);
+        self.send(&format!("go {}", limits))?;
+
+        let deadline = self
+            .get_deadline(game)
+            .map(|seconds| Instant::now() + Duration::from_secs_f32(seconds.max(0.0)));
+        let mut line = None;
+        loop {
+            let seconds =
+                deadline.map(|d| d.saturating_duration_since(Instant::now()).as_secs_f32());
+            let message = self.receive(seconds)?;
+            let message = message.trim();
+            if message.starts_with("info") {
+                line = UciPlayer::parse_info(game, message).or(line);
+            }
+            if let Some(best) = message.strip_prefix("bestmove") {
+                let best = best.split_whitespace().next().unwrap_or("");
+                let mut play = uci::parse_uci(game.get_board(), game.get_side_to_move(), best)
+                    .ok_or(Error::msg(format!(
+                        "{} plays illegal or unsupported move {}",
+                        self.name, best
+                    )))?;
+                self.lines = line.into_iter().collect();
+                if let Some(evaluation) = self.lines.first().map(|l| l.get_evaluation()) {
+                    play.set_evaluation(evaluation);
+                }
+                return Ok(Action::Move(play));
+            }
+        }
+    }
+
+    fn get_lines(&self) -> &[Line] {
+        &self.lines
+    }
+}
+
+impl Drop for UciPlayer {
+    fn drop(&mut self) {
+        // Give the engine a moment to quit by itself
+        let _ = self.send("quit");
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use crate::game::game::Game;
+    use crate::player::player::Player;
+    use crate::player::uci::UciPlayer;
+
+    #[test]
+    fn engine_not_moving_runs_out_of_time() {
+        // Answers the handshake, but never a "go"
+        let script = env::temp_dir().join(format!("crust-silent-{}.sh", std::process::id()));
+        fs::write(
+            &script,
+            "while read line; do case \"$line\" in \
+             uci) echo 'id name Silent'; echo uciok;; \
+             isready) echo readyok;; \
+             quit) exit 0;; \
+             esac; done\n",
+        )
+        .unwrap();
+
+        let player = UciPlayer::start(&format!("sh {}", script.display()));
+        let result = player.map(|mut player| {
+            player.set_move_time(Some(0.1));
+            (player.get_name(), player.choose(&Game::new()))
+        });
+        fs::remove_file(&script).unwrap();
+
+        let (name, result) = result.unwrap();
+        assert_eq!(name, "Silent");
+        assert_eq!(result.unwrap_err().to_string(), "Silent ran out of time");
+    }
+}
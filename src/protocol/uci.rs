@@ -0,0 +1,385 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+
+use anyhow::Error;
+
+use crate::formats::uci;
+use crate::game::game::Game;
+use crate::game::play::{HalfMove, Line};
+use crate::game::search::SearchOptions;
+use crate::model::piece::color::Colour;
+use crate::player::engine;
+use crate::variant::variant::{Variant, VariantType};
+
+/// Depth searched when the GUI gives a time budget, deep enough for the time to run out first.
+const MAX_DEPTH: u8 = 64;
+
+/// The engine speaking the Universal Chess Interface to a GUI.
+pub struct UciEngine {
+    game: Arc<Game>,
+    options: SearchOptions,
+    max_depth: u8,
+    chess960: bool,
+    variant: VariantType,
+    search: Option<Search>,
+}
+
+/// A search running on its own thread, while the commands of the GUI are still read.
+struct Search {
+    interrupt: Arc<AtomicBool>,
+    handle: JoinHandle<Result<Vec<Line>, Error>>,
+    /// Infinite and ponder searches only send their best move once stopped.
+    is_infinite: bool,
+}
+
+impl UciEngine {
+    /// Engine searching at most the given depth when the GUI doesn't ask for another one.
+    pub fn new(options: SearchOptions, max_depth: u8) -> UciEngine {
+        UciEngine {
+            game: Arc::new(Game::new()),
+            options,
+            max_depth,
+            chess960: false,
+            variant: VariantType::default(),
+            search: None,
+        }
+    }
+
+    /// Answer the commands of the GUI until it quits or closes the input. Searches run in the
+    /// background, so that the GUI can stop them.
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        output: impl Write + Send + 'static,
+    ) -> Result<(), Error> {
+        let output = Arc::new(Mutex::new(output));
+        for line in input.lines() {
+            let line = line?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.first().copied() {
+                Some("quit") => return self.end_search(true, &output),
+                Some("go") => {
+                    self.go(&words[1..], &output)?;
+                    continue;
+                }
+                // Once the move pondered on is played, its search ends with the time used
+                Some("stop") | Some("ponderhit") => self.end_search(true, &output)?,
+                // Searches over have already sent their best move
+                _ if self
+                    .search
+                    .as_ref()
+                    .is_some_and(|s| !s.is_infinite && s.handle.is_finished()) =>
+                {
+                    self.end_search(false, &output)?
+                }
+                _ => {}
+            }
+
+            let mut output = output.lock().unwrap();
+            match words.first().copied() {
+                Some("uci") => {
+                    writeln!(output, "id name Crust {}", env!("CARGO_PKG_VERSION"))?;
+                    writeln!(output, "id author The Crust developers")?;
+                    writeln!(
+                        output,
+                        "option name Threads type spin default {} min 1 max 256",
+                        self.options.get_threads()
+                    )?;
+                    writeln!(
+                        output,
+                        "option name MultiPV type spin default {} min 1 max 64",
+                        self.options.get_multi_pv()
+                    )?;
+                    writeln!(output, "option name UCI_Chess960 type check default false")?;
+                    let variants: Vec<String> = VariantType::get_all()
+                        .iter()
+                        .skip(1)
+                        .map(|v| format!(" var {}", v.get_key()))
+                        .collect();
+                    writeln!(
+                        output,
+                        "option name UCI_Variant type combo default chess var chess{}",
+                        variants.join("")
+                    )?;
+                    writeln!(output, "uciok")?;
+                }
+                Some("isready") => writeln!(output, "readyok")?,
+                Some("ucinewgame") => self.game = Arc::new(Game::from_variant(self.variant)),
+                Some("setoption") => self.set_option(&words[1..]),
+                Some("position") => {
+                    if let Err(error) = self.set_position(&words[1..]) {
+                        writeln!(output, "info string {}", error)?;
+                    }
+                }
+                _ => {}
+            }
+            output.flush()?;
+        }
+
+        // Searches still running are waited for, unless they would never end
+        let is_infinite = self.search.as_ref().is_some_and(|s| s.is_infinite);
+        self.end_search(is_infinite, &output)
+    }
+
+    /// Wait for the search running in the background, stopping it first if asked to, and send
+    /// its best move if it hasn't been sent yet.
+    fn end_search(
+        &mut self,
+        stop: bool,
+        output: &Arc<Mutex<impl Write + Send + 'static>>,
+    ) -> Result<(), Error> {
+        let Some(search) = self.search.take() else {
+            return Ok(());
+        };
+        if stop {
+            search.interrupt.store(true, Ordering::Relaxed);
+        }
+        let lines = search
+            .handle
+            .join()
+            .map_err(|_| Error::msg("Search failed"))??;
+        if search.is_infinite {
+            write_best_move(&mut *output.lock().unwrap(), &lines, self.chess960)?;
+        }
+        Ok(())
+    }
+
+    /// "setoption name <name> value <value>"
+    fn set_option(&mut self, words: &[&str]) {
+        let name = words.iter().position(|w| *w == "name");
+        let value = words.iter().position(|w| *w == "value");
+        let (Some(name), Some(value)) = (name, value) else {
+            return;
+        };
+        let text = words[value + 1..].join(" ");
+        let number = text.parse::<usize>();
+        match (words[name + 1..value].join(" ").as_str(), number) {
+            ("Threads", Ok(threads)) => self.options.set_threads(threads),
+            ("MultiPV", Ok(lines)) => self.options.set_multi_pv(lines),
+            ("UCI_Chess960", _) => self.chess960 = text == "true",
+            ("UCI_Variant", _) => {
+                if let Ok(variant) = VariantType::parse(&text) {
+                    self.variant = variant;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// "position [startpos | fen <fen>] [moves <move>...]"
+    fn set_position(&mut self, words: &[&str]) -> Result<(), Error> {
+        let moves = words.iter().position(|w| *w == "moves");
+        let setup = &words[..moves.unwrap_or(words.len())];
+        let mut game = match setup.first().copied() {
+            Some("startpos") => Game::from_variant(self.variant),
+            Some("fen") => Game::from_variant_fen(&setup[1..].join(" "), self.variant)?,
+            _ => return Err(Error::msg("Missing startpos or fen")),
+        };
+        for text in moves.map_or(&[][..], |index| &words[index + 1..]) {
+            let play = uci::parse_uci(game.get_board(), game.get_side_to_move(), text)
+                .ok_or(Error::msg(format!("Illegal move {}", text)))?;
+            game.make_move(play)?;
+        }
+
+        self.game = Arc::new(game);
+        Ok(())
+    }
+
+    /// "go [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [movestogo <n>] [depth <n>]
+    /// [movetime <ms>] [infinite] [ponder]", starting a search in the background. Its progress
+    /// is sent after each iteration, and its best move once it is over, or once stopped for
+    /// infinite and ponder searches.
+    fn go(
+        &mut self,
+        words: &[&str],
+        output: &Arc<Mutex<impl Write + Send + 'static>>,
+    ) -> Result<(), Error> {
+        // Only one search at a time
+        self.end_search(true, output)?;
+
+        let value = |name: &str| {
+            words
+                .iter()
+                .position(|w| *w == name)
+                .and_then(|index| words.get(index + 1))
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+        let colour = self.game.get_side_to_move();
+        let (time, increment) = match colour {
+            Colour::White => (value("wtime"), value("winc")),
+            Colour::Black => (value("btime"), value("binc")),
+        };
+        let clock_time = time.map(|time| {
+            engine::get_time_budget(
+                time as f32 / 1000.0,
+                value("movestogo").map(|m| m as u32),
+                increment.unwrap_or(0) as f32 / 1000.0,
+            )
+        });
+        let is_infinite = words.contains(&"infinite") || words.contains(&"ponder");
+        let move_time = match is_infinite {
+            true => None,
+            false => value("movetime")
+                .map(|time| time as f32 / 1000.0)
+                .or(clock_time),
+        };
+        let max_depth = match (value("depth"), move_time, is_infinite) {
+            // The search counts its depth from 0
+            (Some(depth), _, _) => depth.clamp(1, MAX_DEPTH as u64) as u8 - 1,
+            (None, Some(_), _) | (None, None, true) => MAX_DEPTH,
+            (None, None, false) => self.max_depth,
+        };
+
+        let mut options = self.options;
+        options.set_move_time(move_time);
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let game = Arc::clone(&self.game);
+        let chess960 = self.chess960;
+        let handle = {
+            let interrupt = Arc::clone(&interrupt);
+            let output = Arc::clone(output);
+            thread::spawn(move || {
+                // The progress of the search is sent as it goes
+                let mut result = Ok(());
+                let lines = game.analyse(&options, max_depth, Some(&interrupt), &mut |report| {
+                    if result.is_ok() {
+                        let mut output = output.lock().unwrap();
+                        let (lines, depth) = (report.get_lines(), report.get_depth());
+                        result = write_lines(&mut *output, lines, depth, chess960);
+                    }
+                });
+                result?;
+
+                // A search stopped too early may not have evaluated any move
+                let board = game.get_board();
+                let lines = match Game::select_legal_moves(board, colour).first() {
+                    Some(first) if lines.is_empty() => vec![Line::new(vec![*first], 0.0)],
+                    _ => lines,
+                };
+                if !is_infinite {
+                    write_best_move(&mut *output.lock().unwrap(), &lines, chess960)?;
+                }
+                Ok(lines)
+            })
+        };
+        self.search = Some(Search {
+            interrupt,
+            handle,
+            is_infinite,
+        });
+        Ok(())
+    }
+}
+
+/// "bestmove" line for the best line found, "0000" when there are no legal moves.
+fn write_best_move(output: &mut impl Write, lines: &[Line], chess960: bool) -> Result<(), Error> {
+    match lines.first().and_then(|line| line.get_first_move()) {
+        Some(play) => writeln!(output, "bestmove {}", format_move(&play, chess960))?,
+        None => writeln!(output, "bestmove 0000")?,
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// "info" lines for the lines found by a search, the best one first, castling written as king
+/// takes rook in Chess960.
+pub fn write_lines(
+    output: &mut impl Write,
+    lines: &[Line],
+    depth: u8,
+    chess960: bool,
+) -> Result<(), Error> {
+    for (index, line) in lines.iter().enumerate() {
+        let moves: Vec<String> = line
+            .get_moves()
+            .iter()
+            .map(|play| format_move(play, chess960))
+            .collect();
+        writeln!(
+            output,
+            "info depth {} multipv {} score {} pv {}",
+            depth + 1,
+            index + 1,
+            uci::format_score(line.get_evaluation()),
+            moves.join(" ")
+        )?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn format_move(play: &HalfMove, chess960: bool) -> String {
+    match chess960 {
+        true => uci::to_uci_chess960(play),
+        false => uci::to_uci(play),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use std::sync::{Arc, Mutex};
+
+    use crate::game::search::SearchOptions;
+    use crate::protocol::uci::UciEngine;
+
+    /// Output kept to be read once the engine is done.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(commands: &str) -> Vec<String> {
+        let output = SharedOutput::default();
+        let mut options = SearchOptions::new();
+        options.set_threads(1);
+        UciEngine::new(options, 2)
+            .run(Cursor::new(commands.to_string()), output.clone())
+            .unwrap();
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn search_to_depth() {
+        let lines = run("position startpos moves e2e4\ngo depth 1\n");
+        let infos: Vec<&String> = lines.iter().filter(|l| l.starts_with("info")).collect();
+        assert!(!infos.is_empty());
+        assert!(infos.iter().all(|l| l.starts_with("info depth 1 ")));
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+        assert_eq!(
+            lines.iter().filter(|l| l.starts_with("bestmove")).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn infinite_search_waits_for_stop() {
+        let lines = run("position startpos\ngo infinite\nisready\nstop\nisready\n");
+        let ready = lines.iter().position(|l| l == "readyok").unwrap();
+        let best = lines
+            .iter()
+            .position(|l| l.starts_with("bestmove"))
+            .unwrap();
+        assert!(ready < best);
+        assert_ne!(lines[best], "bestmove 0000");
+        assert_eq!(lines.last().unwrap(), "readyok");
+    }
+
+    #[test]
+    fn no_legal_move() {
+        let lines = run("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo depth 2\n");
+        assert_eq!(lines, ["bestmove 0000"]);
+    }
+}
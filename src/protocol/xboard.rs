@@ -0,0 +1,261 @@
+use std::io::{BufRead, Write};
+use std::time::Instant;
+
+use anyhow::Error;
+
+use crate::formats::{san, uci};
+use crate::game::game::Game;
+use crate::game::search::SearchOptions;
+use crate::model::piece::color::Colour;
+use crate::player::engine;
+use crate::utils;
+use crate::variant::variant::VariantType;
+
+/// Depth searched when the GUI gives a time budget, deep enough for the time to run out first.
+const MAX_DEPTH: u8 = 64;
+
+/// The engine speaking the Chess Engine Communication Protocol (version 2) of XBoard/WinBoard.
+pub struct XboardEngine {
+    game: Game,
+    options: SearchOptions,
+    max_depth: u8,
+    default_depth: u8,
+    engine_colour: Option<Colour>,
+    post: bool,
+    moves_per_session: Option<u32>,
+    increment: f32,
+    move_time: Option<f32>,
+    remaining: Option<f32>,
+    chess960: bool,
+    variant: VariantType,
+}
+
+impl XboardEngine {
+    /// Engine searching at most the given depth when the GUI doesn't ask for another one.
+    pub fn new(options: SearchOptions, max_depth: u8) -> XboardEngine {
+        XboardEngine {
+            game: Game::new(),
+            options,
+            max_depth,
+            default_depth: max_depth,
+            engine_colour: Some(Colour::Black),
+            post: false,
+            moves_per_session: None,
+            increment: 0.0,
+            move_time: None,
+            remaining: None,
+            chess960: false,
+            variant: VariantType::default(),
+        }
+    }
+
+    /// Answer the commands of the GUI until it quits or closes the input.
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> Result<(), Error> {
+        for line in input.lines() {
+            let line = line?;
+            let (command, argument) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            match command {
+                "protover" => writeln!(
+                    output,
+                    "feature myname=\"Crust {}\" setboard=1 usermove=1 ping=1 playother=1 \
+                     colors=0 sigint=0 sigterm=0 analyze=0 variants=\"normal,fischerandom,kingofthehill,3check,giveaway,atomic,horde\" \
+                     done=1",
+                    env!("CARGO_PKG_VERSION")
+                )?,
+                "new" => {
+                    self.game = Game::new();
+                    self.variant = VariantType::default();
+                    self.engine_colour = Some(Colour::Black);
+                    self.max_depth = self.default_depth;
+                    self.move_time = None;
+                    self.chess960 = false;
+                }
+                "variant" => {
+                    self.chess960 = argument == "fischerandom";
+                    match VariantType::parse(argument) {
+                        Ok(variant) => {
+                            self.variant = variant;
+                            self.game = Game::from_variant(variant);
+                        }
+                        Err(error) => writeln!(output, "tellusererror {}", error)?,
+                    }
+                }
+                "setboard" => match Game::from_variant_fen(argument, self.variant) {
+                    Ok(game) => self.game = game,
+                    Err(error) => writeln!(output, "tellusererror {}", error)?,
+                },
+                "force" | "result" => self.engine_colour = None,
+                "go" => {
+                    self.engine_colour = Some(self.game.get_side_to_move());
+                    self.think(output)?;
+                }
+                "playother" => {
+                    self.engine_colour = Some(self.game.get_side_to_move().get_opposite())
+                }
+                "usermove" => self.play_user_move(argument, output)?,
+                // Take back one move, or a whole turn so that the user can play again
+                "undo" => {
+                    self.game.undo();
+                }
+                "remove" => {
+                    self.game.undo();
+                    self.game.undo();
+                }
+                "level" => self.set_level(argument),
+                "st" => self.move_time = argument.parse().ok(),
+                "sd" => self.max_depth = argument.parse().unwrap_or(self.default_depth),
+                "time" => self.remaining = argument.parse::<f32>().ok().map(|cs| cs / 100.0),
+                "post" => self.post = true,
+                "nopost" => self.post = false,
+                "ping" => writeln!(output, "pong {}", argument)?,
+                "quit" => break,
+                "xboard" | "accepted" | "rejected" | "otim" | "random" | "hard" | "easy"
+                | "computer" | "name" | "rating" | "draw" | "?" | "" => {}
+                // Without usermove=1, moves may be sent as they are
+                _ if uci::parse_uci(
+                    self.game.get_board(),
+                    self.game.get_side_to_move(),
+                    command,
+                )
+                .is_some() =>
+                {
+                    self.play_user_move(command, output)?
+                }
+                _ => writeln!(output, "Error (unknown command): {}", command)?,
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// "level <moves per session> <minutes[:seconds]> <increment seconds>"
+    fn set_level(&mut self, argument: &str) {
+        let fields: Vec<&str> = argument.split_whitespace().collect();
+        if let [moves, _, increment] = fields[..] {
+            self.moves_per_session = moves.parse().ok().filter(|m| *m > 0);
+            self.increment = increment.parse().unwrap_or(0.0);
+        }
+    }
+
+    fn play_user_move(&mut self, text: &str, output: &mut impl Write) -> Result<(), Error> {
+        let colour = self.game.get_side_to_move();
+        // Castling is sent as O-O and O-O-O in Chess960
+        let board = self.game.get_board();
+        let play = uci::parse_uci(board, colour, text)
+            .or(san::parse_san(board, colour, text).filter(|play| play.is_castling()));
+        let Some(play) = play else {
+            writeln!(output, "Illegal move: {}", text)?;
+            return Ok(());
+        };
+        self.game.make_move(play)?;
+        if self.write_result(output)? {
+            return Ok(());
+        }
+        if self.engine_colour == Some(colour.get_opposite()) {
+            self.think(output)?;
+        }
+        Ok(())
+    }
+
+    /// Search and play the engine move.
+    fn think(&mut self, output: &mut impl Write) -> Result<(), Error> {
+        if self.write_result(output)? {
+            return Ok(());
+        }
+
+        let moves_to_go = self.moves_per_session.map(|session| {
+            let played = (self.game.get_positions().len() as u32 - 1) / 2;
+            session - played % session
+        });
+        let clock_time = self
+            .remaining
+            .map(|remaining| engine::get_time_budget(remaining, moves_to_go, self.increment));
+        let move_time = self.move_time.or(clock_time);
+        let max_depth = match (self.max_depth == self.default_depth, move_time) {
+            (true, Some(_)) => MAX_DEPTH,
+            _ => self.max_depth,
+        };
+        let mut options = self.options;
+        options.set_move_time(move_time);
+
+        let start = Instant::now();
+        let lines = self.game.think(&options, max_depth);
+        let Some(line) = lines.first() else {
+            return Ok(());
+        };
+        if self.post {
+            let mut board = self.game.get_board().duplicate();
+            let mut moves = vec![];
+            for play in line.get_moves() {
+                moves.push(san::to_san(&board, play));
+                board.execute_move(play);
+            }
+            // Mates in N moves are scored 100000 + N
+            let evaluation = line.get_evaluation();
+            let score = match utils::is_mate(evaluation) {
+                true => {
+                    let distance = utils::get_mate_distance(evaluation);
+                    distance.signum() * (100_000 + distance.abs())
+                }
+                false => (100.0 * evaluation).round() as i32,
+            };
+            writeln!(
+                output,
+                "{} {} {} 0 {}",
+                max_depth,
+                score,
+                start.elapsed().as_millis() / 10,
+                moves.join(" ")
+            )?;
+        }
+        let Some(play) = line.get_first_move() else {
+            return Ok(());
+        };
+        self.game.make_move(play)?;
+        let text = match (
+            self.chess960 && play.is_castling(),
+            play.is_kingside_castling(),
+        ) {
+            (true, true) => String::from("O-O"),
+            (true, false) => String::from("O-O-O"),
+            (false, _) => uci::to_uci(&play),
+        };
+        writeln!(output, "move {}", text)?;
+        self.write_result(output)?;
+        Ok(())
+    }
+
+    /// Tell the GUI the game is over, if it is.
+    fn write_result(&mut self, output: &mut impl Write) -> Result<bool, Error> {
+        let Some(reason) = self.game.get_end_reason() else {
+            return Ok(false);
+        };
+        let result = match self.game.get_outcome().and_then(|o| o.get_winner()) {
+            Some(Colour::White) => "1-0",
+            Some(Colour::Black) => "0-1",
+            None => "1/2-1/2",
+        };
+        writeln!(output, "{} {{{}}}", result, reason)?;
+        self.engine_colour = None;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_mate_score_and_san_line() {
+        let mut engine = XboardEngine::new(SearchOptions::new(), 3);
+        let input = "post\nsetboard 7k/8/5K2/8/8/8/8/6R1 w - - 0 1\ngo\nquit\n";
+        let mut output = vec![];
+        engine.run(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let post = output.lines().next().unwrap();
+        let fields: Vec<&str> = post.split_whitespace().collect();
+        assert_eq!(fields[1], "100002", "{}", post);
+        assert_eq!(fields[4..].join(" "), "Kf7 Kh7 Rh1#");
+        assert!(output.contains("move f6f7"));
+    }
+}
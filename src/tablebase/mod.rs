@@ -0,0 +1,2 @@
+pub mod retrograde;
+pub mod syzygy;
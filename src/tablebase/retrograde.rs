@@ -0,0 +1,835 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Error;
+
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::board::square::Square;
+use crate::model::piece::bishop::Bishop;
+use crate::model::piece::color::Colour;
+use crate::model::piece::king::King;
+use crate::model::piece::knight::Knight;
+use crate::model::piece::pawn::Pawn;
+use crate::model::piece::piece::Piece;
+use crate::model::piece::queen::Queen;
+use crate::model::piece::rook::Rook;
+use crate::utils;
+
+// Distance to mate tables computed by retrograde analysis: starting from the mates, positions are
+// solved backwards one ply at a time. Every table covers one material (e.g. "KQvK", the side with
+// more material being White) and both sides to move.
+
+const MAGIC: &[u8; 4] = b"CRTB";
+const VERSION: u8 = 1;
+const EXTENSION: &str = "crtb";
+const PIECE_ORDER: [char; 6] = ['K', 'Q', 'R', 'B', 'N', 'P'];
+
+// Squares of the a1-d1-d4 triangle: without pawns, the white king can always be moved there
+const TRIANGLE: [usize; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+/// Result of a position for the side to move, with the number of plies before the mate.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    Win(u8),
+    Draw,
+    Loss(u8),
+}
+
+impl Outcome {
+    /// Values are stored as plies to mate plus 1: odd distances are wins for the side to move,
+    /// even ones are losses. 0 stands for draws (and illegal positions).
+    fn from_value(value: u8) -> Outcome {
+        match value {
+            0 => Outcome::Draw,
+            v if (v - 1) % 2 == 1 => Outcome::Win(v - 1),
+            v => Outcome::Loss(v - 1),
+        }
+    }
+
+    /// Outcome for the player who reached the position, one ply earlier.
+    fn get_previous(&self) -> Outcome {
+        match *self {
+            Outcome::Win(plies) => Outcome::Loss(plies + 1),
+            Outcome::Draw => Outcome::Draw,
+            Outcome::Loss(plies) => Outcome::Win(plies + 1),
+        }
+    }
+
+    fn get_evaluation(&self) -> f32 {
+        match *self {
+            Outcome::Win(plies) => utils::WIN - plies as f32,
+            Outcome::Draw => utils::DRAW,
+            Outcome::Loss(plies) => utils::LOSS + plies as f32,
+        }
+    }
+}
+
+/// Position of a table: squares (rank * 8 + file) in the order of the table pieces.
+#[derive(Debug, Clone)]
+struct Position {
+    squares: Vec<usize>,
+    white_to_move: bool,
+}
+
+pub struct EndgameTable {
+    name: String,
+    pieces: Vec<(Colour, char)>,
+    has_pawns: bool,
+    values: Vec<u8>,
+}
+
+impl EndgameTable {
+    fn new(name: &str) -> Result<EndgameTable, Error> {
+        let (white, black) = parse_name(name)?;
+        let mut pieces: Vec<(Colour, char)> = white.chars().map(|c| (Colour::White, c)).collect();
+        pieces.extend(black.chars().map(|c| (Colour::Black, c)));
+        let has_pawns = name.contains('P');
+        let slots = match has_pawns {
+            true => 32,
+            false => TRIANGLE.len(),
+        };
+        let size = 2 * slots * 64usize.pow(pieces.len() as u32 - 1);
+
+        Ok(EndgameTable {
+            name: name.to_string(),
+            pieces,
+            has_pawns,
+            values: vec![0; size],
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_outcome(&self, position: &Position) -> Outcome {
+        Outcome::from_value(self.values[self.encode(position)])
+    }
+
+    /// Index of the position, the same for all its symmetric positions.
+    fn encode(&self, position: &Position) -> usize {
+        let transforms = match self.has_pawns {
+            true => 2,
+            false => 8,
+        };
+
+        let mut best = usize::MAX;
+        for transform in 0..transforms {
+            let mut squares: Vec<usize> = position
+                .squares
+                .iter()
+                .map(|s| transform_square(*s, transform))
+                .collect();
+            let slot = match self.get_king_slot(squares[0]) {
+                Some(slot) => slot,
+                None => continue,
+            };
+
+            // Identical pieces are sorted by square
+            let mut start = 1;
+            while start < squares.len() {
+                let mut end = start + 1;
+                while end < squares.len() && self.pieces[end] == self.pieces[start] {
+                    end += 1;
+                }
+                squares[start..end].sort();
+                start = end;
+            }
+
+            let side = match position.white_to_move {
+                true => 0,
+                false => 1,
+            };
+            let slots = match self.has_pawns {
+                true => 32,
+                false => TRIANGLE.len(),
+            };
+            let index = squares[1..]
+                .iter()
+                .fold(side * slots + slot, |index, square| index * 64 + square);
+            best = best.min(index);
+        }
+
+        best
+    }
+
+    fn decode(&self, mut index: usize) -> Position {
+        let mut squares = vec![0; self.pieces.len()];
+        for square in squares.iter_mut().skip(1).rev() {
+            *square = index % 64;
+            index /= 64;
+        }
+        let slots = match self.has_pawns {
+            true => 32,
+            false => TRIANGLE.len(),
+        };
+        squares[0] = match self.has_pawns {
+            true => (index % slots / 4) * 8 + index % slots % 4,
+            false => TRIANGLE[index % slots],
+        };
+
+        Position {
+            squares,
+            white_to_move: index / slots == 0,
+        }
+    }
+
+    fn get_king_slot(&self, square: usize) -> Option<usize> {
+        match self.has_pawns {
+            true if square % 8 < 4 => Some(square / 8 * 4 + square % 8),
+            true => None,
+            false => TRIANGLE.iter().position(|s| *s == square),
+        }
+    }
+
+    /// Board of a position, if it can be reached in a game.
+    fn build_board(&self, position: &Position) -> Option<Board> {
+        let mut white_pieces = HashMap::new();
+        let mut black_pieces = HashMap::new();
+        for ((colour, symbol), square) in self.pieces.iter().zip(&position.squares) {
+            let rank = (square / 8) as u8;
+            let mut piece: Box<dyn Piece> = match symbol {
+                'K' => Box::new(King::new(*colour)),
+                'Q' => Box::new(Queen::new(*colour)),
+                'R' => Box::new(Rook::new(*colour)),
+                'B' => Box::new(Bishop::new(*colour)),
+                'N' => Box::new(Knight::new(*colour)),
+                _ => {
+                    // Pawns can't stand on the first and last ranks
+                    if rank == 0 || rank == 7 {
+                        return None;
+                    }
+                    let mut pawn = Pawn::new(*colour);
+                    pawn.set_first_move(match colour {
+                        Colour::White => rank == 1,
+                        Colour::Black => rank == 6,
+                    });
+                    Box::new(pawn)
+                }
+            };
+            // Endgames have no castling rights
+            if *symbol != 'P' {
+                piece.set_first_move(false);
+            }
+            let pieces = match colour {
+                Colour::White => &mut white_pieces,
+                Colour::Black => &mut black_pieces,
+            };
+            let square = Square::new(rank, (square % 8) as u8);
+            if pieces.insert(square, piece).is_some() {
+                return None;
+            }
+        }
+        if white_pieces.keys().any(|s| black_pieces.contains_key(s)) {
+            return None;
+        }
+
+        let board = Board::new(utils::init_squares(), white_pieces, black_pieces);
+
+        // The player who just moved can't be in check
+        let colour = get_colour(!position.white_to_move);
+        match board.is_under_check(colour) {
+            true => None,
+            false => Some(board),
+        }
+    }
+
+    /// Positions (from the same table) from which the position can be reached in one move.
+    fn get_predecessors(&self, position: &Position, board: &Board) -> Vec<usize> {
+        let colour = get_colour(!position.white_to_move);
+        let mut predecessors = vec![];
+
+        for (i, (c, symbol)) in self.pieces.iter().enumerate() {
+            if *c != colour {
+                continue;
+            }
+            let from = position.squares[i];
+            let targets: Vec<usize> = match symbol {
+                // Pawns move backwards, with a double step back to their initial rank
+                'P' => {
+                    let step: i32 = match colour {
+                        Colour::White => -8,
+                        Colour::Black => 8,
+                    };
+                    let is_free = |s: i32| !position.squares.contains(&(s as usize));
+                    let first = from as i32 + step;
+                    let mut targets = vec![];
+                    if (8..56).contains(&first) && is_free(first) {
+                        targets.push(first as usize);
+                        let second = first + step;
+                        let is_initial = match colour {
+                            Colour::White => second / 8 == 1,
+                            Colour::Black => second / 8 == 6,
+                        };
+                        if is_initial && is_free(second) {
+                            targets.push(second as usize);
+                        }
+                    }
+                    targets
+                }
+                // Other pieces move back the same way they move forward
+                _ => {
+                    let square = Square::new((from / 8) as u8, (from % 8) as u8);
+                    board
+                        .get_piece(square, Some(colour))
+                        .map_or(vec![], |p| p.available_moves(board, &square))
+                        .into_iter()
+                        .filter(|m| !m.is_capture())
+                        .map(|m| get_index(m.get_to()))
+                        .collect()
+                }
+            };
+
+            for to in targets {
+                let mut squares = position.squares.clone();
+                squares[i] = to;
+                predecessors.push(self.encode(&Position {
+                    squares,
+                    white_to_move: !position.white_to_move,
+                }));
+            }
+        }
+
+        predecessors.sort();
+        predecessors.dedup();
+        predecessors
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(self.name.len() as u8);
+        bytes.extend_from_slice(self.name.as_bytes());
+        bytes.extend_from_slice(&(self.values.len() as u32).to_le_bytes());
+        bytes.extend(pack(&self.values));
+
+        fs::write(path, bytes)
+            .map_err(|e| Error::msg(format!("Cannot write {}: {}", path.display(), e)))
+    }
+
+    pub fn open(path: &Path) -> Result<EndgameTable, Error> {
+        let bytes = fs::read(path)
+            .map_err(|e| Error::msg(format!("Cannot read {}: {}", path.display(), e)))?;
+        let invalid = || Error::msg(format!("{} is not an endgame table", path.display()));
+        if bytes.len() < 6 || &bytes[0..4] != MAGIC || bytes[4] != VERSION {
+            return Err(invalid());
+        }
+
+        let name_end = 6 + bytes[5] as usize;
+        let name = bytes
+            .get(6..name_end)
+            .and_then(|n| std::str::from_utf8(n).ok())
+            .ok_or_else(invalid)?;
+        let mut table = EndgameTable::new(name)?;
+        let size = bytes
+            .get(name_end..name_end + 4)
+            .map(|s| u32::from_le_bytes(s.try_into().unwrap()) as usize)
+            .ok_or_else(invalid)?;
+        let values = unpack(&bytes[name_end + 4..]);
+        if size != table.values.len() || values.len() != size {
+            return Err(invalid());
+        }
+        table.values = values;
+
+        Ok(table)
+    }
+}
+
+/// Generated or loaded tables, by material.
+pub struct EndgameTables {
+    tables: HashMap<String, EndgameTable>,
+}
+
+impl EndgameTables {
+    pub fn new() -> EndgameTables {
+        EndgameTables {
+            tables: HashMap::new(),
+        }
+    }
+
+    /// Load all the tables of a directory.
+    pub fn open(directory: &Path) -> Result<EndgameTables, Error> {
+        let files = fs::read_dir(directory)
+            .map_err(|e| Error::msg(format!("Cannot read {}: {}", directory.display(), e)))?;
+
+        let mut tables = EndgameTables::new();
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().is_some_and(|e| e == EXTENSION) {
+                let table = EndgameTable::open(&path)?;
+                tables.tables.insert(table.name.clone(), table);
+            }
+        }
+
+        Ok(tables)
+    }
+
+    /// Save all the tables in a directory, one file each.
+    pub fn save(&self, directory: &Path) -> Result<(), Error> {
+        fs::create_dir_all(directory)
+            .map_err(|e| Error::msg(format!("Cannot create {}: {}", directory.display(), e)))?;
+        for table in self.tables.values() {
+            let path = directory.join(format!("{}.{}", table.name, EXTENSION));
+            table.save(&path)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_tables(&self) -> Vec<&EndgameTable> {
+        let mut tables: Vec<&EndgameTable> = self.tables.values().collect();
+        tables.sort_by_key(|t| (t.pieces.len(), t.name.clone()));
+        tables
+    }
+
+    /// Generate the table of an ending (e.g. "KRvK"), and before it all the tables of the
+    /// endings reachable through captures and promotions. En passant isn't generated, so
+    /// endings with pawns on both sides are refused.
+    pub fn generate(&mut self, name: &str) -> Result<(), Error> {
+        let (white, black) = parse_name(name)?;
+        if white.contains('P') && black.contains('P') {
+            return Err(Error::msg(format!(
+                "Ending {} isn't supported: en passant isn't generated",
+                name
+            )));
+        }
+        let name = get_canonical_name(&white, &black);
+        if self.tables.contains_key(&name) || white.len() + black.len() == 2 {
+            return Ok(());
+        }
+        for (white, black) in get_sub_materials(&white, &black) {
+            self.generate(&get_canonical_name(&white, &black))?;
+        }
+
+        let mut table = EndgameTable::new(&name)?;
+        self.solve(&mut table);
+        self.tables.insert(name, table);
+
+        Ok(())
+    }
+
+    fn solve(&self, table: &mut EndgameTable) {
+        let size = table.values.len();
+        let mut is_open = vec![false; size];
+        let mut counters = vec![0u8; size];
+        let mut levels: Vec<Vec<usize>> = vec![vec![]];
+        let mut exit_wins: Vec<Vec<usize>> = vec![];
+        let mut exit_losses: Vec<Vec<usize>> = vec![];
+        let push = |levels: &mut Vec<Vec<usize>>, level: usize, index: usize| {
+            if levels.len() <= level {
+                levels.resize(level + 1, vec![]);
+            }
+            levels[level].push(index);
+        };
+
+        // Forward pass: mates, stalemates, and moves leaving the table (captures and promotions)
+        for index in 0..size {
+            let position = table.decode(index);
+            if table.encode(&position) != index {
+                continue;
+            }
+            let board = match table.build_board(&position) {
+                Some(board) => board,
+                None => continue,
+            };
+            let colour = get_colour(position.white_to_move);
+            let moves = Game::select_legal_moves(&board, colour);
+
+            if moves.is_empty() {
+                if board.is_under_check(colour) {
+                    table.values[index] = 1;
+                    levels[0].push(index);
+                }
+                continue;
+            }
+
+            let mut children = vec![];
+            let mut exits = 0;
+            for m in &moves {
+                if m.is_capture() || m.is_promotion() {
+                    let mut child = board.duplicate();
+                    child.execute_move(m);
+                    exits += 1;
+                    match self.probe(&child, colour.get_opposite()).get_previous() {
+                        Outcome::Win(plies) => push(&mut exit_wins, plies as usize, index),
+                        Outcome::Loss(plies) => push(&mut exit_losses, plies as usize - 1, index),
+                        Outcome::Draw => {}
+                    }
+                } else {
+                    let mut squares = position.squares.clone();
+                    let from = get_index(m.get_from());
+                    let moved = squares.iter().position(|s| *s == from).unwrap();
+                    squares[moved] = get_index(m.get_to());
+                    children.push(table.encode(&Position {
+                        squares,
+                        white_to_move: !position.white_to_move,
+                    }));
+                }
+            }
+            children.sort();
+            children.dedup();
+            counters[index] = (children.len() + exits) as u8;
+            is_open[index] = true;
+        }
+
+        // Backward passes: a position is won if a move reaches a lost position, and lost once
+        // all of its moves reach won positions
+        let mut level = 0;
+        while level < levels.len() || level < exit_wins.len() || level < exit_losses.len() {
+            if levels.len() <= level {
+                levels.resize(level + 1, vec![]);
+            }
+            for index in exit_wins.get(level).cloned().unwrap_or_default() {
+                if is_open[index] {
+                    is_open[index] = false;
+                    table.values[index] = level as u8 + 1;
+                    levels[level].push(index);
+                }
+            }
+
+            let is_loss = level % 2 == 0;
+            let mut resolved = vec![];
+            for index in std::mem::take(&mut levels[level]) {
+                is_open[index] = false;
+                let position = table.decode(index);
+                let board = table.build_board(&position).unwrap();
+                resolved.extend(table.get_predecessors(&position, &board));
+            }
+            if !is_loss {
+                resolved.extend(exit_losses.get(level).cloned().unwrap_or_default());
+            }
+
+            for index in resolved {
+                if !is_open[index] {
+                    continue;
+                }
+                if !is_loss {
+                    counters[index] -= 1;
+                    if counters[index] > 0 {
+                        continue;
+                    }
+                }
+                is_open[index] = false;
+                table.values[index] = level as u8 + 2;
+                push(&mut levels, level + 1, index);
+            }
+            level += 1;
+        }
+    }
+
+    /// Outcome of a position for the side to move. Positions with only the kings are draws.
+    pub fn probe(&self, board: &Board, colour: Colour) -> Outcome {
+        self.find(board, colour).unwrap_or(Outcome::Draw)
+    }
+
+    fn find(&self, board: &Board, colour: Colour) -> Option<Outcome> {
+        let white = get_signature(board, Colour::White);
+        let black = get_signature(board, Colour::Black);
+        if white.len() + black.len() == 2 {
+            return Some(Outcome::Draw);
+        }
+
+        // Tables have the side with more material as White: otherwise colours are swapped and
+        // the board mirrored, so that pawns still move up
+        let name = get_canonical_name(&white, &black);
+        let table = self.tables.get(&name)?;
+        let is_swapped = name != format!("{}v{}", white, black);
+        let (table_white, mirror) = match is_swapped {
+            true => (Colour::Black, 56),
+            false => (Colour::White, 0),
+        };
+
+        let mut pieces: Vec<(Colour, char, usize)> = vec![];
+        for c in [Colour::White, Colour::Black] {
+            for (square, piece) in board.get_pieces(c) {
+                let table_colour = match c == table_white {
+                    true => Colour::White,
+                    false => Colour::Black,
+                };
+                pieces.push((
+                    table_colour,
+                    piece.get_symbol(),
+                    get_index(*square) ^ mirror,
+                ));
+            }
+        }
+
+        let mut squares = vec![];
+        for piece in &table.pieces {
+            let index = pieces.iter().position(|(c, s, _)| (*c, *s) == *piece)?;
+            squares.push(pieces.remove(index).2);
+        }
+        let position = Position {
+            squares,
+            white_to_move: (colour == table_white),
+        };
+
+        Some(table.get_outcome(&position))
+    }
+
+    /// Moves with the best outcome. When the position is won or lost a single move is returned:
+    /// the fastest mate, or the longest resistance.
+    pub fn select_moves(
+        &self,
+        board: &Board,
+        colour: Colour,
+        moves: &[HalfMove],
+    ) -> Option<Vec<HalfMove>> {
+        let mut outcomes = vec![];
+        for m in moves {
+            let mut child = board.duplicate();
+            child.execute_move(m);
+            let outcome = self.find(&child, colour.get_opposite())?.get_previous();
+            outcomes.push((outcome.get_evaluation(), outcome, *m));
+        }
+
+        let best = outcomes
+            .iter()
+            .map(|(e, _, _)| *e)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let mut best: Vec<(Outcome, HalfMove)> = outcomes
+            .into_iter()
+            .filter(|(e, _, _)| *e == best)
+            .map(|(_, o, m)| (o, m))
+            .collect();
+
+        // Drawing moves are left to the search
+        match best.first()?.0 {
+            Outcome::Draw => Some(best.into_iter().map(|(_, m)| m).collect()),
+            outcome => {
+                let (_, mut play) = best.swap_remove(0);
+                play.set_evaluation(outcome.get_evaluation());
+                Some(vec![play])
+            }
+        }
+    }
+}
+
+impl Default for EndgameTables {
+    fn default() -> EndgameTables {
+        EndgameTables::new()
+    }
+}
+
+fn get_colour(is_white: bool) -> Colour {
+    match is_white {
+        true => Colour::White,
+        false => Colour::Black,
+    }
+}
+
+fn get_index(square: Square) -> usize {
+    square.get_rank() as usize * 8 + square.get_file() as usize
+}
+
+/// One of the 8 symmetries of the board: mirror files, mirror ranks, swap ranks and files.
+fn transform_square(square: usize, transform: usize) -> usize {
+    let mut square = square;
+    if transform & 1 != 0 {
+        square ^= 7;
+    }
+    if transform & 2 != 0 {
+        square ^= 56;
+    }
+    if transform & 4 != 0 {
+        square = (square % 8) * 8 + square / 8;
+    }
+    square
+}
+
+fn get_signature(board: &Board, colour: Colour) -> String {
+    let mut symbols: Vec<char> = board
+        .get_pieces(colour)
+        .values()
+        .map(|p| p.get_symbol())
+        .collect();
+    symbols.sort_by_key(|s| PIECE_ORDER.iter().position(|o| o == s));
+    symbols.into_iter().collect()
+}
+
+fn parse_name(name: &str) -> Result<(String, String), Error> {
+    let invalid = || Error::msg(format!("Invalid ending {}", name));
+    let (white, black) = name.split_once('v').ok_or_else(invalid)?;
+    for side in [white, black] {
+        let is_valid = side.starts_with('K')
+            && side.chars().skip(1).all(|c| "QRBNP".contains(c))
+            && side.chars().filter(|c| *c == 'K').count() == 1;
+        if !is_valid {
+            return Err(invalid());
+        }
+    }
+
+    let sort = |side: &str| {
+        let mut symbols: Vec<char> = side.chars().collect();
+        symbols.sort_by_key(|s| PIECE_ORDER.iter().position(|o| o == s));
+        symbols.into_iter().collect::<String>()
+    };
+    Ok((sort(white), sort(black)))
+}
+
+/// Name of the table of a material, with the side with more material first.
+fn get_canonical_name(white: &str, black: &str) -> String {
+    let value = |side: &str| -> u32 {
+        side.chars()
+            .map(|c| match c {
+                'Q' => 9,
+                'R' => 5,
+                'B' | 'N' => 3,
+                'P' => 1,
+                _ => 0,
+            })
+            .sum()
+    };
+    let key = |side: &str| (value(side), side.len(), side.to_string());
+    match key(white) >= key(black) {
+        true => format!("{}v{}", white, black),
+        false => format!("{}v{}", black, white),
+    }
+}
+
+/// Materials reachable with a capture or a promotion (to queen).
+fn get_sub_materials(white: &str, black: &str) -> Vec<(String, String)> {
+    let mut materials = vec![];
+    let remove = |side: &str, i: usize| {
+        let mut side = side.to_string();
+        side.remove(i);
+        side
+    };
+    let promote = |side: &str, i: usize| {
+        let mut symbols: Vec<char> = remove(side, i).chars().collect();
+        symbols.push('Q');
+        symbols.sort_by_key(|s| PIECE_ORDER.iter().position(|o| o == s));
+        symbols.into_iter().collect::<String>()
+    };
+
+    for (i, c) in white.char_indices().skip(1) {
+        materials.push((remove(white, i), black.to_string()));
+        if c == 'P' {
+            materials.push((promote(white, i), black.to_string()));
+        }
+    }
+    for (i, c) in black.char_indices().skip(1) {
+        materials.push((white.to_string(), remove(black, i)));
+        if c == 'P' {
+            materials.push((white.to_string(), promote(black, i)));
+        }
+    }
+
+    materials
+        .into_iter()
+        .filter(|(w, b)| w.starts_with('K') && b.starts_with('K'))
+        .collect()
+}
+
+/// PackBits: a header byte below 128 is followed by that many literal bytes plus one, otherwise
+/// the next byte is repeated (header - 126) times.
+fn pack(values: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut literals: Vec<u8> = vec![];
+    let mut i = 0;
+    while i < values.len() {
+        let mut run = 1;
+        while i + run < values.len() && values[i + run] == values[i] && run < 129 {
+            run += 1;
+        }
+        if (run >= 3 || literals.len() == 128) && !literals.is_empty() {
+            bytes.push(literals.len() as u8 - 1);
+            bytes.append(&mut literals);
+        }
+        if run >= 3 {
+            bytes.push(run as u8 + 126);
+            bytes.push(values[i]);
+            i += run;
+        } else {
+            literals.push(values[i]);
+            i += 1;
+        }
+    }
+    if !literals.is_empty() {
+        bytes.push(literals.len() as u8 - 1);
+        bytes.append(&mut literals);
+    }
+
+    bytes
+}
+
+fn unpack(bytes: &[u8]) -> Vec<u8> {
+    let mut values = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let header = bytes[i] as usize;
+        if header < 128 {
+            let end = (i + 2 + header).min(bytes.len());
+            values.extend_from_slice(&bytes[i + 1..end]);
+            i = end;
+        } else {
+            if let Some(value) = bytes.get(i + 1) {
+                values.extend(std::iter::repeat_n(*value, header - 126));
+            }
+            i += 2;
+        }
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Legal positions, decisive positions and longest mate in plies, for white then black to
+    /// move.
+    fn get_statistics(name: &str) -> [(usize, usize, u8); 2] {
+        let mut tables = EndgameTables::new();
+        tables.generate(name).unwrap();
+        let table = &tables.tables[name];
+
+        let mut statistics = [(0, 0, 0); 2];
+        for index in 0..table.values.len() {
+            let position = table.decode(index);
+            if table.encode(&position) != index || table.build_board(&position).is_none() {
+                continue;
+            }
+            let (positions, decisive, longest) = match position.white_to_move {
+                true => &mut statistics[0],
+                false => &mut statistics[1],
+            };
+            *positions += 1;
+            if let Outcome::Win(plies) | Outcome::Loss(plies) = table.get_outcome(&position) {
+                *decisive += 1;
+                *longest = (*longest).max(plies);
+            }
+        }
+
+        statistics
+    }
+
+    #[test]
+    fn generate_queen_ending() {
+        // Mate in 10 at most
+        assert_eq!(
+            get_statistics("KQvK"),
+            [(18081, 18081, 19), (28056, 25160, 20)]
+        );
+    }
+
+    #[test]
+    fn generate_rook_ending() {
+        // Mate in 16 at most
+        assert_eq!(
+            get_statistics("KRvK"),
+            [(21959, 21959, 31), (28056, 25260, 32)]
+        );
+    }
+
+    #[test]
+    fn pawns_on_both_sides_are_refused() {
+        let mut tables = EndgameTables::new();
+        assert!(tables.generate("KPvKP").is_err());
+        assert!(tables.generate("KRPvKP").is_err());
+        assert!(tables.get_tables().is_empty());
+    }
+}
@@ -0,0 +1,1288 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::Error;
+
+use crate::game::game::Game;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::PieceType;
+use crate::utils;
+
+// Syzygy tablebases, probed from the .rtbw (win/draw/loss) and .rtbz (distance to zeroing)
+// files of a local directory. The file format is the one designed by Ronald de Man: positions
+// are mapped to an index exploiting the symmetries of the board, and values are stored
+// compressed with Huffman coded "recursive pairing" symbols.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+const MAX_PIECES: usize = 7;
+const PIECE_ORDER: [char; 6] = ['K', 'Q', 'R', 'B', 'N', 'P'];
+
+// Flags of a table
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// Result of a position for the side to move. Cursed wins and blessed losses are wins and
+/// losses that cannot be forced before the 50 moves rule.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            v if v <= -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    pub fn get_opposite(&self) -> Wdl {
+        Wdl::from_value(-(*self as i32))
+    }
+
+    /// Distance to zeroing of the move that reached a position with this result.
+    fn dtz_before_zeroing(&self) -> i32 {
+        match self {
+            Wdl::Win => 1,
+            Wdl::CursedWin => 101,
+            Wdl::BlessedLoss => -101,
+            Wdl::Loss => -1,
+            Wdl::Draw => 0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// Outcome of a single table lookup: DTZ tables only store one side to move.
+enum Lookup {
+    Value(i32),
+    ChangeSideToMove,
+}
+
+/// Index tables shared by all the tablebases, computed once.
+struct Indexes {
+    map_pawns: [usize; 64],
+    map_b1h1h7: [usize; 64],
+    map_a1d1d4: [usize; 64],
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; MAX_PIECES],
+    lead_pawn_index: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+fn off_a1h8(square: usize) -> i32 {
+    (square >> 3) as i32 - (square & 7) as i32
+}
+
+impl Indexes {
+    fn get() -> &'static Indexes {
+        static INDEXES: OnceLock<Indexes> = OnceLock::new();
+        INDEXES.get_or_init(Indexes::new)
+    }
+
+    fn new() -> Indexes {
+        let mut indexes = Indexes {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            lead_pawn_index: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+        };
+
+        // Squares below the a1-h8 diagonal, mapped to 0..27
+        let mut code = 0;
+        for square in 0..64 {
+            if off_a1h8(square) < 0 {
+                indexes.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        // Squares of the a1-d1-d4 triangle mapped to 0..9, diagonal ones last
+        let mut diagonal = vec![];
+        code = 0;
+        for square in 0..28 {
+            if off_a1h8(square) < 0 && square & 7 <= 3 {
+                indexes.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_a1h8(square) == 0 && square & 7 <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            indexes.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // The 462 legal placements of two kings with the first one in the a1-d1-d4 triangle. If
+        // the first king is on the diagonal, the second one cannot be above it.
+        let mut both_on_diagonal = vec![];
+        code = 0;
+        for index in 0..10 {
+            for s1 in 0..28 {
+                if indexes.map_a1d1d4[s1] != index || (index == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64 {
+                    let distance = ((s1 >> 3) as i32 - (s2 >> 3) as i32)
+                        .abs()
+                        .max(((s1 & 7) as i32 - (s2 & 7) as i32).abs());
+                    if distance <= 1 || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                        continue;
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((index, s2));
+                    } else {
+                        indexes.map_kk[index][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (index, s2) in both_on_diagonal {
+            indexes.map_kk[index][s2] = code;
+            code += 1;
+        }
+
+        // Ways to choose k elements from a set of n elements
+        indexes.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                indexes.binomial[k][n] = match k > 0 {
+                    true => indexes.binomial[k - 1][n - 1],
+                    false => 0,
+                } + match k < n {
+                    true => indexes.binomial[k][n - 1],
+                    false => 0,
+                };
+            }
+        }
+
+        // Squares a2-h7 mapped to 0..47: the pawn with the highest value is the leading one,
+        // the nearest to the edge and, among pawns on the same file, the one with lowest rank
+        let mut available_squares = 47;
+        for lead_pawns in 1..MAX_PIECES - 1 {
+            for file in 0..4 {
+                let mut index = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        indexes.map_pawns[square] = available_squares;
+                        indexes.map_pawns[square ^ 7] = available_squares - 1;
+                        available_squares = available_squares.saturating_sub(2);
+                    }
+                    indexes.lead_pawn_index[lead_pawns][square] = index;
+                    index += indexes.binomial[lead_pawns - 1][indexes.map_pawns[square]];
+                }
+                indexes.lead_pawns_size[lead_pawns][file] = index;
+            }
+        }
+
+        indexes
+    }
+}
+
+/// Little helpers to read the table data without panicking on corrupted files.
+fn read_u8(data: &[u8], offset: usize) -> u8 {
+    data.get(offset).copied().unwrap_or(0)
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([read_u8(data, offset), read_u8(data, offset + 1)])
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        read_u8(data, offset),
+        read_u8(data, offset + 1),
+        read_u8(data, offset + 2),
+        read_u8(data, offset + 3),
+    ])
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        read_u8(data, offset),
+        read_u8(data, offset + 1),
+        read_u8(data, offset + 2),
+        read_u8(data, offset + 3),
+    ])
+}
+
+fn read_u64_be(data: &[u8], offset: usize) -> u64 {
+    (read_u32_be(data, offset) as u64) << 32 | read_u32_be(data, offset + 4) as u64
+}
+
+/// Decompression and indexing data of one sub-table (one side to move, one leading pawn file).
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    block_size: usize,
+    span: u64,
+    num_blocks: usize,
+    min_sym_len: u8,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_index: [u64; MAX_PIECES + 1],
+    group_len: [usize; MAX_PIECES + 1],
+    map_index: [usize; 4],
+}
+
+impl PairsData {
+    fn get_left(&self, data: &[u8], symbol: usize) -> usize {
+        let offset = self.btree + 3 * symbol;
+        ((read_u8(data, offset + 1) as usize & 0xF) << 8) | read_u8(data, offset) as usize
+    }
+
+    fn get_right(&self, data: &[u8], symbol: usize) -> usize {
+        let offset = self.btree + 3 * symbol;
+        ((read_u8(data, offset + 2) as usize) << 4) | (read_u8(data, offset + 1) as usize >> 4)
+    }
+
+    fn get_block_length(&self, data: &[u8], block: usize) -> i64 {
+        read_u16_le(data, self.block_length + 2 * block) as i64
+    }
+
+    fn set_symlen(&mut self, data: &[u8], symbol: usize, visited: &mut Vec<bool>) -> u8 {
+        visited[symbol] = true;
+        let right = self.get_right(data, symbol);
+        if right == 0xFFF {
+            return 0;
+        }
+        let left = self.get_left(data, symbol);
+        for child in [left, right] {
+            if child < visited.len() && !visited[child] {
+                self.symlen[child] = self.set_symlen(data, child, visited);
+            }
+        }
+
+        let left = self.symlen.get(left).copied().unwrap_or(0);
+        let right = self.symlen.get(right).copied().unwrap_or(0);
+        left.wrapping_add(right).wrapping_add(1)
+    }
+
+    fn set_sizes(&mut self, data: &[u8], mut offset: usize) -> usize {
+        self.flags = read_u8(data, offset);
+        offset += 1;
+
+        // All the positions store the same value
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            self.min_sym_len = read_u8(data, offset);
+            return offset + 1;
+        }
+
+        let groups = self
+            .group_len
+            .iter()
+            .position(|l| *l == 0)
+            .unwrap_or(MAX_PIECES);
+        let table_size = self.group_index[groups];
+
+        self.block_size = 1 << read_u8(data, offset);
+        self.span = 1 << read_u8(data, offset + 1);
+        self.sparse_index_size = table_size.div_ceil(self.span) as usize;
+        let padding = read_u8(data, offset + 2) as usize;
+        self.num_blocks = read_u32_le(data, offset + 3) as usize;
+        self.block_length_size = self.num_blocks + padding;
+        let max_sym_len = read_u8(data, offset + 7);
+        self.min_sym_len = read_u8(data, offset + 8);
+        offset += 9;
+        self.lowest_sym = offset;
+
+        // Canonical Huffman code: base64[l] is the lowest symbol of length l + min_sym_len,
+        // right-padded to 64 bits
+        let lengths = (max_sym_len as usize + 1).saturating_sub(self.min_sym_len as usize);
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths.saturating_sub(1)).rev() {
+            let lowest = read_u16_le(data, self.lowest_sym + 2 * i) as u64;
+            let next_lowest = read_u16_le(data, self.lowest_sym + 2 * (i + 1)) as u64;
+            self.base64[i] = self.base64[i + 1]
+                .wrapping_add(lowest)
+                .wrapping_sub(next_lowest)
+                / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            let shift = 64 - i as u32 - self.min_sym_len as u32;
+            *base = base.checked_shl(shift).unwrap_or(0);
+        }
+        offset += lengths * 2;
+
+        // Recursive pairing: every symbol stands for a pair of smaller symbols
+        let symbols = read_u16_le(data, offset) as usize;
+        offset += 2;
+        self.btree = offset;
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                self.symlen[symbol] = self.set_symlen(data, symbol, &mut visited);
+            }
+        }
+
+        offset + symbols * 3 + (symbols & 1)
+    }
+
+    fn decompress(&self, data: &[u8], index: u64) -> i32 {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return self.min_sym_len as i32;
+        }
+        if self.span == 0 || self.base64.is_empty() {
+            return 0;
+        }
+
+        // The sparse index points near the block holding the value, then move block by block
+        let k = (index / self.span) as usize;
+        let entry = self.sparse_index + 6 * k;
+        let mut block = read_u32_le(data, entry) as usize;
+        let mut offset = read_u16_le(data, entry + 4) as i64;
+        offset += (index % self.span) as i64 - (self.span / 2) as i64;
+
+        while offset < 0 && block > 0 {
+            block -= 1;
+            offset += self.get_block_length(data, block) + 1;
+        }
+        while offset > self.get_block_length(data, block) && block < self.block_length_size {
+            offset -= self.get_block_length(data, block) + 1;
+            block += 1;
+        }
+
+        // Read symbols until the one containing the value
+        let mut pointer = self.data + block * self.block_size;
+        let mut buffer = read_u64_be(data, pointer);
+        pointer += 8;
+        let mut buffer_size = 64;
+        let min_sym_len = self.min_sym_len as usize;
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while len + 1 < self.base64.len() && buffer < self.base64[len] {
+                len += 1;
+            }
+            symbol = ((buffer - self.base64[len]) >> (64 - len - min_sym_len)) as usize;
+            symbol = (symbol + read_u16_le(data, self.lowest_sym + 2 * len) as usize) & 0xFFFF;
+            let symlen = self.symlen.get(symbol).copied().unwrap_or(0) as i64;
+            if offset < symlen + 1 {
+                break;
+            }
+            offset -= symlen + 1;
+            let len = len + min_sym_len;
+            buffer <<= len;
+            buffer_size -= len;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(data, pointer) as u64) << (64 - buffer_size);
+                pointer += 4;
+            }
+        }
+
+        // Expand the symbol until the leaf holding the value
+        while self.symlen.get(symbol).copied().unwrap_or(0) != 0 {
+            let left = self.get_left(data, symbol);
+            let left_len = self.symlen.get(left).copied().unwrap_or(0) as i64;
+            if offset < left_len + 1 {
+                symbol = left;
+            } else {
+                offset -= left_len + 1;
+                symbol = self.get_right(data, symbol);
+            }
+        }
+
+        self.get_left(data, symbol) as i32
+    }
+}
+
+/// Material of a table, e.g. "KRvK": pieces of the stronger side (white in the files) first.
+#[derive(Debug, Clone)]
+struct Material {
+    white: String,
+    black: String,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    pawn_count: [usize; 2],
+}
+
+impl Material {
+    fn new(white: &str, black: &str) -> Material {
+        let count = |side: &str, symbol| side.chars().filter(|c| *c == symbol).count();
+        let white_pawns = count(white, 'P');
+        let black_pawns = count(black, 'P');
+        let has_unique_pieces = ['Q', 'R', 'B', 'N', 'P']
+            .into_iter()
+            .any(|s| count(white, s) == 1 || count(black, s) == 1);
+
+        // With pawns on both sides, the side with less pawns leads
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = match white_leads {
+            true => [white_pawns, black_pawns],
+            false => [black_pawns, white_pawns],
+        };
+
+        Material {
+            white: white.to_string(),
+            black: black.to_string(),
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            pawn_count,
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.white == self.black
+    }
+}
+
+/// One parsed .rtbw or .rtbz file.
+struct Table {
+    kind: TableKind,
+    data: Vec<u8>,
+    material: Material,
+    items: [Vec<PairsData>; 2],
+    map: usize,
+}
+
+impl Table {
+    fn open(path: &Path, kind: TableKind, material: &Material) -> Option<Table> {
+        let data = fs::read(path).ok()?;
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if data.len() < 5 || data[0..4] != magic {
+            return None;
+        }
+
+        let mut table = Table {
+            kind,
+            data: vec![],
+            material: material.clone(),
+            items: [vec![], vec![]],
+            map: 0,
+        };
+        table.parse(&data)?;
+        table.data = data;
+        Some(table)
+    }
+
+    fn get_sides(&self) -> usize {
+        match self.kind == TableKind::Wdl && !self.material.is_symmetric() {
+            true => 2,
+            false => 1,
+        }
+    }
+
+    fn get_item(&self, side_to_move: usize, file: usize) -> &PairsData {
+        let side = match self.kind {
+            TableKind::Wdl => side_to_move % 2,
+            TableKind::Dtz => 0,
+        };
+        let file = match self.material.has_pawns {
+            true => file,
+            false => 0,
+        };
+        &self.items[side][file]
+    }
+
+    fn parse(&mut self, data: &[u8]) -> Option<()> {
+        let indexes = Indexes::get();
+        let material = self.material.clone();
+        let flags = data[4];
+        if material.has_pawns != (flags & 2 != 0) || material.is_symmetric() == (flags & 1 != 0) {
+            return None;
+        }
+
+        let sides = self.get_sides();
+        let files = match material.has_pawns {
+            true => 4,
+            false => 1,
+        };
+        let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        self.items = [
+            vec![PairsData::default(); files],
+            vec![PairsData::default(); files],
+        ];
+
+        // Pieces order and groups
+        let mut offset = 5;
+        for file in 0..files {
+            let second = match both_pawns {
+                true => read_u8(data, offset + 1),
+                false => 0xFF,
+            };
+            let order = [
+                [read_u8(data, offset) & 0xF, second & 0xF],
+                [read_u8(data, offset) >> 4, second >> 4],
+            ];
+            offset += 1 + both_pawns as usize;
+
+            for k in 0..material.piece_count {
+                let byte = read_u8(data, offset);
+                self.items[0][file].pieces[k] = byte & 0xF;
+                self.items[1][file].pieces[k] = byte >> 4;
+                offset += 1;
+            }
+            for (side, order) in order.iter().enumerate().take(sides) {
+                Table::set_groups(indexes, &material, &mut self.items[side][file], order, file);
+            }
+        }
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                offset = self.items[side][file].set_sizes(data, offset);
+            }
+        }
+
+        // Values of DTZ tables are sorted by frequency, the map restores the original ones
+        if self.kind == TableKind::Dtz {
+            self.map = offset;
+            for file in 0..files {
+                let item = &mut self.items[0][file];
+                if item.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                if item.flags & FLAG_WIDE != 0 {
+                    offset += offset & 1;
+                    for i in 0..4 {
+                        item.map_index[i] = (offset - self.map) / 2 + 1;
+                        offset += 2 * read_u16_le(data, offset) as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        item.map_index[i] = offset - self.map + 1;
+                        offset += read_u8(data, offset) as usize + 1;
+                    }
+                }
+            }
+            offset += offset & 1;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let item = &mut self.items[side][file];
+                item.sparse_index = offset;
+                offset += item.sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let item = &mut self.items[side][file];
+                item.block_length = offset;
+                offset += item.block_length_size * 2;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let item = &mut self.items[side][file];
+                offset = (offset + 0x3F) & !0x3F;
+                item.data = offset;
+                offset += item.num_blocks * item.block_size;
+            }
+        }
+
+        match offset <= data.len() {
+            true => Some(()),
+            false => None,
+        }
+    }
+
+    /// Pieces are encoded in groups: the leading group (pawns of the leading side, or three
+    /// unique pieces, or the two kings), then pieces of the same type and colour together.
+    fn set_groups(
+        indexes: &Indexes,
+        material: &Material,
+        item: &mut PairsData,
+        order: &[u8; 2],
+        file: usize,
+    ) {
+        let mut n = 0;
+        let mut first_len: i32 = match (material.has_pawns, material.has_unique_pieces) {
+            (true, _) => 0,
+            (false, true) => 3,
+            (false, false) => 2,
+        };
+        item.group_len[0] = 1;
+        for i in 1..material.piece_count {
+            first_len -= 1;
+            if first_len > 0 || item.pieces[i] == item.pieces[i - 1] {
+                item.group_len[n] += 1;
+            } else {
+                n += 1;
+                item.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        item.group_len[n] = 0;
+
+        let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut next = 1 + both_pawns as usize;
+        let mut free_squares = 64
+            - item.group_len[0]
+            - match both_pawns {
+                true => item.group_len[1],
+                false => 0,
+            };
+        let mut index: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                // Leading pawns or pieces
+                item.group_index[0] = index;
+                index *= match (material.has_pawns, material.has_unique_pieces) {
+                    (true, _) => indexes.lead_pawns_size[item.group_len[0]][file],
+                    (false, true) => 31332,
+                    (false, false) => 462,
+                };
+            } else if k == order[1] {
+                // Remaining pawns
+                item.group_index[1] = index;
+                index *= indexes.binomial[item.group_len[1]][48 - item.group_len[0]];
+            } else {
+                // Remaining pieces
+                item.group_index[next] = index;
+                index *= indexes.binomial[item.group_len[next]][free_squares];
+                free_squares -= item.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        item.group_index[n] = index;
+    }
+
+    fn map_score(&self, file: usize, value: i32, wdl: Wdl) -> i32 {
+        if self.kind == TableKind::Wdl {
+            return value - 2;
+        }
+
+        let item = self.get_item(0, file);
+        let mut value = value;
+        if item.flags & FLAG_MAPPED != 0 {
+            let index = match wdl {
+                Wdl::Win => item.map_index[0],
+                Wdl::Loss => item.map_index[1],
+                Wdl::CursedWin => item.map_index[2],
+                _ => item.map_index[3],
+            } + value as usize;
+            value = match item.flags & FLAG_WIDE != 0 {
+                true => read_u16_le(&self.data, self.map + 2 * index) as i32,
+                false => read_u8(&self.data, self.map + index) as i32,
+            };
+        }
+
+        // Values are stored in moves or in plies: always return plies
+        let is_moves = match wdl {
+            Wdl::Win => item.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => item.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        match is_moves {
+            true => value * 2 + 1,
+            false => value + 1,
+        }
+    }
+
+    /// Look up the value of a position, given as (square, piece code) pairs sorted by square.
+    /// Codes are 1 to 6 for white pawn, knight, bishop, rook, queen and king, plus 8 for black.
+    fn probe(
+        &self,
+        pieces: &[(usize, u8)],
+        side_to_move: usize,
+        is_black_stronger: bool,
+        wdl: Wdl,
+    ) -> Lookup {
+        let indexes = Indexes::get();
+        let material = &self.material;
+
+        // Tables are computed with the stronger side as white, and symmetric tables only for
+        // white to move: otherwise colours and squares must be flipped
+        let is_flipped = (material.is_symmetric() && side_to_move == 1) || is_black_stronger;
+        let flip_colour = if is_flipped { 8 } else { 0 };
+        let flip_squares = if is_flipped { 56 } else { 0 };
+        let side_to_move = is_flipped as usize ^ side_to_move;
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut codes = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut file = 0;
+
+        // With pawns there is a table for each file of the leading pawn, the one with the
+        // highest pawn map value
+        if material.has_pawns {
+            let lead_code = self.get_item(0, 0).pieces[0] ^ flip_colour;
+            for (square, _) in pieces.iter().filter(|(_, c)| *c == lead_code) {
+                squares[size] = square ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns = size;
+            let mut lead = 0;
+            for i in 1..lead_pawns {
+                if indexes.map_pawns[squares[i]] > indexes.map_pawns[squares[lead]] {
+                    lead = i;
+                }
+            }
+            squares.swap(0, lead);
+            file = (squares[0] & 7).min(7 - (squares[0] & 7));
+        }
+
+        // DTZ tables store one side to move only
+        if self.kind == TableKind::Dtz {
+            let flags = self.get_item(side_to_move, file).flags;
+            let is_stored = (flags & FLAG_STM) as usize == side_to_move
+                || (material.is_symmetric() && !material.has_pawns);
+            if !is_stored {
+                return Lookup::ChangeSideToMove;
+            }
+        }
+
+        let lead_code = match material.has_pawns {
+            true => Some(self.get_item(0, 0).pieces[0] ^ flip_colour),
+            false => None,
+        };
+        for (square, code) in pieces.iter().filter(|(_, c)| Some(*c) != lead_code) {
+            squares[size] = square ^ flip_squares;
+            codes[size] = code ^ flip_colour;
+            size += 1;
+        }
+        let item = self.get_item(side_to_move, file);
+
+        // Same pieces order as the one of the table
+        for i in lead_pawns..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if item.pieces[i] == codes[j] {
+                    codes.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // The leading piece must be on files a-d
+        if squares[0] & 7 > 3 {
+            for square in squares.iter_mut().take(size) {
+                *square ^= 7;
+            }
+        }
+
+        let mut index;
+        if material.has_pawns {
+            index = indexes.lead_pawn_index[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|s| indexes.map_pawns[*s]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                index += indexes.binomial[i][indexes.map_pawns[*square]];
+            }
+        } else {
+            // The leading piece must be on ranks 1-4
+            if squares[0] >> 3 > 3 {
+                for square in squares.iter_mut().take(size) {
+                    *square ^= 56;
+                }
+            }
+
+            // The first leading piece not on the a1-h8 diagonal must be below it
+            for i in 0..item.group_len[0] {
+                if off_a1h8(squares[i]) == 0 {
+                    continue;
+                }
+                if off_a1h8(squares[i]) > 0 {
+                    for square in squares.iter_mut().take(size).skip(i) {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            index = match material.has_unique_pieces {
+                true => Table::encode_unique_pieces(indexes, &squares),
+                false => indexes.map_kk[indexes.map_a1d1d4[squares[0]]][squares[1]] as u64,
+            };
+        }
+        index *= item.group_index[0];
+
+        // Remaining groups, each one sorted by square
+        let mut start = item.group_len[0];
+        let mut remaining_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut next = 1;
+        while item.group_len[next] != 0 {
+            let len = item.group_len[next];
+            squares[start..start + len].sort();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|s| square > **s).count();
+                let available = square - adjust - 8 * remaining_pawns as usize;
+                n += indexes.binomial[i + 1][available];
+            }
+            remaining_pawns = false;
+            index += n * item.group_index[next];
+            start += len;
+            next += 1;
+        }
+
+        let value = item.decompress(&self.data, index);
+        Lookup::Value(self.map_score(file, value, wdl))
+    }
+
+    fn encode_unique_pieces(indexes: &Indexes, squares: &[usize; MAX_PIECES]) -> u64 {
+        let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+        let adjust1 = (s1 > s0) as usize;
+        let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+        let rank = |s: usize| s >> 3;
+
+        let index = if off_a1h8(s0) != 0 {
+            (indexes.map_a1d1d4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+        } else if off_a1h8(s1) != 0 {
+            (6 * 63 + rank(s0) * 28 + indexes.map_b1h1h7[s1]) * 62 + s2 - adjust2
+        } else if off_a1h8(s2) != 0 {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + rank(s0) * 7 * 28
+                + (rank(s1) - adjust1) * 28
+                + indexes.map_b1h1h7[s2]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank(s0) * 7 * 6
+                + (rank(s1) - adjust1) * 6
+                + (rank(s2) - adjust2)
+        };
+
+        index as u64
+    }
+}
+
+/// Tables of a given material, read from disk at their first use.
+struct Entry {
+    material: Material,
+    wdl_path: PathBuf,
+    dtz_path: PathBuf,
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>,
+}
+
+impl Entry {
+    fn get_table(&self, kind: TableKind) -> Option<&Table> {
+        match kind {
+            TableKind::Wdl => self
+                .wdl
+                .get_or_init(|| Table::open(&self.wdl_path, kind, &self.material)),
+            TableKind::Dtz => self
+                .dtz
+                .get_or_init(|| Table::open(&self.dtz_path, kind, &self.material)),
+        }
+        .as_ref()
+    }
+}
+
+pub struct Tablebase {
+    entries: HashMap<String, Entry>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    /// Find all the tables of a directory. Files are only read when needed.
+    pub fn open(directory: &Path) -> Result<Tablebase, Error> {
+        let files = fs::read_dir(directory)
+            .map_err(|e| Error::msg(format!("Cannot read {}: {}", directory.display(), e)))?;
+
+        let mut entries = HashMap::new();
+        let mut max_pieces = 0;
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|e| e != "rtbw") {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let (white, black) = match name.split_once('v') {
+                Some(sides) => sides,
+                None => continue,
+            };
+            let is_valid = |side: &str| {
+                side.starts_with('K') && side.chars().all(|c| PIECE_ORDER.contains(&c))
+            };
+            if !is_valid(white) || !is_valid(black) || name.len() - 1 > MAX_PIECES {
+                continue;
+            }
+
+            let material = Material::new(white, black);
+            max_pieces = max_pieces.max(material.piece_count);
+            let entry = Entry {
+                material,
+                wdl_path: path.clone(),
+                dtz_path: path.with_extension("rtbz"),
+                wdl: OnceLock::new(),
+                dtz: OnceLock::new(),
+            };
+            entries.insert(name, entry);
+        }
+
+        Ok(Tablebase {
+            entries,
+            max_pieces,
+        })
+    }
+
+    pub fn get_max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether the tables for the material on the board are available. Positions where a player
+    /// may still castle aren't, as the tables assume no castling rights.
+    pub fn is_probable(&self, board: &Board) -> bool {
+        let pieces = board.get_pieces(Colour::White).len() + board.get_pieces(Colour::Black).len();
+        if pieces > self.max_pieces || Tablebase::has_castling_rights(board) {
+            return false;
+        }
+
+        let white = Tablebase::get_signature(board, Colour::White);
+        let black = Tablebase::get_signature(board, Colour::Black);
+        self.entries.contains_key(&format!("{}v{}", white, black))
+            || self.entries.contains_key(&format!("{}v{}", black, white))
+    }
+
+    /// Whether a king and a rook of the same player have never moved.
+    fn has_castling_rights(board: &Board) -> bool {
+        [Colour::White, Colour::Black].into_iter().any(|colour| {
+            let pieces = board.get_pieces(colour);
+            let is_unmoved = |symbol| {
+                pieces
+                    .values()
+                    .any(|p| p.get_symbol() == symbol && p.is_first_move())
+            };
+            is_unmoved('K') && is_unmoved('R')
+        })
+    }
+
+    /// Win, draw or loss for the side to move, if the position is in the tablebase.
+    pub fn probe_wdl(&self, board: &Board, colour: Colour) -> Option<Wdl> {
+        self.search_wdl(board, colour, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Distance to zeroing (capture or pawn move) in plies for the side to move, positive if
+    /// winning and negative if losing. Plus or minus 100 for cursed wins and blessed losses.
+    pub fn probe_dtz(&self, board: &Board, colour: Colour) -> Option<i32> {
+        let (wdl, is_zeroing_best) = self.search_wdl(board, colour, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+
+        // The table stores a "don't care" value when zeroing is the best option
+        if is_zeroing_best {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        let sign = (wdl as i32).signum();
+        match self.probe_table(board, colour, TableKind::Dtz, wdl)? {
+            Lookup::Value(dtz) => {
+                let cursed = match wdl {
+                    Wdl::CursedWin | Wdl::BlessedLoss => 100,
+                    _ => 0,
+                };
+                Some((dtz + cursed) * sign)
+            }
+            Lookup::ChangeSideToMove => {
+                // The table stores the other side to move: find the best move one ply ahead
+                let mut min_dtz = i32::MAX;
+                for m in Game::select_legal_moves(board, colour) {
+                    let is_zeroing = Tablebase::is_zeroing(&m);
+                    let mut next = board.duplicate();
+                    next.execute_move(&m);
+                    let opponent = colour.get_opposite();
+
+                    let mut dtz = match is_zeroing {
+                        true => -self.probe_wdl(&next, opponent)?.dtz_before_zeroing(),
+                        false => -self.probe_dtz(&next, opponent)?,
+                    };
+                    let is_mate = dtz == 1
+                        && next.is_under_check(opponent)
+                        && Game::select_legal_moves(&next, opponent).is_empty();
+                    if is_mate {
+                        min_dtz = 1;
+                    }
+                    if !is_zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < min_dtz && dtz.signum() == sign {
+                        min_dtz = dtz;
+                    }
+                }
+                match min_dtz {
+                    i32::MAX => Some(-1),
+                    dtz => Some(dtz),
+                }
+            }
+        }
+    }
+
+    /// Moves preserving the best tablebase result, given how many plies have been played
+    /// without zeroing. A single move is returned when it makes the fastest progress towards a
+    /// certain win, or offers the longest resistance in a lost position.
+    pub fn select_moves(
+        &self,
+        board: &Board,
+        colour: Colour,
+        moves: &[HalfMove],
+        consecutive_moves: u8,
+    ) -> Option<Vec<HalfMove>> {
+        let counter = consecutive_moves as i32;
+        let mut ranked = vec![];
+        for m in moves {
+            let mut next = board.duplicate();
+            next.execute_move(m);
+            let opponent = colour.get_opposite();
+
+            let mut dtz = match Tablebase::is_zeroing(m) {
+                true => self
+                    .probe_wdl(&next, opponent)?
+                    .get_opposite()
+                    .dtz_before_zeroing(),
+                false => match -self.probe_dtz(&next, opponent)? {
+                    dtz if dtz > 0 => dtz + 1,
+                    dtz if dtz < 0 => dtz - 1,
+                    dtz => dtz,
+                },
+            };
+            if dtz == 2 && m.is_check() && Game::select_legal_moves(&next, opponent).is_empty() {
+                dtz = 1;
+            }
+
+            // Certain wins and losses rank the same, unless the 50 moves rule is in sight
+            let rank = if dtz > 0 {
+                match dtz + counter <= 99 {
+                    true => 1000,
+                    false => 1000 - (dtz + counter),
+                }
+            } else if dtz < 0 {
+                match -dtz * 2 + counter < 100 {
+                    true => -1000,
+                    false => -1000 + (-dtz + counter),
+                }
+            } else {
+                0
+            };
+            ranked.push((rank, dtz, *m));
+        }
+
+        let best_rank = ranked.iter().map(|(r, _, _)| *r).max()?;
+        let best: Vec<(i32, HalfMove)> = ranked
+            .into_iter()
+            .filter(|(r, _, _)| *r == best_rank)
+            .map(|(_, dtz, m)| (dtz, m))
+            .collect();
+
+        // Drawing moves are left to the search
+        if best_rank == 0 {
+            return Some(best.into_iter().map(|(_, m)| m).collect());
+        }
+        let (dtz, mut play) = best.into_iter().min_by_key(|(dtz, _)| *dtz)?;
+        play.set_evaluation(match dtz > 0 {
+            true => utils::TABLEBASE_WIN - dtz as f32,
+            false => utils::TABLEBASE_LOSS - dtz as f32,
+        });
+        Some(vec![play])
+    }
+
+    fn is_zeroing(play: &HalfMove) -> bool {
+        play.is_capture() || matches!(play.get_piece(), PieceType::Pawn(_))
+    }
+
+    /// Tables don't store positions where capturing is the best option (nor where a pawn move
+    /// is, for DTZ): captures must be tried first. Also tells whether zeroing is the best move.
+    fn search_wdl(&self, board: &Board, colour: Colour, check_pawns: bool) -> Option<(Wdl, bool)> {
+        let moves = Game::select_legal_moves(board, colour);
+        let mut best = Wdl::Loss;
+        let mut count = 0;
+
+        for m in &moves {
+            if !m.is_capture() && (!check_pawns || !matches!(m.get_piece(), PieceType::Pawn(_))) {
+                continue;
+            }
+            count += 1;
+
+            let mut next = board.duplicate();
+            next.execute_move(m);
+            let (value, _) = self.search_wdl(&next, colour.get_opposite(), false)?;
+            let value = value.get_opposite();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // With only zeroing moves, the table value may be wrong
+        let is_all_moves = count > 0 && count == moves.len();
+        let value = match is_all_moves {
+            true => best,
+            false => match self.probe_table(board, colour, TableKind::Wdl, Wdl::Draw)? {
+                Lookup::Value(value) => Wdl::from_value(value),
+                Lookup::ChangeSideToMove => return None,
+            },
+        };
+
+        match best >= value {
+            true => Some((best, best > Wdl::Draw || is_all_moves)),
+            false => Some((value, false)),
+        }
+    }
+
+    fn probe_table(
+        &self,
+        board: &Board,
+        colour: Colour,
+        kind: TableKind,
+        wdl: Wdl,
+    ) -> Option<Lookup> {
+        let white = Tablebase::get_signature(board, Colour::White);
+        let black = Tablebase::get_signature(board, Colour::Black);
+
+        // Only kings
+        if white.len() + black.len() == 2 {
+            return Some(Lookup::Value(0));
+        }
+
+        let (entry, is_black_stronger) = match self.entries.get(&format!("{}v{}", white, black)) {
+            Some(entry) => (entry, false),
+            None => (self.entries.get(&format!("{}v{}", black, white))?, true),
+        };
+        let table = entry.get_table(kind)?;
+
+        let mut pieces: Vec<(usize, u8)> = vec![];
+        for c in [Colour::White, Colour::Black] {
+            for (square, piece) in board.get_pieces(c) {
+                let code = match piece.get_type() {
+                    PieceType::Pawn(_) => 1,
+                    PieceType::Knight(_) => 2,
+                    PieceType::Bishop(_) => 3,
+                    PieceType::Rook(_) => 4,
+                    PieceType::Queen(_) => 5,
+                    PieceType::King(_) => 6,
+                } + match c {
+                    Colour::White => 0,
+                    Colour::Black => 8,
+                };
+                let index = square.get_rank() as usize * 8 + square.get_file() as usize;
+                pieces.push((index, code));
+            }
+        }
+        pieces.sort();
+
+        let side_to_move = match colour {
+            Colour::White => 0,
+            Colour::Black => 1,
+        };
+        Some(table.probe(&pieces, side_to_move, is_black_stronger, wdl))
+    }
+
+    fn get_signature(board: &Board, colour: Colour) -> String {
+        let mut symbols: Vec<char> = board
+            .get_pieces(colour)
+            .values()
+            .map(|p| p.get_symbol())
+            .collect();
+        symbols.sort_by_key(|s| PIECE_ORDER.iter().position(|o| o == s));
+        symbols.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::formats::fen;
+    use crate::game::search::SearchOptions;
+
+    fn parse(text: &str) -> (Board, Colour) {
+        let position = fen::parse_fen(text).unwrap();
+        let colour = position.get_colour();
+        (position.into_board(), colour)
+    }
+
+    /// Directory of the test, removed first if a previous run left it.
+    fn create_directory(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("crust-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn missing_directory() {
+        let path = env::temp_dir().join(format!("crust-missing-{}", std::process::id()));
+        assert!(Tablebase::open(&path).is_err());
+    }
+
+    #[test]
+    fn empty_directory() {
+        let path = create_directory("empty-syzygy");
+        let tablebase = Tablebase::open(&path);
+        fs::remove_dir_all(&path).unwrap();
+
+        let tablebase = tablebase.unwrap();
+        assert_eq!(tablebase.get_max_pieces(), 0);
+        let (board, colour) = parse("8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+        assert!(!tablebase.is_probable(&board));
+        assert_eq!(tablebase.probe_wdl(&board, colour), None);
+        assert_eq!(tablebase.probe_dtz(&board, colour), None);
+
+        // The search goes on without tables
+        let mut game = Game::from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        game.set_tablebase(Some(tablebase));
+        let mut options = SearchOptions::new();
+        options.set_threads(1);
+        assert!(!game.think(&options, 1).is_empty());
+    }
+
+    #[test]
+    fn castling_rights_are_not_probed() {
+        let path = create_directory("castling-syzygy");
+        fs::write(path.join("KRvK.rtbw"), []).unwrap();
+        let tablebase = Tablebase::open(&path);
+        fs::remove_dir_all(&path).unwrap();
+
+        let tablebase = tablebase.unwrap();
+        assert_eq!(tablebase.get_max_pieces(), 3);
+        let (board, _) = parse("8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+        assert!(tablebase.is_probable(&board));
+        let (board, _) = parse("8/8/8/4k3/8/8/8/R3K3 w Q - 0 1");
+        assert!(!tablebase.is_probable(&board));
+    }
+}
@@ -1,5 +1,7 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::model::board::board::Board;
 use crate::model::board::square::Square;
@@ -12,13 +14,23 @@ use crate::model::piece::piece::Piece;
 use crate::model::piece::queen::Queen;
 use crate::model::piece::rook::Rook;
 
-pub static LOSS: f32 = f32::MIN;
-pub static DRAW: f32 = f32::MIN + 1f32;
-pub static WIN: f32 = f32::MAX;
+// Mates are scored as WIN (or LOSS) minus (or plus) the number of plies needed to deliver them,
+// and tablebase results just below them
+pub static MAX_MATE_PLIES: f32 = 256.0;
+pub static LOSS: f32 = -1_000_000.0;
+pub static TABLEBASE_LOSS: f32 = LOSS + MAX_MATE_PLIES + 1.0;
+pub static DRAW: f32 = TABLEBASE_LOSS + MAX_MATE_PLIES + 1.0;
+pub static WIN: f32 = 1_000_000.0;
+pub static TABLEBASE_WIN: f32 = WIN - MAX_MATE_PLIES - 1.0;
+pub static WIN_BY_DRAW: f32 = TABLEBASE_WIN - MAX_MATE_PLIES - 1.0;
 pub static MAX_CONSECUTIVE_MOVES: u8 = 100;
 pub static MATERIAL_FACTOR: f32 = 1.0;
 pub static ACTIVITY_FACTOR: f32 = 1.0;
 pub static PROXIMITY_FACTOR: f32 = 1.0;
+pub static TRANSPOSITION_TABLE_SIZE: usize = 1 << 16;
+pub static NULL_MOVE_REDUCTION: u8 = 2;
+pub static LATE_MOVE_INDEX: usize = 3;
+pub static QUIESCENCE_PLIES: u8 = 6;
 
 pub fn normalize_rank(rank: u8) -> u8 {
     rank + 1
@@ -28,6 +40,75 @@ pub fn normalize_file(file: u8) -> char {
     (97 + file) as char
 }
 
+pub fn is_mate(evaluation: f32) -> bool {
+    evaluation >= WIN - MAX_MATE_PLIES || evaluation <= LOSS + MAX_MATE_PLIES
+}
+
+/// Mates and tablebase results, whose value depends on the distance from the root of the search.
+pub fn is_decisive(evaluation: f32) -> bool {
+    evaluation > WIN_BY_DRAW || evaluation < DRAW
+}
+
+/// Number of moves (not plies) before the mate, positive if the player is delivering it.
+pub fn get_mate_distance(evaluation: f32) -> i32 {
+    match evaluation > 0.0 {
+        true => ((WIN - evaluation) as i32 + 1) / 2,
+        false => -((evaluation - LOSS) as i32 + 1) / 2,
+    }
+}
+
+pub fn format_evaluation(evaluation: f32) -> String {
+    if !is_decisive(evaluation) {
+        format!("{:07.4}", evaluation)
+    } else if !is_mate(evaluation) {
+        match evaluation > 0.0 {
+            true => String::from("tablebase win"),
+            false => String::from("tablebase loss"),
+        }
+    } else {
+        match get_mate_distance(evaluation) {
+            moves if moves > 0 => format!("mate in {}", moves),
+            moves => format!("mated in {}", -moves),
+        }
+    }
+}
+
+/// Clock time as "h:mm:ss", or "m:ss.s" under one hour; a fallen flag shows as "0:00.0".
+pub fn format_seconds(seconds: f32) -> String {
+    let seconds = seconds.max(0.0);
+    let minutes = (seconds / 60.0) as u32;
+    match minutes >= 60 {
+        true => format!(
+            "{}:{:02}:{:02}",
+            minutes / 60,
+            minutes % 60,
+            seconds as u32 % 60
+        ),
+        false => format!("{}:{:04.1}", minutes, seconds - 60.0 * minutes as f32),
+    }
+}
+
+/// Pseudo-random number (xorshift64*), seeded once per thread from the system time.
+pub fn random_u64() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0x2545F4914F6CDD1D, |d| d.as_nanos() as u64)
+                | 1,
+        );
+    }
+
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545F4914F6CDD1D)
+    })
+}
+
 pub fn hash_position(board: &Board, colour: Colour) -> u64 {
     let mut state = DefaultHasher::new();
     board.hash(&mut state);
@@ -0,0 +1,54 @@
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::utils;
+use crate::variant::variant::{Outcome, Variant};
+
+/// Losing chess: captures are compulsory, the king is an ordinary piece, and the player who
+/// loses all their pieces or can't move wins.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Antichess;
+
+impl Variant for Antichess {
+    fn get_name(&self) -> &'static str {
+        "Antichess"
+    }
+
+    fn get_key(&self) -> &'static str {
+        "antichess"
+    }
+
+    fn get_start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    }
+
+    fn is_royal(&self, _colour: Colour) -> bool {
+        false
+    }
+
+    fn select_moves(&self, _board: &Board, _colour: Colour, moves: Vec<HalfMove>) -> Vec<HalfMove> {
+        match moves.iter().any(|m| m.is_capture()) {
+            true => moves.into_iter().filter(|m| m.is_capture()).collect(),
+            false => moves,
+        }
+    }
+
+    fn get_outcome(&self, board: &Board, colour: Colour) -> Option<Outcome> {
+        board
+            .get_pieces(colour)
+            .is_empty()
+            .then(|| Outcome::Win(colour, format!("{:?} lost all their pieces", colour)))
+    }
+
+    fn get_outcome_without_moves(&self, _board: &Board, colour: Colour) -> Outcome {
+        Outcome::Win(colour, format!("{:?} can't move", colour))
+    }
+
+    fn evaluate(&self, board: &Board, colour: Colour) -> f32 {
+        // Fewer pieces than the enemy is the goal
+        let allies = board.get_pieces(colour).len().max(1) as f32;
+        let enemies = board.get_pieces(colour.get_opposite()).len() as f32;
+
+        (enemies / allies) * utils::MATERIAL_FACTOR
+    }
+}
@@ -0,0 +1,87 @@
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::variant::variant::{Outcome, Variant};
+
+/// Captures explode, removing the capturing piece and every piece but pawns around the target
+/// square. Exploding the enemy king wins, and adjacent kings can't check each other.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Atomic;
+
+impl Atomic {
+    fn are_kings_adjacent(board: &Board) -> bool {
+        match (
+            board.get_king_square(Colour::White),
+            board.get_king_square(Colour::Black),
+        ) {
+            (Some(white), Some(black)) => {
+                white.get_rank().abs_diff(black.get_rank()) <= 1
+                    && white.get_file().abs_diff(black.get_file()) <= 1
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Variant for Atomic {
+    fn get_name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn get_key(&self) -> &'static str {
+        "atomic"
+    }
+
+    fn is_in_check(&self, board: &Board, colour: Colour) -> bool {
+        !Atomic::are_kings_adjacent(board) && board.is_king_attacked(colour)
+    }
+
+    /// Moves keeping the king of the player on the board, and out of check unless they explode
+    /// the enemy king.
+    fn select_moves(&self, board: &Board, colour: Colour, moves: Vec<HalfMove>) -> Vec<HalfMove> {
+        moves
+            .into_iter()
+            .filter(|m| {
+                let mut board = board.duplicate();
+                board.execute_move(m);
+                board.get_king_square(colour).is_some()
+                    && (board.get_king_square(colour.get_opposite()).is_none()
+                        || !board.is_under_check(colour))
+            })
+            .collect()
+    }
+
+    fn complete_move(&self, board: &mut Board, play: &HalfMove) {
+        if !play.is_capture() {
+            return;
+        }
+        let target = play.get_to();
+        board.remove_piece(&target);
+        for rank in -1..=1 {
+            for file in -1..=1 {
+                let square = board
+                    .get_square(
+                        target.get_rank() as i8 + rank,
+                        target.get_file() as i8 + file,
+                    )
+                    .copied();
+                let Some(square) = square else {
+                    continue;
+                };
+                if board
+                    .get_piece(square, None)
+                    .is_some_and(|p| p.get_symbol() != 'P')
+                {
+                    board.remove_piece(&square);
+                }
+            }
+        }
+    }
+
+    fn get_outcome(&self, board: &Board, _colour: Colour) -> Option<Outcome> {
+        [Colour::White, Colour::Black]
+            .into_iter()
+            .find(|c| board.get_king_square(*c).is_none())
+            .map(|c| Outcome::Win(c.get_opposite(), format!("{:?} king exploded", c)))
+    }
+}
@@ -0,0 +1,40 @@
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::variant::variant::{Outcome, Variant};
+
+/// White has no king but a horde of pawns, and loses once they are all captured. Its pawns on
+/// the first rank may step twice as well.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Horde;
+
+impl Variant for Horde {
+    fn get_name(&self) -> &'static str {
+        "Horde"
+    }
+
+    fn get_key(&self) -> &'static str {
+        "horde"
+    }
+
+    fn get_start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+    }
+
+    fn is_royal(&self, colour: Colour) -> bool {
+        colour == Colour::Black
+    }
+
+    fn is_pawn_start(&self, colour: Colour, rank: u8) -> bool {
+        match colour {
+            Colour::White => rank <= 1,
+            Colour::Black => rank == 6,
+        }
+    }
+
+    fn get_outcome(&self, board: &Board, _colour: Colour) -> Option<Outcome> {
+        board
+            .get_pieces(Colour::White)
+            .is_empty()
+            .then(|| Outcome::Win(Colour::Black, String::from("White lost all their pieces")))
+    }
+}
@@ -0,0 +1,53 @@
+use crate::model::board::board::Board;
+use crate::model::board::square::Square;
+use crate::model::piece::color::Colour;
+use crate::variant::standard::Standard;
+use crate::variant::variant::{Outcome, Variant};
+
+const CENTRE_FACTOR: f32 = 0.5;
+
+/// Standard chess, also won by bringing the king to one of the four central squares.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KingOfTheHill;
+
+impl KingOfTheHill {
+    /// King moves needed to reach the centre, 0 on d4, e4, d5 and e5.
+    fn get_distance(square: &Square) -> u8 {
+        let distance = |coordinate: u8| match coordinate {
+            0..=3 => 3 - coordinate,
+            _ => coordinate - 4,
+        };
+        distance(square.get_rank()).max(distance(square.get_file()))
+    }
+}
+
+impl Variant for KingOfTheHill {
+    fn get_name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn get_key(&self) -> &'static str {
+        "kingofthehill"
+    }
+
+    fn get_outcome(&self, board: &Board, _colour: Colour) -> Option<Outcome> {
+        [Colour::White, Colour::Black].into_iter().find_map(|c| {
+            board
+                .get_king_square(c)
+                .filter(|s| KingOfTheHill::get_distance(s) == 0)
+                .map(|_| Outcome::Win(c, format!("{:?} king reached the centre", c)))
+        })
+    }
+
+    fn evaluate(&self, board: &Board, colour: Colour) -> f32 {
+        // Kings closer to the centre than the enemy one are worth more
+        let distance = |c| {
+            board
+                .get_king_square(c)
+                .map_or(0.0, |s| KingOfTheHill::get_distance(&s) as f32)
+        };
+        let centre = distance(colour.get_opposite()) - distance(colour);
+
+        Standard.evaluate(board, colour) + centre * CENTRE_FACTOR
+    }
+}
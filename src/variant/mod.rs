@@ -0,0 +1,8 @@
+pub mod antichess;
+pub mod atomic;
+pub mod horde;
+pub mod king_of_the_hill;
+pub mod standard;
+pub mod three_check;
+#[allow(clippy::module_inception)]
+pub mod variant;
@@ -0,0 +1,15 @@
+use crate::variant::variant::Variant;
+
+/// Standard chess, also played from the Chess960 starting positions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Standard;
+
+impl Variant for Standard {
+    fn get_name(&self) -> &'static str {
+        "Standard"
+    }
+
+    fn get_key(&self) -> &'static str {
+        "standard"
+    }
+}
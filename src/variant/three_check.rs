@@ -0,0 +1,44 @@
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::model::piece::piece::Piece;
+use crate::variant::standard::Standard;
+use crate::variant::variant::{Outcome, Variant};
+
+pub const CHECKS: u8 = 3;
+const CHECK_FACTOR: f32 = 1.0;
+
+/// Standard chess, also won by giving check three times.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn get_name(&self) -> &'static str {
+        "Three-check"
+    }
+
+    fn get_key(&self) -> &'static str {
+        "3check"
+    }
+
+    fn complete_move(&self, board: &mut Board, play: &HalfMove) {
+        let colour = play.get_piece().get_colour();
+        if board.is_under_check(colour.get_opposite()) {
+            board.add_check(colour);
+        }
+    }
+
+    fn get_outcome(&self, board: &Board, _colour: Colour) -> Option<Outcome> {
+        [Colour::White, Colour::Black]
+            .into_iter()
+            .find(|c| board.get_checks(*c) >= CHECKS)
+            .map(|c| Outcome::Win(c, format!("{:?} gave {} checks", c, CHECKS)))
+    }
+
+    fn evaluate(&self, board: &Board, colour: Colour) -> f32 {
+        let checks =
+            board.get_checks(colour) as f32 - board.get_checks(colour.get_opposite()) as f32;
+
+        Standard.evaluate(board, colour) + checks * CHECK_FACTOR
+    }
+}
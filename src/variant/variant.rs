@@ -0,0 +1,183 @@
+use std::fmt::{Debug, Display, Formatter};
+
+use anyhow::Error;
+use enum_dispatch::enum_dispatch;
+
+use crate::formats::fen::START_FEN;
+use crate::game::play::HalfMove;
+use crate::model::board::board::Board;
+use crate::model::piece::color::Colour;
+use crate::utils;
+use crate::variant::antichess::Antichess;
+use crate::variant::atomic::Atomic;
+use crate::variant::horde::Horde;
+use crate::variant::king_of_the_hill::KingOfTheHill;
+use crate::variant::standard::Standard;
+use crate::variant::three_check::ThreeCheck;
+
+#[enum_dispatch(Variant)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VariantType {
+    Standard(Standard),
+    KingOfTheHill(KingOfTheHill),
+    ThreeCheck(ThreeCheck),
+    Antichess(Antichess),
+    Atomic(Atomic),
+    Horde(Horde),
+}
+
+impl VariantType {
+    /// All the variants, standard chess first.
+    pub fn get_all() -> [VariantType; 6] {
+        [
+            Standard.into(),
+            KingOfTheHill.into(),
+            ThreeCheck.into(),
+            Antichess.into(),
+            Atomic.into(),
+            Horde.into(),
+        ]
+    }
+
+    /// Variant from its key or its name, in any case, e.g. "3check" or "Three-check". Chess960
+    /// is played with the rules of standard chess.
+    pub fn parse(text: &str) -> Result<VariantType, Error> {
+        let key: String = text
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        if matches!(
+            key.as_str(),
+            "chess" | "normal" | "chess960" | "fischerandom" | "fromposition"
+        ) {
+            return Ok(Standard.into());
+        }
+        if matches!(key.as_str(), "giveaway" | "losingchess") {
+            return Ok(Antichess.into());
+        }
+        VariantType::get_all()
+            .into_iter()
+            .find(|v| {
+                v.get_key() == key || v.get_name().replace([' ', '-'], "").to_lowercase() == key
+            })
+            .ok_or(Error::msg(format!(
+                "Unknown variant {}, e.g. {}",
+                text,
+                VariantType::get_all().map(|v| v.get_key()).join(", ")
+            )))
+    }
+
+    pub fn is_standard(&self) -> bool {
+        matches!(self, VariantType::Standard(_))
+    }
+}
+
+impl Default for VariantType {
+    fn default() -> VariantType {
+        Standard.into()
+    }
+}
+
+impl Display for VariantType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.get_name())
+    }
+}
+
+/// How a game ends by the rules of its variant, with the reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Colour, String),
+    Draw(String),
+}
+
+impl Outcome {
+    /// Winner of the game, `None` for a draw.
+    pub fn get_winner(&self) -> Option<Colour> {
+        match self {
+            Outcome::Win(colour, _) => Some(*colour),
+            Outcome::Draw(_) => None,
+        }
+    }
+
+    pub fn get_reason(&self) -> &str {
+        match self {
+            Outcome::Win(_, reason) | Outcome::Draw(reason) => reason,
+        }
+    }
+}
+
+/// Rules of a game of chess. The default methods are those of standard chess, each variant
+/// overriding the starting position, the legal moves or the way the game ends.
+#[enum_dispatch]
+pub trait Variant: Debug + Send + Sync {
+    /// Name of the variant, as in the Variant tag of PGN games, e.g. "King of the Hill".
+    fn get_name(&self) -> &'static str;
+
+    /// Name of the variant in the UCI_Variant option and on the command line, e.g. "kingofthehill".
+    fn get_key(&self) -> &'static str;
+
+    fn get_start_fen(&self) -> &'static str {
+        START_FEN
+    }
+
+    /// Whether the king of the player must be on the board and can't be left in check.
+    fn is_royal(&self, _colour: Colour) -> bool {
+        true
+    }
+
+    /// Whether a pawn of the player on this rank has not moved yet, and may step twice.
+    fn is_pawn_start(&self, colour: Colour, rank: u8) -> bool {
+        match colour {
+            Colour::White => rank == 1,
+            Colour::Black => rank == 6,
+        }
+    }
+
+    fn is_in_check(&self, board: &Board, colour: Colour) -> bool {
+        self.is_royal(colour) && board.is_king_attacked(colour)
+    }
+
+    /// Moves allowed among the ones the pieces of the player can make: those which don't leave
+    /// its king in check.
+    fn select_moves(&self, board: &Board, colour: Colour, moves: Vec<HalfMove>) -> Vec<HalfMove> {
+        moves
+            .into_iter()
+            .filter(|m| {
+                let mut board = board.duplicate();
+                board.execute_move(m);
+                !board.is_under_check(colour)
+            })
+            .collect()
+    }
+
+    /// Changes to the board once the piece has moved, e.g. an explosion.
+    fn complete_move(&self, _board: &mut Board, _play: &HalfMove) {}
+
+    /// Result decided by the position before the player moves, whatever its legal moves.
+    fn get_outcome(&self, _board: &Board, _colour: Colour) -> Option<Outcome> {
+        None
+    }
+
+    /// Result when the player to move has no legal move: checkmate or stalemate.
+    fn get_outcome_without_moves(&self, board: &Board, colour: Colour) -> Outcome {
+        match board.is_under_check(colour) {
+            true => Outcome::Win(colour.get_opposite(), format!("{:?} is checkmated", colour)),
+            false => Outcome::Draw(format!("{:?} is stalemated", colour)),
+        }
+    }
+
+    /// Static evaluation of the position for the player.
+    fn evaluate(&self, board: &Board, colour: Colour) -> f32 {
+        let material = board.evaluate_material(colour);
+        let activity = board.evaluate_activity(colour);
+        let proximity = board.evaluate_proximity(colour);
+
+        // TODO other evaluations (king safety, ...)
+
+        (material * utils::MATERIAL_FACTOR)
+            + (activity * utils::ACTIVITY_FACTOR)
+            + (proximity + utils::PROXIMITY_FACTOR)
+    }
+}